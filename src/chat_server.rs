@@ -53,7 +53,7 @@ pub async fn start_chat_server(
 
 #[cfg(feature = "web")]
 async fn index_handler() -> impl IntoResponse {
-    let html = include_str!("../ui/chat-graph.html");
+    let html = include_str!("../static/chat-graph.html");
     Html(html)
 }
 
@@ -9,4 +9,4 @@ pub mod graph_exporter;
 pub use analyzer::ConversationAnalyzer;
 pub use topic::TopicDetector;
 pub use prompt_history::PromptHistory;
-// pub use graph_exporter::GraphData; // 未使用
+pub use graph_exporter::GraphData;
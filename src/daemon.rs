@@ -0,0 +1,500 @@
+// Background daemon
+// ワークスペースのグラフ・検索インデックスを常駐プロセスで保持し、
+// Unix ソケット経由で CLI / MCP サーバー / Web UI に提供する
+//
+// Protocol: one JSON request per line, one JSON response per line.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::graph::CodeGraph;
+use crate::progress::Progress;
+use crate::scanner::Scanner;
+use crate::search::graph_search::GraphSearchResult;
+
+/// Socket of the daemon serving `directory`
+pub fn socket_path(directory: &Path) -> PathBuf {
+    directory.join(".index-chan").join("daemon.sock")
+}
+
+/// Request sent to the daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "snake_case")]
+pub enum DaemonRequest {
+    Status,
+    Graph,
+    Search {
+        query: String,
+        top_k: usize,
+        graph_depth: usize,
+        #[serde(default)]
+        filter_generic: bool,
+        #[serde(default)]
+        semantic: bool,
+    },
+    /// Re-extract these files now instead of waiting for the file watcher
+    Refresh { files: Vec<PathBuf> },
+    /// Re-check every file against its content hash, then send the graph
+    Sync,
+    Shutdown,
+}
+
+/// Response line: exactly one of `result` / `error` is set
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonResponse {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub directory: PathBuf,
+    /// Incremented every time the graph changes
+    pub generation: u64,
+    pub nodes: usize,
+    pub edges: usize,
+    pub dead_code: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    pub generation: u64,
+    pub graph: CodeGraph,
+}
+
+/// Connection to a running daemon
+pub struct DaemonClient {
+    #[cfg(unix)]
+    reader: std::io::BufReader<std::os::unix::net::UnixStream>,
+}
+
+impl DaemonClient {
+    /// Connect to the daemon for `directory`; `None` when no daemon is running
+    pub fn connect(directory: &Path) -> Option<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::net::UnixStream;
+            use std::time::Duration;
+
+            let socket = socket_path(directory);
+            if !socket.exists() {
+                return None;
+            }
+            let stream = UnixStream::connect(&socket).ok()?;
+            // Semantic search may build embeddings on first use
+            stream.set_read_timeout(Some(Duration::from_secs(300))).ok()?;
+            Some(Self {
+                reader: std::io::BufReader::new(stream),
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = directory;
+            None
+        }
+    }
+
+    /// Send a request and wait for its result
+    pub fn request(&mut self, request: &DaemonRequest) -> Result<Value> {
+        #[cfg(unix)]
+        {
+            use std::io::{BufRead, Write};
+
+            let mut line = serde_json::to_string(request)?;
+            line.push('\n');
+            self.reader.get_mut().write_all(line.as_bytes())?;
+
+            let mut response = String::new();
+            if self.reader.read_line(&mut response)? == 0 {
+                return Err(anyhow!("Daemon closed the connection"));
+            }
+            let response: DaemonResponse = serde_json::from_str(&response)?;
+            match (response.result, response.error) {
+                (_, Some(error)) => Err(anyhow!(error)),
+                (Some(result), None) => Ok(result),
+                (None, None) => Ok(Value::Null),
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = request;
+            Err(anyhow!("Daemon is only supported on Unix"))
+        }
+    }
+
+    pub fn status(&mut self) -> Result<DaemonStatus> {
+        Ok(serde_json::from_value(self.request(&DaemonRequest::Status)?)?)
+    }
+
+    pub fn graph(&mut self) -> Result<GraphSnapshot> {
+        Ok(serde_json::from_value(self.request(&DaemonRequest::Graph)?)?)
+    }
+
+    pub fn search(
+        &mut self,
+        query: &str,
+        top_k: usize,
+        graph_depth: usize,
+        filter_generic: bool,
+        semantic: bool,
+    ) -> Result<Vec<GraphSearchResult>> {
+        let request = DaemonRequest::Search {
+            query: query.to_string(),
+            top_k,
+            graph_depth,
+            filter_generic,
+            semantic,
+        };
+        Ok(serde_json::from_value(self.request(&request)?)?)
+    }
+
+    /// Tell the daemon these files changed; returns the new status
+    pub fn refresh(&mut self, files: Vec<PathBuf>) -> Result<DaemonStatus> {
+        Ok(serde_json::from_value(self.request(&DaemonRequest::Refresh { files })?)?)
+    }
+
+    /// The graph after the daemon caught up with every edit on disk, including
+    /// ones still inside its debounce window
+    pub fn sync(&mut self) -> Result<GraphSnapshot> {
+        Ok(serde_json::from_value(self.request(&DaemonRequest::Sync)?)?)
+    }
+}
+
+/// Graph of `directory`: from the running daemon if there is one, otherwise scanned
+pub fn load_graph(directory: &Path) -> Result<CodeGraph> {
+    load_graph_with_progress(directory, Progress::default())
+}
+
+/// Graph of `directory` matching the files on disk, for commands that edit them by line range.
+/// A daemon's snapshot can lag behind recent edits, so it re-checks the files first.
+pub fn load_current_graph(directory: &Path) -> Result<CodeGraph> {
+    if let Some(mut client) = DaemonClient::connect(directory) {
+        let snapshot = client.sync()?;
+        eprintln!("📡 Using graph from daemon (generation {}, synced)", snapshot.generation);
+        return Ok(snapshot.graph);
+    }
+    Scanner::new()?.scan_directory(directory)
+}
+
+/// [`load_graph`], reporting scan progress to `progress` (and stopping when it is cancelled)
+pub fn load_graph_with_progress(directory: &Path, progress: Progress) -> Result<CodeGraph> {
+    if let Some(snapshot) = DaemonClient::connect(directory).and_then(|mut c| c.graph().ok()) {
+        eprintln!("📡 Using graph from daemon (generation {})", snapshot.generation);
+        return Ok(snapshot.graph);
+    }
+    let mut scanner = Scanner::new()?;
+    scanner.set_progress(progress);
+    scanner.scan_directory(directory)
+}
+
+#[cfg(all(unix, feature = "db"))]
+pub use server::run;
+
+#[cfg(all(unix, feature = "db"))]
+mod server {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    use crate::search::GraphSearcher;
+    use crate::watcher::{event_paths, event_stream, ProjectWatcher};
+
+    #[cfg(feature = "semantic-search")]
+    use crate::embedding_cache::EmbeddingCache;
+
+    /// What the daemon sends for a [`GraphSnapshot`], borrowing its graph instead of copying it
+    #[derive(Serialize)]
+    struct GraphSnapshotRef<'a> {
+        generation: u64,
+        graph: &'a CodeGraph,
+    }
+
+    /// What requests read, replaced as a whole when the graph changes; its lock is only
+    /// held to copy or replace it
+    #[derive(Clone)]
+    struct Snapshot {
+        generation: u64,
+        graph: Arc<CodeGraph>,
+        searcher: Arc<GraphSearcher>,
+        dead_code: usize,
+        /// Built on first semantic search, dropped when the graph changes
+        #[cfg(feature = "semantic-search")]
+        embeddings: Option<Arc<EmbeddingCache>>,
+    }
+
+    impl Snapshot {
+        fn of(project: &ProjectWatcher, generation: u64) -> Self {
+            let graph = project.shared_graph();
+            Self {
+                generation,
+                searcher: Arc::new(GraphSearcher::new(Arc::clone(&graph))),
+                graph,
+                dead_code: project.dead_code_count(),
+                #[cfg(feature = "semantic-search")]
+                embeddings: None,
+            }
+        }
+    }
+
+    struct DaemonState {
+        directory: PathBuf,
+        /// Held while the graph is updated, so updates apply one at a time without
+        /// blocking requests that only read
+        project: Mutex<ProjectWatcher>,
+        snapshot: Mutex<Snapshot>,
+    }
+
+    impl DaemonState {
+        fn new(project: ProjectWatcher) -> Self {
+            Self {
+                directory: project.directory().to_path_buf(),
+                snapshot: Mutex::new(Snapshot::of(&project, 0)),
+                project: Mutex::new(project),
+            }
+        }
+
+        fn snapshot(&self) -> Snapshot {
+            self.snapshot.lock().unwrap().clone()
+        }
+
+        /// Publish the graph `project` holds now
+        fn updated(&self, project: &ProjectWatcher) {
+            let mut snapshot = self.snapshot.lock().unwrap();
+            *snapshot = Snapshot::of(project, snapshot.generation + 1);
+        }
+
+        /// Re-extract the files among `paths` that the graph depends on
+        fn refresh(&self, paths: &[PathBuf]) -> Result<()> {
+            let mut project = self.project.lock().unwrap();
+            if let Some(changes) = project.classify(paths) {
+                project.apply(&changes)?;
+                self.updated(&project);
+            }
+            Ok(())
+        }
+
+        fn status(&self) -> DaemonStatus {
+            let snapshot = self.snapshot();
+            DaemonStatus {
+                directory: self.directory.clone(),
+                generation: snapshot.generation,
+                nodes: snapshot.graph.nodes.len(),
+                edges: snapshot.graph.edges.len(),
+                dead_code: snapshot.dead_code,
+            }
+        }
+    }
+
+    /// Serve `directory` until a `shutdown` request
+    pub fn run(directory: &Path, debounce: Duration) -> Result<()> {
+        // Absolute paths so clients in other working directories can open the files
+        let directory = directory.canonicalize()?;
+        let socket = socket_path(&directory);
+        if socket.exists() {
+            if UnixStream::connect(&socket).is_ok() {
+                return Err(anyhow!("A daemon is already running for {}", directory.display()));
+            }
+            std::fs::remove_file(&socket)?; // left behind by a killed daemon
+        }
+
+        let state = Arc::new(DaemonState::new(ProjectWatcher::new(&directory)?));
+
+        let (_debouncer, events) = event_stream(&directory, debounce)?;
+        let watch_state = Arc::clone(&state);
+        std::thread::spawn(move || {
+            for result in events {
+                if let Err(e) = watch_state.refresh(&event_paths(result)) {
+                    eprintln!("⚠️  Update failed: {}", e);
+                }
+            }
+        });
+
+        std::fs::create_dir_all(socket.parent().unwrap_or(&directory))?;
+        let listener = UnixListener::bind(&socket)?;
+        println!("🛰️  デーモン起動: {}", socket.display());
+
+        let stopping = Arc::new(AtomicBool::new(false));
+        for stream in listener.incoming() {
+            if stopping.load(Ordering::SeqCst) {
+                break;
+            }
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let state = Arc::clone(&state);
+            let stopping = Arc::clone(&stopping);
+            let socket = socket.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = serve_connection(stream, &state, &stopping, &socket) {
+                    eprintln!("⚠️  Connection error: {}", e);
+                }
+            });
+        }
+
+        // The debouncer drops on return, which ends the watch thread
+        drop(listener);
+        let _ = std::fs::remove_file(&socket);
+        println!("👋 デーモン停止");
+        Ok(())
+    }
+
+    fn serve_connection(
+        stream: UnixStream,
+        state: &DaemonState,
+        stopping: &AtomicBool,
+        socket: &Path,
+    ) -> Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let request = serde_json::from_str::<DaemonRequest>(&line);
+            let shutdown = matches!(request, Ok(DaemonRequest::Shutdown));
+            let response = match request {
+                Ok(request) => match handle(&request, state) {
+                    Ok(result) => DaemonResponse { result: Some(result), error: None },
+                    Err(e) => DaemonResponse { result: None, error: Some(e.to_string()) },
+                },
+                Err(e) => DaemonResponse {
+                    result: None,
+                    error: Some(format!("Invalid request: {}", e)),
+                },
+            };
+            let mut out = serde_json::to_string(&response)?;
+            out.push('\n');
+            writer.write_all(out.as_bytes())?;
+
+            if shutdown {
+                // Wake the accept loop so it sees the flag and returns
+                stopping.store(true, Ordering::SeqCst);
+                let _ = UnixStream::connect(socket);
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Requests work on a snapshot taken under a short lock: embedding builds and graph
+    /// serialization never hold up the watcher or other clients
+    fn handle(request: &DaemonRequest, state: &DaemonState) -> Result<Value> {
+        match request {
+            DaemonRequest::Status | DaemonRequest::Shutdown => Ok(serde_json::to_value(state.status())?),
+            DaemonRequest::Graph => {
+                let snapshot = state.snapshot();
+                Ok(serde_json::to_value(GraphSnapshotRef {
+                    generation: snapshot.generation,
+                    graph: &snapshot.graph,
+                })?)
+            }
+            DaemonRequest::Search {
+                query,
+                top_k,
+                graph_depth,
+                filter_generic,
+                semantic,
+            } => {
+                let snapshot = state.snapshot();
+                #[cfg(feature = "semantic-search")]
+                if *semantic {
+                    let embeddings = match &snapshot.embeddings {
+                        Some(embeddings) => Arc::clone(embeddings),
+                        None => {
+                            let built = Arc::new(EmbeddingCache::get_or_create(&snapshot.graph, &state.directory)?);
+                            // Kept unless the graph changed while they were built
+                            let mut current = state.snapshot.lock().unwrap();
+                            if current.generation == snapshot.generation {
+                                current.embeddings = Some(Arc::clone(&built));
+                            }
+                            built
+                        }
+                    };
+                    let results = snapshot.searcher.search_semantic(query, &embeddings.embeddings, *top_k, *graph_depth);
+                    return Ok(serde_json::to_value(results)?);
+                }
+                #[cfg(not(feature = "semantic-search"))]
+                let _ = semantic;
+                let results = snapshot
+                    .searcher
+                    .search_with_graph_filtered(query, *top_k, *graph_depth, *filter_generic);
+                Ok(serde_json::to_value(results)?)
+            }
+            DaemonRequest::Sync => {
+                {
+                    let mut project = state.project.lock().unwrap();
+                    if project.sync()? {
+                        state.updated(&project);
+                    }
+                }
+                let snapshot = state.snapshot();
+                Ok(serde_json::to_value(GraphSnapshotRef {
+                    generation: snapshot.generation,
+                    graph: &snapshot.graph,
+                })?)
+            }
+            DaemonRequest::Refresh { files } => {
+                state.refresh(files)?;
+                Ok(serde_json::to_value(state.status())?)
+            }
+        }
+    }
+}
+
+#[cfg(all(test, unix, feature = "db"))]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_project;
+
+    #[test]
+    fn test_client_talks_to_daemon() {
+        let temp = temp_project();
+        let root = temp.path();
+        std::fs::write(root.join("app.ts"), "function main() {\n  helper();\n}\nfunction helper() {}\n").unwrap();
+
+        let daemon_root = root.to_path_buf();
+        // A long debounce: only explicit refresh / sync can see the edits below
+        let daemon = std::thread::spawn(move || run(&daemon_root, std::time::Duration::from_secs(30)));
+
+        let mut client = None;
+        for _ in 0..100 {
+            client = DaemonClient::connect(root);
+            if client.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        let mut client = client.expect("daemon did not start");
+
+        let status = client.status().unwrap();
+        assert_eq!(status.nodes, 2);
+        assert_eq!(status.dead_code, 0);
+
+        // An explicit refresh sees the edit without waiting for the watcher
+        std::fs::write(root.join("app.ts"), "function main() {\n}\nfunction helper() {}\n").unwrap();
+        let refreshed = client.refresh(vec![PathBuf::from("app.ts")]).unwrap();
+        assert!(refreshed.generation > status.generation);
+        assert_eq!(refreshed.dead_code, 1);
+
+        let snapshot = client.graph().unwrap();
+        assert_eq!(snapshot.graph.nodes_by_name("helper").count(), 1);
+        let results = client.search("helper", 3, 1, false, false).unwrap();
+        assert!(results.iter().any(|r| r.metadata.function_name == "helper"));
+
+        // Sync re-checks the files itself: the moved helper is found at its new line
+        std::fs::write(root.join("app.ts"), "function main() {\n}\n\n\nfunction helper() {}\n").unwrap();
+        let graph = load_current_graph(root).unwrap();
+        assert_eq!(graph.nodes_by_name("helper").next().unwrap().line_range.0, 5);
+
+        client.request(&DaemonRequest::Shutdown).unwrap();
+        daemon.join().unwrap().unwrap();
+        assert!(!socket_path(root).exists());
+    }
+}
@@ -3,9 +3,20 @@
 use crate::graph::{CodeGraph, CodeNode, DependencyEdge, EdgeType, NodeType};
 use anyhow::{Context, Result};
 use sqlx::{sqlite::SqlitePoolOptions, Pool, Row, Sqlite};
+use std::collections::HashMap;
 use std::path::Path;
 use std::str::FromStr;
 
+/// Row counts changed by [`GraphDB::sync_graph`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncStats {
+    pub nodes_added: usize,
+    pub nodes_updated: usize,
+    pub nodes_removed: usize,
+    pub edges_added: usize,
+    pub edges_removed: usize,
+}
+
 pub struct GraphDB {
     pool: Pool<Sqlite>,
 }
@@ -42,7 +53,11 @@ impl GraphDB {
                 start_line INTEGER NOT NULL,
                 end_line INTEGER NOT NULL,
                 is_exported BOOLEAN NOT NULL,
-                is_used BOOLEAN NOT NULL
+                is_used BOOLEAN NOT NULL,
+                signature TEXT NOT NULL DEFAULT '',
+                qualified_name TEXT NOT NULL DEFAULT '',
+                symbol_id TEXT NOT NULL DEFAULT '',
+                package TEXT NOT NULL DEFAULT ''
             );
             
             CREATE TABLE IF NOT EXISTS edges (
@@ -64,6 +79,27 @@ impl GraphDB {
         .await
         .context("Failed to initialize database schema")?;
 
+        // Databases created before symbol ids / packages lack these columns
+        let columns: Vec<String> = sqlx::query("PRAGMA table_info(nodes)")
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .collect();
+        for column in ["signature", "qualified_name", "symbol_id", "package"] {
+            if !columns.iter().any(|c| c == column) {
+                sqlx::query(&format!(
+                    "ALTER TABLE nodes ADD COLUMN {} TEXT NOT NULL DEFAULT ''",
+                    column
+                ))
+                .execute(&self.pool)
+                .await?;
+            }
+        }
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_nodes_symbol ON nodes(symbol_id)")
+            .execute(&self.pool)
+            .await?;
+
         Ok(())
     }
 
@@ -79,8 +115,9 @@ impl GraphDB {
             let node_type_str = format!("{:?}", node.node_type);
             sqlx::query(
                 r#"
-                INSERT INTO nodes (id, name, type, file_path, start_line, end_line, is_exported, is_used)
-                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                INSERT INTO nodes (id, name, type, file_path, start_line, end_line, is_exported, is_used,
+                                   signature, qualified_name, symbol_id, package)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
                 "#
             )
             .bind(node.id as i64)
@@ -91,6 +128,10 @@ impl GraphDB {
             .bind(node.line_range.1 as i64)
             .bind(node.is_exported)
             .bind(node.is_used)
+            .bind(&node.signature)
+            .bind(&node.qualified_name)
+            .bind(&node.symbol_id)
+            .bind(&node.package)
             .execute(&mut *tx)
             .await?;
         }
@@ -115,6 +156,185 @@ impl GraphDB {
         Ok(())
     }
 
+    /// Bring the database in line with `graph`, touching only rows that changed.
+    /// Nodes are matched by symbol id (database ids are kept for surviving nodes);
+    /// databases without symbol ids are rewritten with `save_graph`.
+    pub async fn sync_graph(&self, graph: &CodeGraph) -> Result<SyncStats> {
+        let mut tx = self.pool.begin().await?;
+
+        let rows = sqlx::query(
+            "SELECT id, symbol_id, name, type, file_path, start_line, end_line, is_exported, is_used, signature, qualified_name, package FROM nodes",
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        let has_symbol_ids = rows.iter().all(|row| !row.get::<String, _>("symbol_id").is_empty())
+            && graph.nodes.values().all(|node| !node.symbol_id.is_empty());
+        if !has_symbol_ids {
+            drop(tx);
+            self.save_graph(graph).await?;
+            return Ok(SyncStats {
+                nodes_added: graph.nodes.len(),
+                nodes_removed: rows.len(),
+                edges_added: graph.edges.len(),
+                ..Default::default()
+            });
+        }
+
+        let row_key = |node: &CodeNode| {
+            (
+                node.name.clone(),
+                format!("{:?}", node.node_type),
+                node.file_path.display().to_string(),
+                node.line_range.0 as i64,
+                node.line_range.1 as i64,
+                node.is_exported,
+                node.is_used,
+                node.signature.clone(),
+                node.qualified_name.clone(),
+                node.package.clone(),
+            )
+        };
+        let mut existing: HashMap<String, (i64, _)> = HashMap::new();
+        for row in &rows {
+            let key = (
+                row.get::<String, _>("name"),
+                row.get::<String, _>("type"),
+                row.get::<String, _>("file_path"),
+                row.get::<i64, _>("start_line"),
+                row.get::<i64, _>("end_line"),
+                row.get::<bool, _>("is_exported"),
+                row.get::<bool, _>("is_used"),
+                row.get::<String, _>("signature"),
+                row.get::<String, _>("qualified_name"),
+                row.get::<String, _>("package"),
+            );
+            existing.insert(row.get("symbol_id"), (row.get("id"), key));
+        }
+
+        let mut stats = SyncStats::default();
+        let mut next_db_id = existing.values().map(|(id, _)| *id).max().unwrap_or(-1) + 1;
+        let mut db_ids: HashMap<usize, i64> = HashMap::new();
+
+        // Nodes, in id order so new rows get ids in scan order
+        let mut nodes: Vec<&CodeNode> = graph.nodes.values().collect();
+        nodes.sort_by_key(|n| n.id);
+        for node in nodes {
+            let key = row_key(node);
+            let db_id = match existing.remove(&node.symbol_id) {
+                Some((db_id, old_key)) => {
+                    if old_key != key {
+                        stats.nodes_updated += 1;
+                        sqlx::query(
+                            r#"
+                            UPDATE nodes SET name = ?, type = ?, file_path = ?, start_line = ?, end_line = ?,
+                                             is_exported = ?, is_used = ?, signature = ?, qualified_name = ?,
+                                             package = ?
+                            WHERE id = ?
+                            "#,
+                        )
+                        .bind(&key.0)
+                        .bind(&key.1)
+                        .bind(&key.2)
+                        .bind(key.3)
+                        .bind(key.4)
+                        .bind(key.5)
+                        .bind(key.6)
+                        .bind(&key.7)
+                        .bind(&key.8)
+                        .bind(&key.9)
+                        .bind(db_id)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    db_id
+                }
+                None => {
+                    stats.nodes_added += 1;
+                    let db_id = next_db_id;
+                    next_db_id += 1;
+                    sqlx::query(
+                        r#"
+                        INSERT INTO nodes (id, name, type, file_path, start_line, end_line, is_exported, is_used,
+                                           signature, qualified_name, symbol_id, package)
+                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                        "#,
+                    )
+                    .bind(db_id)
+                    .bind(&key.0)
+                    .bind(&key.1)
+                    .bind(&key.2)
+                    .bind(key.3)
+                    .bind(key.4)
+                    .bind(key.5)
+                    .bind(key.6)
+                    .bind(&key.7)
+                    .bind(&key.8)
+                    .bind(&node.symbol_id)
+                    .bind(&key.9)
+                    .execute(&mut *tx)
+                    .await?;
+                    db_id
+                }
+            };
+            db_ids.insert(node.id, db_id);
+        }
+
+        // Whatever is left no longer exists
+        for (db_id, _) in existing.values() {
+            stats.nodes_removed += 1;
+            sqlx::query("DELETE FROM edges WHERE source_id = ? OR target_id = ?")
+                .bind(db_id)
+                .bind(db_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("DELETE FROM nodes WHERE id = ?")
+                .bind(db_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        // Edges are compared as a multiset of (source, target, type) in database ids;
+        // the top-level marker (usize::MAX) is stored as -1
+        let to_db = |id: usize| if id == usize::MAX { Some(-1) } else { db_ids.get(&id).copied() };
+        let mut stored: HashMap<(i64, i64, String), Vec<i64>> = HashMap::new();
+        for row in sqlx::query("SELECT id, source_id, target_id, type FROM edges")
+            .fetch_all(&mut *tx)
+            .await?
+        {
+            stored
+                .entry((row.get("source_id"), row.get("target_id"), row.get("type")))
+                .or_default()
+                .push(row.get("id"));
+        }
+        for edge in &graph.edges {
+            let (Some(source), Some(target)) = (to_db(edge.from), to_db(edge.to)) else {
+                continue;
+            };
+            let key = (source, target, format!("{:?}", edge.edge_type));
+            if stored.get_mut(&key).and_then(|ids| ids.pop()).is_some() {
+                continue;
+            }
+            stats.edges_added += 1;
+            sqlx::query("INSERT INTO edges (source_id, target_id, type) VALUES (?, ?, ?)")
+                .bind(key.0)
+                .bind(key.1)
+                .bind(&key.2)
+                .execute(&mut *tx)
+                .await?;
+        }
+        for edge_id in stored.into_values().flatten() {
+            stats.edges_removed += 1;
+            sqlx::query("DELETE FROM edges WHERE id = ?")
+                .bind(edge_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+        Ok(stats)
+    }
+
     pub async fn load_graph(&self) -> Result<CodeGraph> {
         let mut graph = CodeGraph::new();
 
@@ -132,6 +352,10 @@ impl GraphDB {
             let end_line: i64 = row.get("end_line");
             let is_exported: bool = row.get("is_exported");
             let is_used: bool = row.get("is_used");
+            let signature: String = row.get("signature");
+            let qualified_name: String = row.get("qualified_name");
+            let symbol_id: String = row.get("symbol_id");
+            let package: String = row.get("package");
 
             // Parse enums (simplified, assumes generated strings match)
             let node_type = match type_str.as_str() {
@@ -150,7 +374,10 @@ impl GraphDB {
                 line_range: (start_line as usize, end_line as usize),
                 is_exported,
                 is_used,
-                signature: String::new(), // DB doesn't store signatures yet
+                signature,
+                qualified_name,
+                symbol_id,
+                package,
             };
 
             // 手動で挿入して next_id を適切に更新する必要があるが、
@@ -185,9 +412,76 @@ impl GraphDB {
                 from: source_id as usize,
                 to: target_id as usize,
                 edge_type,
+                // Call-site positions are not stored in the database
+                site: None,
             });
         }
 
+        // nodes / edges were filled directly, so the lookup indices need rebuilding
+        graph.rebuild_indices();
+
         Ok(graph)
     }
+
+    /// Look up a node id by stable symbol id
+    pub async fn find_node_by_symbol_id(&self, symbol_id: &str) -> Result<Option<usize>> {
+        let row = sqlx::query("SELECT id FROM nodes WHERE symbol_id = ?")
+            .bind(symbol_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|r| r.get::<i64, _>("id") as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+    use crate::test_support::{temp_project, write};
+    use std::collections::BTreeSet;
+
+    #[tokio::test]
+    async fn test_sync_graph_touches_only_changed_rows() {
+        let temp = temp_project();
+        let root = temp.path();
+        write(root, "a.ts", "export function main() {\n  helper();\n}\nfunction helper() {}\nfunction old() {}\n");
+        write(root, "b.ts", "export function other() {}\n");
+        let scan = || Scanner::with_incremental(false).unwrap().scan_directory(root).unwrap();
+        let symbol = |graph: &CodeGraph, name: &str| graph.nodes_by_name(name).next().unwrap().symbol_id.clone();
+
+        let db = GraphDB::new(&root.join("graph.db")).await.unwrap();
+        let first = scan();
+        let stats = db.sync_graph(&first).await.unwrap();
+        assert_eq!((stats.nodes_added, stats.nodes_removed), (4, 0));
+        let helper_id = db.find_node_by_symbol_id(&symbol(&first, "helper")).await.unwrap();
+        let other_id = db.find_node_by_symbol_id(&symbol(&first, "other")).await.unwrap();
+
+        // Unchanged graph: nothing to do
+        assert_eq!(db.sync_graph(&first).await.unwrap(), SyncStats::default());
+
+        // main and helper move, old goes, added comes with a new call
+        write(root, "a.ts", "export function main() {\n  helper();\n  added();\n}\nfunction helper() {}\nfunction added() {}\n");
+        let second = scan();
+        let stats = db.sync_graph(&second).await.unwrap();
+        assert_eq!(
+            stats,
+            SyncStats { nodes_added: 1, nodes_updated: 2, nodes_removed: 1, edges_added: 1, edges_removed: 0 }
+        );
+
+        // Surviving nodes keep their rows, and the rows match the new graph
+        assert_eq!(db.find_node_by_symbol_id(&symbol(&second, "helper")).await.unwrap(), helper_id);
+        assert_eq!(db.find_node_by_symbol_id(&symbol(&second, "other")).await.unwrap(), other_id);
+        let stored = db.load_graph().await.unwrap();
+        let names: BTreeSet<&str> = stored.nodes.values().map(|n| n.name.as_str()).collect();
+        assert_eq!(names, BTreeSet::from(["added", "helper", "main", "other"]));
+        let helper = stored.nodes_by_name("helper").next().unwrap();
+        assert_eq!(helper.line_range, (5, 5));
+        let main = stored.nodes_by_name("main").next().unwrap().id;
+        let callees: BTreeSet<&str> = stored
+            .outgoing_edges(main)
+            .filter_map(|e| stored.nodes.get(&e.to))
+            .map(|n| n.name.as_str())
+            .collect();
+        assert_eq!(callees, BTreeSet::from(["added", "helper"]));
+    }
 }
@@ -7,6 +7,7 @@ use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use crate::graph::{CodeGraph, NodeId};
+use crate::progress::Progress;
 
 #[cfg(feature = "semantic-search")]
 use crate::embedding::EmbeddingGenerator;
@@ -36,16 +37,26 @@ impl EmbeddingCache {
     }
 
     /// Build embeddings for all nodes in the graph
-    #[cfg(feature = "semantic-search")]
     pub fn build_from_graph(graph: &CodeGraph, project_path: &Path) -> Result<Self> {
+        Self::build_with_progress(graph, project_path, &Progress::default())
+    }
+
+    /// Build embeddings, reporting each batch of nodes and stopping once `progress` is cancelled
+    #[cfg(feature = "semantic-search")]
+    pub fn build_with_progress(graph: &CodeGraph, project_path: &Path, progress: &Progress) -> Result<Self> {
         let mut cache = Self::new(project_path);
+        let total = graph.nodes.len() as u64;
 
         eprintln!("Building embeddings for {} nodes...", graph.nodes.len());
 
+        // The first run downloads the model, which can take a while
+        progress.report(0, Some(total), "Loading embedding model");
         let generator = EmbeddingGenerator::new()?;
+        progress.check()?;
 
         let mut count = 0;
-        for (&node_id, node) in &graph.nodes {
+        for (done, (&node_id, node)) in graph.nodes.iter().enumerate() {
+            progress.check()?;
             // Create text representation for embedding
             let text = format!(
                 "{} {} {}",
@@ -71,8 +82,10 @@ impl EmbeddingCache {
             // Progress indicator
             if count % 50 == 0 {
                 eprintln!("Progress: {}/{} nodes", count, graph.nodes.len());
+                progress.report(done as u64 + 1, Some(total), "Embedding nodes");
             }
         }
+        progress.report(total, Some(total), "Embedding nodes");
 
         eprintln!("Completed: {} embeddings generated", count);
 
@@ -81,7 +94,7 @@ impl EmbeddingCache {
 
     /// Stub for when semantic-search feature is disabled
     #[cfg(not(feature = "semantic-search"))]
-    pub fn build_from_graph(_graph: &CodeGraph, project_path: &Path) -> Result<Self> {
+    pub fn build_with_progress(_graph: &CodeGraph, project_path: &Path, _progress: &Progress) -> Result<Self> {
         Ok(Self::new(project_path))
     }
 
@@ -114,8 +127,13 @@ impl EmbeddingCache {
     }
 
     /// Get or create cache for a project
-    #[cfg(feature = "semantic-search")]
     pub fn get_or_create(graph: &CodeGraph, project_path: &Path) -> Result<Self> {
+        Self::get_or_create_with_progress(graph, project_path, &Progress::default())
+    }
+
+    /// Get or create cache, reporting to `progress` while embeddings are built
+    #[cfg(feature = "semantic-search")]
+    pub fn get_or_create_with_progress(graph: &CodeGraph, project_path: &Path, progress: &Progress) -> Result<Self> {
         let cache_path = Self::cache_path(project_path);
 
         if cache_path.exists() {
@@ -134,14 +152,14 @@ impl EmbeddingCache {
         }
 
         // Build new cache
-        let cache = Self::build_from_graph(graph, project_path)?;
+        let cache = Self::build_with_progress(graph, project_path, progress)?;
         cache.save(&cache_path)?;
         Ok(cache)
     }
 
     /// Stub for when semantic-search feature is disabled
     #[cfg(not(feature = "semantic-search"))]
-    pub fn get_or_create(_graph: &CodeGraph, project_path: &Path) -> Result<Self> {
+    pub fn get_or_create_with_progress(_graph: &CodeGraph, project_path: &Path, _progress: &Progress) -> Result<Self> {
         Ok(Self::new(project_path))
     }
 }
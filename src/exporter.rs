@@ -35,6 +35,10 @@ impl GraphExporter {
             file,
             "  <key id=\"name\" for=\"node\" attr.name=\"name\" attr.type=\"string\"/>"
         )?;
+        writeln!(
+            file,
+            "  <key id=\"symbol\" for=\"node\" attr.name=\"symbol_id\" attr.type=\"string\"/>"
+        )?;
         writeln!(
             file,
             "  <key id=\"type\" for=\"node\" attr.name=\"type\" attr.type=\"string\"/>"
@@ -66,6 +70,11 @@ impl GraphExporter {
                 "      <data key=\"name\">{}</data>",
                 escape_xml(&node.name)
             )?;
+            writeln!(
+                file,
+                "      <data key=\"symbol\">{}</data>",
+                escape_xml(&node.symbol_id)
+            )?;
             writeln!(file, "      <data key=\"type\">{:?}</data>", node.node_type)?;
             writeln!(
                 file,
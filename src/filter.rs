@@ -136,6 +136,28 @@ impl GraphFilter {
         self.build_filtered_graph(graph, &relevant_nodes)
     }
 
+    /// Filter graph to the nodes of one workspace package
+    pub fn filter_by_package(
+        &self,
+        graph: &CodeGraph,
+        package: &str,
+        include_dependencies: bool,
+    ) -> Result<CodeGraph> {
+        let mut relevant_nodes: HashSet<NodeId> = graph
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.package == package)
+            .map(|(id, _)| *id)
+            .collect();
+
+        if include_dependencies {
+            let deps = self.collect_dependencies(graph, &relevant_nodes);
+            relevant_nodes.extend(deps);
+        }
+
+        self.build_filtered_graph(graph, &relevant_nodes)
+    }
+
     /// Filter graph using semantic search (requires search feature)
     #[cfg(feature = "search")]
     pub fn filter_by_semantic_search(
@@ -250,6 +272,10 @@ impl GraphFilter {
         let file_path_lower = node.file_path.to_string_lossy().to_lowercase();
 
         for keyword in keywords {
+            // Exact symbol id selects exactly that node
+            if !node.symbol_id.is_empty() && node.symbol_id == *keyword {
+                return true;
+            }
             let keyword_lower = keyword.to_lowercase();
             if node_name_lower.contains(&keyword_lower) 
                 || file_path_lower.contains(&keyword_lower) {
@@ -267,13 +293,9 @@ impl GraphFilter {
     ) -> HashSet<NodeId> {
         let mut dependencies = HashSet::new();
 
-        for edge in &graph.edges {
-            if seed_nodes.contains(&edge.from) {
-                dependencies.insert(edge.to);
-            }
-            if seed_nodes.contains(&edge.to) {
-                dependencies.insert(edge.from);
-            }
+        for &id in seed_nodes {
+            dependencies.extend(graph.outgoing_edges(id).map(|edge| edge.to));
+            dependencies.extend(graph.incoming_edges(id).map(|edge| edge.from));
         }
 
         dependencies
@@ -298,14 +320,19 @@ impl GraphFilter {
         }
 
         // Add edges (only between filtered nodes)
-        for edge in &graph.edges {
-            if let (Some(&new_from), Some(&new_to)) = 
-                (id_mapping.get(&edge.from), id_mapping.get(&edge.to)) {
-                filtered.add_edge(DependencyEdge {
-                    from: new_from,
-                    to: new_to,
-                    edge_type: edge.edge_type,
-                });
+        let mut kept: Vec<NodeId> = id_mapping.keys().copied().collect();
+        kept.sort_unstable();
+        for id in kept {
+            for edge in graph.outgoing_edges(id) {
+                if let (Some(&new_from), Some(&new_to)) =
+                    (id_mapping.get(&edge.from), id_mapping.get(&edge.to)) {
+                    filtered.add_edge(DependencyEdge {
+                        from: new_from,
+                        to: new_to,
+                        edge_type: edge.edge_type,
+                        site: edge.site.clone(),
+                    });
+                }
             }
         }
 
@@ -314,12 +341,10 @@ impl GraphFilter {
 
     #[cfg(feature = "search")]
     fn find_node_by_name(&self, graph: &CodeGraph, name: &str) -> Option<NodeId> {
-        for (id, node) in &graph.nodes {
-            if node.name == name {
-                return Some(*id);
-            }
+        if let Some(node) = graph.find_by_symbol_id(name) {
+            return Some(node.id);
         }
-        None
+        graph.nodes_by_name(name).next().map(|node| node.id)
     }
 }
 
@@ -342,6 +367,9 @@ mod tests {
             is_exported: true,
             is_used: true,
             signature: "fn llm_analyzer()".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         };
 
         let node2 = CodeNode {
@@ -353,6 +381,9 @@ mod tests {
             is_exported: true,
             is_used: true,
             signature: "fn scan_file()".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         };
 
         graph.add_node(node1);
@@ -378,6 +409,9 @@ mod tests {
             is_exported: true,
             is_used: true,
             signature: "fn used_function()".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         };
 
         let node2 = CodeNode {
@@ -389,6 +423,9 @@ mod tests {
             is_exported: false,
             is_used: false,
             signature: "fn unused_function()".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         };
 
         graph.add_node(node1);
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 
 pub type NodeId = usize;
 
@@ -40,10 +40,48 @@ pub const DEPTH_LIMITS: &[usize] = &[
 ];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "CodeGraphData")]
 pub struct CodeGraph {
     pub nodes: HashMap<NodeId, CodeNode>,
     pub edges: Vec<DependencyEdge>,
     pub next_id: NodeId,
+    /// Lookup indices, maintained by `add_node` / `add_edge`.
+    /// Call `rebuild_indices` after mutating `nodes` / `edges` directly.
+    #[serde(skip)]
+    index: GraphIndex,
+}
+
+/// Serialised form of `CodeGraph` (indices are rebuilt on load)
+#[derive(Deserialize)]
+struct CodeGraphData {
+    nodes: HashMap<NodeId, CodeNode>,
+    edges: Vec<DependencyEdge>,
+    next_id: NodeId,
+}
+
+impl From<CodeGraphData> for CodeGraph {
+    fn from(data: CodeGraphData) -> Self {
+        let mut graph = Self {
+            nodes: data.nodes,
+            edges: data.edges,
+            next_id: data.next_id,
+            index: GraphIndex::default(),
+        };
+        graph.rebuild_indices();
+        graph
+    }
+}
+
+/// Name / file / symbol indices and adjacency lists (edge positions in `edges`)
+#[derive(Debug, Clone, Default)]
+struct GraphIndex {
+    by_name: HashMap<String, Vec<NodeId>>,
+    by_qualified_name: HashMap<String, Vec<NodeId>>,
+    by_symbol_id: HashMap<String, NodeId>,
+    /// Per file, ordered by (start line, id) for line-interval lookups
+    by_file: HashMap<PathBuf, Vec<NodeId>>,
+    outgoing: HashMap<NodeId, Vec<usize>>,
+    incoming: HashMap<NodeId, Vec<usize>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +95,32 @@ pub struct CodeNode {
     pub is_used: bool,
     #[serde(default)]
     pub signature: String,
+    /// Fully qualified path (Rust: `crate_name::module::Type::method`), empty if unknown
+    #[serde(default)]
+    pub qualified_name: String,
+    /// Stable symbol id that survives rescans (see `make_symbol_id`)
+    #[serde(default)]
+    pub symbol_id: String,
+    /// Workspace package the file belongs to (package.json / Cargo.toml `name`), empty outside a package
+    #[serde(default)]
+    pub package: String,
+}
+
+/// Build a stable symbol id: `<lang>:<file>#<container>.<name>`, with `~N` appended
+/// for the N-th (N >= 2) definition of the same container/name in a file.
+///
+/// e.g. `rs:src/scanner.rs#Scanner.scan_file`, `ts:src/app.ts#main~2`
+pub fn make_symbol_id(language: &str, file: &str, scope: &[String], name: &str, ordinal: usize) -> String {
+    let mut id = format!("{}:{}#", language, file.replace('\\', "/"));
+    for container in scope {
+        id.push_str(container);
+        id.push('.');
+    }
+    id.push_str(name);
+    if ordinal > 1 {
+        id.push_str(&format!("~{}", ordinal));
+    }
+    id
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -72,6 +136,17 @@ pub struct DependencyEdge {
     pub from: NodeId,
     pub to: NodeId,
     pub edge_type: EdgeType,
+    /// Where the reference is written (None when the edge has no single source position)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site: Option<SourcePosition>,
+}
+
+/// A position in a source file: 1-based line and column (in characters)
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SourcePosition {
+    pub file_path: PathBuf,
+    pub line: usize,
+    pub column: usize,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -135,6 +210,14 @@ impl SemanticRelationType {
     }
 }
 
+/// Package-level dependency: how many edges run from package `from` into package `to`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PackageDependency {
+    pub from: String,
+    pub to: String,
+    pub edges: usize,
+}
+
 /// グラフ探索結果
 #[derive(Debug, Clone)]
 pub struct TraversalResult {
@@ -169,6 +252,7 @@ impl CodeGraph {
             nodes: HashMap::new(),
             edges: Vec::new(),
             next_id: 0,
+            index: GraphIndex::default(),
         }
     }
 
@@ -176,11 +260,13 @@ impl CodeGraph {
         let id = self.next_id;
         self.next_id += 1;
         node.id = id;
+        self.index_node(&node);
         self.nodes.insert(id, node);
         id
     }
 
     pub fn add_edge(&mut self, edge: DependencyEdge) {
+        self.index_edge(self.edges.len(), &edge);
         self.edges.push(edge);
     }
 
@@ -189,6 +275,125 @@ impl CodeGraph {
         self.nodes.get(&id)
     }
 
+    /// Rebuild all indices from `nodes` and `edges`
+    pub fn rebuild_indices(&mut self) {
+        self.index = GraphIndex::default();
+
+        let mut ids: Vec<NodeId> = self.nodes.keys().copied().collect();
+        ids.sort_unstable();
+        for id in ids {
+            let node = self.nodes[&id].clone();
+            self.index_node(&node);
+        }
+
+        let edges = std::mem::take(&mut self.edges);
+        for (position, edge) in edges.iter().enumerate() {
+            self.index_edge(position, edge);
+        }
+        self.edges = edges;
+    }
+
+    fn index_node(&mut self, node: &CodeNode) {
+        let index = &mut self.index;
+        index.by_name.entry(node.name.clone()).or_default().push(node.id);
+        if !node.qualified_name.is_empty() {
+            index
+                .by_qualified_name
+                .entry(node.qualified_name.clone())
+                .or_default()
+                .push(node.id);
+        }
+        if !node.symbol_id.is_empty() {
+            index.by_symbol_id.insert(node.symbol_id.clone(), node.id);
+        }
+
+        let key = (node.line_range.0, node.id);
+        let nodes = &self.nodes;
+        let in_file = index.by_file.entry(node.file_path.clone()).or_default();
+        let position = in_file.partition_point(|other| {
+            let other_start = nodes.get(other).map(|n| n.line_range.0).unwrap_or(0);
+            (other_start, *other) < key
+        });
+        in_file.insert(position, node.id);
+    }
+
+    fn index_edge(&mut self, position: usize, edge: &DependencyEdge) {
+        self.index.outgoing.entry(edge.from).or_default().push(position);
+        self.index.incoming.entry(edge.to).or_default().push(position);
+    }
+
+    /// Look up a node by its stable symbol id
+    pub fn find_by_symbol_id(&self, symbol_id: &str) -> Option<&CodeNode> {
+        self.index
+            .by_symbol_id
+            .get(symbol_id)
+            .and_then(|id| self.nodes.get(id))
+    }
+
+    /// Nodes with exactly this name, in id order
+    pub fn nodes_by_name<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a CodeNode> + 'a {
+        self.index
+            .by_name
+            .get(name)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.nodes.get(id))
+    }
+
+    /// Nodes with exactly this qualified path, in id order
+    pub fn nodes_by_qualified_name<'a>(&'a self, qualified_name: &str) -> impl Iterator<Item = &'a CodeNode> + 'a {
+        self.index
+            .by_qualified_name
+            .get(qualified_name)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.nodes.get(id))
+    }
+
+    /// Nodes defined in a file, ordered by start line
+    pub fn nodes_in_file<'a>(&'a self, path: &Path) -> impl Iterator<Item = &'a CodeNode> + 'a {
+        self.index
+            .by_file
+            .get(path)
+            .into_iter()
+            .flatten()
+            .filter_map(|id| self.nodes.get(id))
+    }
+
+    /// Innermost node in `path` whose line range contains `line`
+    pub fn node_at_line(&self, path: &Path, line: usize) -> Option<&CodeNode> {
+        let ids = self.index.by_file.get(path)?;
+        // Only nodes starting at or before `line` can contain it
+        let end = ids.partition_point(|id| {
+            self.nodes.get(id).map(|n| n.line_range.0 <= line).unwrap_or(false)
+        });
+        ids[..end]
+            .iter()
+            .filter_map(|id| self.nodes.get(id))
+            .filter(|n| line <= n.line_range.1)
+            .min_by_key(|n| (n.line_range.1 - n.line_range.0, n.id))
+    }
+
+    /// Edges leaving `id` (callees, in insertion order)
+    pub fn outgoing_edges<'a>(&'a self, id: NodeId) -> impl Iterator<Item = &'a DependencyEdge> + 'a {
+        self.index
+            .outgoing
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(|&position| &self.edges[position])
+    }
+
+    /// Edges pointing at `id` (callers, in insertion order)
+    pub fn incoming_edges<'a>(&'a self, id: NodeId) -> impl Iterator<Item = &'a DependencyEdge> + 'a {
+        self.index
+            .incoming
+            .get(&id)
+            .into_iter()
+            .flatten()
+            .map(|&position| &self.edges[position])
+    }
+
     /// ノードが終端（汎用名）かどうかチェック
     fn is_terminal_node(&self, node_id: NodeId) -> bool {
         if let Some(node) = self.nodes.get(&node_id) {
@@ -268,8 +473,8 @@ impl CodeGraph {
                 .unwrap_or_else(|| depth_limits.last().copied().unwrap_or(5));
 
             // 出力エッジ (from -> to)
-            for edge in &self.edges {
-                if edge.from == current_id && !visited.contains(&edge.to) {
+            for edge in self.outgoing_edges(current_id) {
+                if !visited.contains(&edge.to) {
                     // depth制限チェック
                     let count = depth_counts.entry(next_depth).or_insert(0);
                     if *count >= limit {
@@ -296,8 +501,8 @@ impl CodeGraph {
             }
 
             // 入力エッジ (to <- from)
-            for edge in &self.edges {
-                if edge.to == current_id && !visited.contains(&edge.from) {
+            for edge in self.incoming_edges(current_id) {
+                if !visited.contains(&edge.from) {
                     // depth制限チェック
                     let count = depth_counts.entry(next_depth).or_insert(0);
                     if *count >= limit {
@@ -327,6 +532,37 @@ impl CodeGraph {
         results
     }
 
+    /// Package names with their node counts, sorted by name (empty = outside any package)
+    pub fn packages(&self) -> Vec<(String, usize)> {
+        let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+        for node in self.nodes.values() {
+            *counts.entry(node.package.as_str()).or_default() += 1;
+        }
+        counts.into_iter().map(|(name, count)| (name.to_string(), count)).collect()
+    }
+
+    /// Edges crossing package boundaries, aggregated per (from, to) package pair.
+    /// Top-level references have no source node and are not counted.
+    pub fn package_dependencies(&self) -> Vec<PackageDependency> {
+        let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+        for edge in &self.edges {
+            let (Some(from), Some(to)) = (self.nodes.get(&edge.from), self.nodes.get(&edge.to)) else {
+                continue;
+            };
+            if from.package != to.package {
+                *counts.entry((from.package.as_str(), to.package.as_str())).or_default() += 1;
+            }
+        }
+        counts
+            .into_iter()
+            .map(|((from, to), edges)| PackageDependency {
+                from: from.to_string(),
+                to: to.to_string(),
+                edges,
+            })
+            .collect()
+    }
+
     /// ノード名で検索
     pub fn find_nodes_by_name(&self, query: &str) -> Vec<NodeId> {
         let query_lower = query.to_lowercase();
@@ -355,6 +591,9 @@ mod tests {
             is_exported: true,
             is_used: true,
             signature: "fn new()".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         });
 
         let custom_id = graph.add_node(CodeNode {
@@ -366,12 +605,64 @@ mod tests {
             is_exported: true,
             is_used: true,
             signature: "fn my_custom_function()".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         });
 
         assert!(graph.is_terminal_node(new_id));
         assert!(!graph.is_terminal_node(custom_id));
     }
 
+    #[test]
+    fn test_indices_survive_serialization() {
+        let mut graph = CodeGraph::new();
+        let node = |name: &str, range: (usize, usize)| CodeNode {
+            id: 0,
+            name: name.to_string(),
+            node_type: NodeType::Function,
+            file_path: PathBuf::from("src/lib.rs"),
+            line_range: range,
+            is_exported: false,
+            is_used: false,
+            signature: String::new(),
+            qualified_name: format!("crate::{}", name),
+            symbol_id: format!("rs:src/lib.rs#{}", name),
+            package: String::new(),
+        };
+        let outer = graph.add_node(node("outer", (1, 20)));
+        let inner = graph.add_node(node("inner", (5, 8)));
+        let other = graph.add_node(node("other", (22, 30)));
+        graph.add_edge(DependencyEdge { from: outer, to: inner, edge_type: EdgeType::Calls, site: None });
+        graph.add_edge(DependencyEdge { from: other, to: inner, edge_type: EdgeType::Calls, site: None });
+
+        let json = serde_json::to_string(&graph).unwrap();
+        let restored: CodeGraph = serde_json::from_str(&json).unwrap();
+
+        for g in [&graph, &restored] {
+            assert_eq!(g.node_at_line(Path::new("src/lib.rs"), 6).map(|n| n.id), Some(inner));
+            assert_eq!(g.node_at_line(Path::new("src/lib.rs"), 12).map(|n| n.id), Some(outer));
+            assert!(g.node_at_line(Path::new("src/lib.rs"), 21).is_none());
+            assert_eq!(g.nodes_in_file(Path::new("src/lib.rs")).count(), 3);
+            assert_eq!(g.nodes_by_name("other").next().map(|n| n.id), Some(other));
+            assert_eq!(g.nodes_by_qualified_name("crate::inner").count(), 1);
+            assert_eq!(g.find_by_symbol_id("rs:src/lib.rs#outer").map(|n| n.id), Some(outer));
+            let callers: Vec<NodeId> = g.incoming_edges(inner).map(|e| e.from).collect();
+            assert_eq!(callers, vec![outer, other]);
+            assert_eq!(g.outgoing_edges(outer).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_symbol_id_format() {
+        let scope = vec!["Scanner".to_string()];
+        assert_eq!(
+            make_symbol_id("rs", "src/scanner.rs", &scope, "scan_file", 1),
+            "rs:src/scanner.rs#Scanner.scan_file"
+        );
+        assert_eq!(make_symbol_id("ts", "src\\app.ts", &[], "main", 2), "ts:src/app.ts#main~2");
+    }
+
     #[test]
     fn test_depth_limits() {
         let mut graph = CodeGraph::new();
@@ -387,6 +678,9 @@ mod tests {
                 is_exported: true,
                 is_used: true,
                 signature: format!("fn func_{}()", i),
+                qualified_name: String::new(),
+                symbol_id: String::new(),
+                package: String::new(),
             })
         }).collect();
 
@@ -395,6 +689,7 @@ mod tests {
                 from: ids[i],
                 to: ids[i + 1],
                 edge_type: EdgeType::Calls,
+                site: None,
             });
         }
 
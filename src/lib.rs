@@ -10,13 +10,22 @@ pub mod exporter;
 pub mod filter;
 pub mod graph;
 pub mod parser;
+pub mod progress;
 pub mod reporter;
+pub mod resolver;
+pub mod rust_modules;
 pub mod scanner;
+pub mod sfc;
+pub mod workspace;
 
 // データベース機能（オプション）
 #[cfg(feature = "db")]
 pub mod database;
 
+// ファイル監視（db 機能が必要）
+#[cfg(feature = "db")]
+pub mod watcher;
+
 // 会話分析機能
 pub mod conversation;
 
@@ -30,12 +39,23 @@ pub mod search;
 pub mod embedding;
 pub mod embedding_cache;
 
+// 依存関係グラフWebサーバー（オプション）
+#[cfg(feature = "web")]
+pub mod web_server;
+
 // チャットグラフWebサーバー（オプション）
 pub mod chat_server;
 
 // MCP Server (Phase 6)
 pub mod mcp;
 
+// 常駐デーモン（クライアントは常に利用可能、サーバーは db 機能が必要）
+pub mod daemon;
+
+// テスト用の一時プロジェクト
+#[cfg(test)]
+mod test_support;
+
 // 再エクスポート
 pub use annotator::{AnnotationResult, Annotator};
 pub use cleaner::{CleanResult, Cleaner};
@@ -5,15 +5,12 @@ use std::path::PathBuf;
 use cleaner::Cleaner;
 use detector::detect_dead_code;
 use reporter::{generate_json_report, print_report};
-use scanner::Scanner;
-
-#[cfg(feature = "db")]
-use graph::CodeGraph;
 
 use index_chan::{
-    annotator, backup, cleaner, conversation, detector, exporter, llm, mcp, reporter, scanner,
+    annotator, backup, cleaner, conversation, daemon, detector, exporter, llm, mcp, reporter,
     search,
 };
+use index_chan::workspace::Workspace;
 
 #[cfg(feature = "db")]
 use index_chan::database;
@@ -45,6 +42,10 @@ enum Commands {
         #[arg(long)]
         llm: bool,
 
+        /// Limit to one workspace package (name from package.json / Cargo.toml)
+        #[arg(long, value_name = "NAME")]
+        package: Option<String>,
+
         /// Use database instead of scanning (requires init first)
         #[cfg(feature = "db")]
         #[arg(long)]
@@ -85,6 +86,29 @@ enum Commands {
         dry_run: bool,
     },
 
+    /// Rename a symbol at its definition, call sites and imports
+    Rename {
+        /// Project directory
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Function name or symbol id to rename
+        #[arg(value_name = "SYMBOL")]
+        symbol: String,
+
+        /// New name
+        #[arg(value_name = "NEW_NAME")]
+        new_name: String,
+
+        /// Dry run (only show the diff)
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Rename even if NEW_NAME is already defined
+        #[arg(long)]
+        force: bool,
+    },
+
     /// Undo the last operation (restore from backup)
     Undo {
         /// Project directory
@@ -199,6 +223,10 @@ enum Commands {
         #[arg(short, long, default_value = "graphml")]
         format: String,
 
+        /// Limit to one workspace package (name from package.json / Cargo.toml)
+        #[arg(long, value_name = "NAME")]
+        package: Option<String>,
+
         /// Use database instead of scanning (requires init first)
         #[cfg(feature = "db")]
         #[arg(long)]
@@ -252,6 +280,10 @@ enum Commands {
         /// Database path (optional, defaults to .index-chan/<project>.db)
         #[arg(long)]
         db_path: Option<PathBuf>,
+
+        /// Limit to one workspace package (name from package.json / Cargo.toml)
+        #[arg(long, value_name = "NAME")]
+        package: Option<String>,
     },
 
     /// Watch for file changes and update database
@@ -261,9 +293,29 @@ enum Commands {
         #[arg(value_name = "DIRECTORY")]
         directory: PathBuf,
 
-        /// Database path (optional, defaults to .index-chan/<project>.db)
+        /// Database path (optional, defaults to .index-chan/graph.db)
         #[arg(long)]
         db_path: Option<PathBuf>,
+
+        /// Debounce interval for file events in milliseconds
+        #[arg(long, default_value = "500")]
+        debounce_ms: u64,
+    },
+
+    /// Keep the project graph in memory and serve it to other commands (Unix socket)
+    #[cfg(feature = "db")]
+    Daemon {
+        /// Target directory (workspace root)
+        #[arg(value_name = "DIRECTORY")]
+        directory: PathBuf,
+
+        /// Debounce interval for file events in milliseconds
+        #[arg(long, default_value = "500")]
+        debounce_ms: u64,
+
+        /// Stop the daemon running for DIRECTORY
+        #[arg(long)]
+        stop: bool,
     },
 
     /// Visualize chat graph and prompts (web UI)
@@ -312,11 +364,31 @@ enum Commands {
         message: Option<String>,
     },
 
-    /// Start MCP server (stdio mode)
+    /// Start MCP server (stdio mode, or Streamable HTTP with --http)
     McpServer {
         /// Project directory (optional, can be set per-request)
         #[arg(value_name = "DIRECTORY")]
         directory: Option<PathBuf>,
+
+        /// Serve over HTTP instead of stdio
+        #[cfg(feature = "web")]
+        #[arg(long)]
+        http: bool,
+
+        /// Address to bind (loopback by default)
+        #[cfg(feature = "web")]
+        #[arg(long, default_value = "127.0.0.1")]
+        host: std::net::IpAddr,
+
+        /// HTTP port
+        #[cfg(feature = "web")]
+        #[arg(short, long, default_value = "3939")]
+        port: u16,
+
+        /// Bearer token required from clients (default: $INDEX_CHAN_MCP_TOKEN)
+        #[cfg(feature = "web")]
+        #[arg(long)]
+        token: Option<String>,
     },
 }
 
@@ -328,6 +400,7 @@ fn main() -> Result<()> {
             directory,
             output,
             llm,
+            package,
             #[cfg(feature = "db")]
             use_db,
         } => {
@@ -373,21 +446,45 @@ fn main() -> Result<()> {
                 }
             } else {
                 // 通常のスキャン
-                let mut scanner = Scanner::new()?;
-                scanner.scan_directory(&directory)?
+                daemon::load_graph(&directory)?
+            };
+
+            let scope_dir = match &package {
+                Some(name) => {
+                    if !has_package(&graph, name) {
+                        return Ok(());
+                    }
+                    println!("📦 パッケージ: {}", name);
+                    Workspace::detect(&directory)
+                        .packages()
+                        .iter()
+                        .find(|p| &p.name == name)
+                        .map(|p| p.root.clone())
+                        .unwrap_or_else(|| directory.clone())
+                }
+                None => directory.clone(),
             };
 
-            let total_files = walkdir::WalkDir::new(&directory)
+            let total_files = walkdir::WalkDir::new(&scope_dir)
                 .into_iter()
                 .filter_map(|e| e.ok())
                 .filter(|e| {
-                    e.path().extension().and_then(|s| s.to_str()) == Some("ts")
-                        || e.path().extension().and_then(|s| s.to_str()) == Some("tsx")
+                    matches!(
+                        e.path().extension().and_then(|s| s.to_str()),
+                        Some("ts" | "tsx" | "vue" | "svelte")
+                    )
                 })
                 .count();
 
-            let total_functions = graph.nodes.len();
-            let dead_code = detect_dead_code(&graph);
+            // Dead code is detected on the whole graph so uses from other packages count
+            let mut dead_code = detect_dead_code(&graph);
+            let total_functions = match &package {
+                Some(name) => {
+                    dead_code.retain(|d| &d.node.package == name);
+                    graph.nodes.values().filter(|n| &n.package == name).count()
+                }
+                None => graph.nodes.len(),
+            };
 
             // LLM analysis if requested
             if llm {
@@ -397,6 +494,9 @@ fn main() -> Result<()> {
             }
 
             print_report(&dead_code, total_files, total_functions);
+            if package.is_none() {
+                print_package_summary(&graph, &dead_code);
+            }
 
             if let Some(output_path) = output {
                 let report = generate_json_report(&dead_code, total_files, total_functions);
@@ -419,9 +519,8 @@ fn main() -> Result<()> {
             }
             println!();
 
-            // スキャン
-            let mut scanner = Scanner::new()?;
-            let graph = scanner.scan_directory(&directory)?;
+            // スキャン（行範囲で書き換えるので、デーモンのグラフもディスクに合わせる）
+            let graph = daemon::load_current_graph(&directory)?;
 
             let dead_code = detect_dead_code(&graph);
 
@@ -463,9 +562,8 @@ fn main() -> Result<()> {
             }
             println!();
 
-            // スキャン
-            let mut scanner = Scanner::new()?;
-            let graph = scanner.scan_directory(&directory)?;
+            // スキャン（行範囲で書き換えるので、デーモンのグラフもディスクに合わせる）
+            let graph = daemon::load_current_graph(&directory)?;
 
             let dead_code = detect_dead_code(&graph);
 
@@ -501,6 +599,76 @@ fn main() -> Result<()> {
             Ok(())
         }
 
+        Commands::Rename {
+            directory,
+            symbol,
+            new_name,
+            dry_run,
+            force,
+        } => {
+            use mcp::changes::ChangeManager;
+
+            println!("✏️  Renaming {} → {}: {}", symbol, new_name, directory.display());
+            if dry_run {
+                println!("(Dry run mode)");
+            }
+            println!();
+
+            let graph = daemon::load_graph(&directory)?;
+            let plan = mcp::rename::plan_rename(graph, &directory, &symbol, &new_name)?;
+
+            let manager = ChangeManager::from_directory(&directory)?;
+            let preview = manager.preview_changes(&plan.changes, false)?;
+            for diff in &preview.diffs {
+                println!("📄 {}", diff.file_path);
+                println!("{}", diff.diff);
+            }
+            println!(
+                "📊 {} edits in {} files (+{} -{})",
+                plan.edits.len(),
+                plan.changes.len(),
+                preview.total_additions,
+                preview.total_deletions
+            );
+
+            for warning in &plan.warnings {
+                println!("⚠️  {}", warning);
+            }
+            if !plan.unresolved.is_empty() {
+                println!("\n🔎 Mentions of {} left unchanged (check by hand):", plan.old_name);
+                for mention in &plan.unresolved {
+                    println!("  {}:{}:{}  {}", mention.file_path, mention.line, mention.column, mention.source_line.trim());
+                }
+            }
+
+            if dry_run {
+                println!("\n💡 Remove --dry-run flag to actually rename");
+                return Ok(());
+            }
+            if !plan.warnings.is_empty() && !force {
+                anyhow::bail!("{} is already defined: nothing was renamed (pass --force to rename anyway)", new_name);
+            }
+
+            let result = manager.apply_changes(&plan.changes, true)?;
+            for failed in &result.failed_files {
+                eprintln!("❌ {}: {}", failed.file, failed.error);
+            }
+            if let Some(backup_dir) = &result.backup_dir {
+                println!("\n📦 Backup: {}", backup_dir);
+            }
+            if result.rolled_back {
+                eprintln!("↩️  Rolled back: no files were changed");
+            }
+            if !result.rollback_failed.is_empty() {
+                eprintln!("⚠️  Partially rolled back: {} keep the new content", result.rollback_failed.join(", "));
+            }
+            if result.success {
+                println!("✅ Renamed {} files", result.applied_files.len());
+            }
+
+            Ok(())
+        }
+
         Commands::Undo {
             directory,
             backup,
@@ -624,8 +792,7 @@ fn main() -> Result<()> {
             println!();
 
             // Scan directory
-            let mut scanner = Scanner::new()?;
-            let graph = scanner.scan_directory(&directory)?;
+            let graph = daemon::load_graph(&directory)?;
 
             println!("📊 Found {} functions", graph.nodes.len());
 
@@ -635,9 +802,7 @@ fn main() -> Result<()> {
             for (_id, node) in &graph.nodes {
                 // Get dependencies
                 let dependencies: Vec<String> = graph
-                    .edges
-                    .iter()
-                    .filter(|e| e.from == node.id)
+                    .outgoing_edges(node.id)
                     .filter_map(|e| graph.nodes.get(&e.to).map(|n| n.name.clone()))
                     .collect();
 
@@ -905,6 +1070,7 @@ fn main() -> Result<()> {
             directory,
             output,
             format,
+            package,
             #[cfg(feature = "db")]
             use_db,
         } => {
@@ -958,8 +1124,18 @@ fn main() -> Result<()> {
                     unreachable!()
                 }
             } else {
-                let mut scanner = Scanner::new()?;
-                scanner.scan_directory(&directory)?
+                daemon::load_graph(&directory)?
+            };
+
+            let graph = match &package {
+                Some(name) => {
+                    if !has_package(&graph, name) {
+                        return Ok(());
+                    }
+                    println!("📦 パッケージ: {}", name);
+                    index_chan::filter::GraphFilter::new().filter_by_package(&graph, name, false)?
+                }
+                None => graph,
             };
 
             println!("📊 グラフ統計:");
@@ -1056,8 +1232,7 @@ fn main() -> Result<()> {
                     unreachable!()
                 }
             } else {
-                let mut scanner = Scanner::new()?;
-                scanner.scan_directory(&directory)?
+                daemon::load_graph(&directory)?
             };
 
             println!("📊 グラフ統計:");
@@ -1072,7 +1247,7 @@ fn main() -> Result<()> {
                 #[cfg(feature = "web")]
                 {
                     use std::process::Command;
-                    let _ = Command::new("cmd").args(&["/C", "start", &url]).spawn();
+                    let _ = Command::new("cmd").args(["/C", "start", &url]).spawn();
                 }
             }
 
@@ -1080,7 +1255,10 @@ fn main() -> Result<()> {
             #[cfg(feature = "web")]
             {
                 let runtime = tokio::runtime::Runtime::new()?;
-                runtime.block_on(async { web_server::server::start_server(graph, port).await })?;
+                // Without --use-db the page follows a running daemon's live graph
+                runtime.block_on(async {
+                    web_server::server::start_server(graph, directory.clone(), !use_db, port).await
+                })?;
             }
 
             Ok(())
@@ -1115,7 +1293,7 @@ fn main() -> Result<()> {
             println!();
 
             println!("🔍 ディレクトリをスキャン中...");
-            let mut scanner = Scanner::new()?;
+            let mut scanner = index_chan::scanner::Scanner::new()?;
             let graph = scanner.scan_directory(&directory)?;
             println!("✅ スキャン完了: {} nodes", graph.nodes.len());
 
@@ -1138,6 +1316,7 @@ fn main() -> Result<()> {
         Commands::Stats {
             directory,
             db_path: _,
+            package,
         } => {
             println!("📊 プロジェクト統計: {}", directory.display());
             println!();
@@ -1166,23 +1345,83 @@ fn main() -> Result<()> {
                 db.load_graph().await
             })?;
 
-            let dead_code = detect_dead_code(&graph);
+            let mut dead_code = detect_dead_code(&graph);
+
+            if let Some(name) = &package {
+                if !has_package(&graph, name) {
+                    return Ok(());
+                }
+                dead_code.retain(|d| &d.node.package == name);
+                let nodes = graph.nodes.values().filter(|n| &n.package == name).count();
+                let dependencies = graph.package_dependencies();
+
+                println!("📦 パッケージ: {}", name);
+                println!("  ノード数: {}", nodes);
+                println!("  デッドコード: {} 個", dead_code.len());
+                for dep in dependencies.iter().filter(|d| &d.from == name) {
+                    println!("  → {} ({} edges)", package_label(&dep.to), dep.edges);
+                }
+                for dep in dependencies.iter().filter(|d| &d.to == name) {
+                    println!("  ← {} ({} edges)", package_label(&dep.from), dep.edges);
+                }
+                return Ok(());
+            }
 
             println!("📊 統計:");
             println!("  ノード数: {}", graph.nodes.len());
             println!("  エッジ数: {}", graph.edges.len());
             println!("  デッドコード: {} 個", dead_code.len());
+            print_package_summary(&graph, &dead_code);
 
             Ok(())
         }
         #[cfg(feature = "db")]
         Commands::Watch {
-            directory: _,
-            db_path: _,
+            directory,
+            db_path,
+            debounce_ms,
         } => {
-            println!("⚠️ Watch機能は現在メンテナンス中です。");
-            println!("💡 代わりに定期的に index-chan scan を実行してください。");
-            Ok(())
+            if !directory.exists() {
+                eprintln!("❌ ディレクトリが見つかりません: {}", directory.display());
+                return Ok(());
+            }
+
+            let db_path = db_path.unwrap_or_else(|| directory.join(".index-chan").join("graph.db"));
+            println!("🔍 初回スキャン中: {}", directory.display());
+            index_chan::watcher::watch(
+                &directory,
+                &db_path,
+                std::time::Duration::from_millis(debounce_ms),
+            )
+        }
+        #[cfg(feature = "db")]
+        Commands::Daemon {
+            directory,
+            debounce_ms,
+            stop,
+        } => {
+            if stop {
+                match daemon::DaemonClient::connect(&directory) {
+                    Some(mut client) => {
+                        client.request(&daemon::DaemonRequest::Shutdown)?;
+                        println!("👋 デーモンを停止しました");
+                    }
+                    None => println!("💡 デーモンは起動していません: {}", directory.display()),
+                }
+                return Ok(());
+            }
+
+            #[cfg(unix)]
+            {
+                println!("🔍 初回スキャン中: {}", directory.display());
+                daemon::run(&directory, std::time::Duration::from_millis(debounce_ms))
+            }
+            #[cfg(not(unix))]
+            {
+                let _ = debounce_ms;
+                eprintln!("❌ デーモンは Unix 系 OS でのみ利用できます");
+                Ok(())
+            }
         }
 
         #[cfg(feature = "web")]
@@ -1348,8 +1587,24 @@ fn main() -> Result<()> {
 
         Commands::Chat { directory, message } => run_chat(directory, message),
 
-        Commands::McpServer { directory } => {
-            eprintln!("🔌 Starting MCP server (stdio mode)...");
+        Commands::McpServer {
+            directory,
+            #[cfg(feature = "web")]
+            http,
+            #[cfg(feature = "web")]
+            host,
+            #[cfg(feature = "web")]
+            port,
+            #[cfg(feature = "web")]
+            token,
+        } => {
+            #[cfg(feature = "web")]
+            let stdio = !http;
+            #[cfg(not(feature = "web"))]
+            let stdio = true;
+            if stdio {
+                eprintln!("🔌 Starting MCP server (stdio mode)...");
+            }
             let project_dir = directory.unwrap_or_else(|| std::env::current_dir().unwrap());
             eprintln!("📂 Project directory: {}", project_dir.display());
 
@@ -1360,7 +1615,7 @@ fn main() -> Result<()> {
                 if !db_path.exists() {
                     eprintln!("🔄 Performing startup scan...");
                     let res: Result<()> = (|| {
-                        let mut scanner = Scanner::new()?;
+                        let mut scanner = index_chan::scanner::Scanner::new()?;
                         let graph = scanner.scan_directory(&project_dir)?;
                         let rt = tokio::runtime::Runtime::new()?;
                         rt.block_on(async {
@@ -1382,12 +1637,67 @@ fn main() -> Result<()> {
             }
 
             let mut server = mcp::McpServer::new(Some(project_dir));
+
+            #[cfg(feature = "web")]
+            if http {
+                let token = token.or_else(|| std::env::var("INDEX_CHAN_MCP_TOKEN").ok());
+                let options = mcp::http::HttpOptions { host, port, token, ..Default::default() };
+                let runtime = tokio::runtime::Runtime::new()?;
+                return runtime.block_on(mcp::http::serve(server, options));
+            }
+
             server.run()?;
             Ok(())
         }
     }
 }
 
+/// Whether the graph has nodes in `name`; prints the known packages otherwise
+fn has_package(graph: &index_chan::graph::CodeGraph, name: &str) -> bool {
+    let packages = graph.packages();
+    if packages.iter().any(|(package, _)| package == name) {
+        return true;
+    }
+    eprintln!("❌ パッケージが見つかりません: {}", name);
+    let names: Vec<&str> = packages.iter().map(|(p, _)| p.as_str()).filter(|p| !p.is_empty()).collect();
+    if !names.is_empty() {
+        eprintln!("💡 利用可能なパッケージ: {}", names.join(", "));
+    }
+    false
+}
+
+fn package_label(name: &str) -> &str {
+    if name.is_empty() {
+        "(no package)"
+    } else {
+        name
+    }
+}
+
+/// Per-package counts and inter-package edges, printed only for multi-package workspaces
+fn print_package_summary(graph: &index_chan::graph::CodeGraph, dead_code: &[detector::DeadCode]) {
+    let packages = graph.packages();
+    if packages.len() < 2 {
+        return;
+    }
+
+    println!();
+    println!("📦 パッケージ別:");
+    for (name, nodes) in &packages {
+        let dead = dead_code.iter().filter(|d| &d.node.package == name).count();
+        println!("  {}: {} nodes, デッドコード {} 個", package_label(name), nodes, dead);
+    }
+
+    let dependencies = graph.package_dependencies();
+    if !dependencies.is_empty() {
+        println!();
+        println!("🔗 パッケージ間の依存:");
+        for dep in dependencies {
+            println!("  {} → {} ({} edges)", package_label(&dep.from), package_label(&dep.to), dep.edges);
+        }
+    }
+}
+
 /// Run interactive chat with Index
 fn run_chat(directory: Option<PathBuf>, single_message: Option<String>) -> Result<()> {
     use std::io::{self, Write};
@@ -1483,8 +1793,7 @@ fn handle_chat_command(input: &str, directory: &Option<PathBuf>) -> Result<()> {
         }
         "/scan" | "/s" => {
             println!("🔍 スキャン中なんだよ...\n");
-            let mut scanner = Scanner::new()?;
-            let graph = scanner.scan_directory(&dir)?;
+            let graph = daemon::load_graph(&dir)?;
             let dead_code = detect_dead_code(&graph);
 
             if dead_code.is_empty() {
@@ -1510,8 +1819,7 @@ fn handle_chat_command(input: &str, directory: &Option<PathBuf>) -> Result<()> {
         }
         "/annotate" | "/a" => {
             println!("📝 アノテーション追加中（dry-run）なんだよ...\n");
-            let mut scanner = Scanner::new()?;
-            let graph = scanner.scan_directory(&dir)?;
+            let graph = daemon::load_graph(&dir)?;
             let dead_code = detect_dead_code(&graph);
 
             let annotator = annotator::Annotator::new(true);
@@ -1528,8 +1836,7 @@ fn handle_chat_command(input: &str, directory: &Option<PathBuf>) -> Result<()> {
         }
         "/clean" | "/c" => {
             println!("🧹 クリーニング確認中（dry-run）なんだよ...\n");
-            let mut scanner = Scanner::new()?;
-            let graph = scanner.scan_directory(&dir)?;
+            let graph = daemon::load_graph(&dir)?;
             let dead_code = detect_dead_code(&graph);
 
             let cleaner = Cleaner::new(true, false, true);
@@ -1546,8 +1853,7 @@ fn handle_chat_command(input: &str, directory: &Option<PathBuf>) -> Result<()> {
         }
         "/stats" => {
             println!("📊 プロジェクト統計なんだよ...\n");
-            let mut scanner = Scanner::new()?;
-            let graph = scanner.scan_directory(&dir)?;
+            let graph = daemon::load_graph(&dir)?;
             let dead_code = detect_dead_code(&graph);
 
             println!("  ノード数: {}", graph.nodes.len());
@@ -1716,8 +2022,7 @@ async fn execute_cli_tool(
 
     match name {
         "scan_project" => {
-            let mut scanner = Scanner::new().map_err(|e| e.to_string())?;
-            let graph = scanner.scan_directory(&path).map_err(|e| e.to_string())?;
+            let graph = daemon::load_graph(&path).map_err(|e| e.to_string())?;
             let dead_code = detect_dead_code(&graph);
 
             Ok(serde_json::json!({
@@ -1738,8 +2043,7 @@ async fn execute_cli_tool(
                 .get("dry_run")
                 .and_then(|v| v.as_bool())
                 .unwrap_or(true);
-            let mut scanner = Scanner::new().map_err(|e| e.to_string())?;
-            let graph = scanner.scan_directory(&path).map_err(|e| e.to_string())?;
+            let graph = daemon::load_graph(&path).map_err(|e| e.to_string())?;
             let dead_code = detect_dead_code(&graph);
 
             let annotator = annotator::Annotator::new(dry_run);
@@ -1760,8 +2064,7 @@ async fn execute_cli_tool(
                 .get("safe_only")
                 .and_then(|v| v.as_bool())
                 .unwrap_or(true);
-            let mut scanner = Scanner::new().map_err(|e| e.to_string())?;
-            let graph = scanner.scan_directory(&path).map_err(|e| e.to_string())?;
+            let graph = daemon::load_graph(&path).map_err(|e| e.to_string())?;
             let dead_code = detect_dead_code(&graph);
 
             let cleaner = Cleaner::new(dry_run, false, safe_only);
@@ -1775,8 +2078,7 @@ async fn execute_cli_tool(
             }))
         }
         "get_project_stats" => {
-            let mut scanner = Scanner::new().map_err(|e| e.to_string())?;
-            let graph = scanner.scan_directory(&path).map_err(|e| e.to_string())?;
+            let graph = daemon::load_graph(&path).map_err(|e| e.to_string())?;
             let dead_code = detect_dead_code(&graph);
 
             Ok(serde_json::json!({
@@ -3,11 +3,18 @@
 
 use std::path::{Path, PathBuf};
 use std::collections::HashMap;
-use anyhow::{Result, anyhow};
+use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
 
 use crate::graph::CodeGraph;
-use crate::scanner::Scanner;
+use crate::mcp::context::word_columns;
+use crate::mcp::diff::{inline_changes, unified_diff, InlineChange};
+use crate::parser::{syntax_errors, CodeParser, FunctionInfo};
+use crate::resolver::{normalize_path, ModuleResolver, Resolution};
+use crate::rust_modules::RustModuleTree;
+use crate::scanner::ScanCache;
+use crate::sfc::{parse_sfc, SfcKind};
 
 /// Change validation result
 #[derive(Debug, Clone, Serialize)]
@@ -19,6 +26,9 @@ pub struct ValidationResult {
     pub affected_functions: Vec<String>,
     pub new_functions: Vec<String>,
     pub import_issues: Vec<ImportIssue>,
+    pub syntax_errors: Vec<SyntaxIssue>,
+    /// Definitions the changes delete although other code still uses them
+    pub removed_symbols: Vec<RemovedSymbol>,
 }
 
 #[derive(Debug, Clone, Serialize, PartialEq)]
@@ -37,6 +47,24 @@ pub struct ImportIssue {
     pub issue: String,
 }
 
+/// Syntax error in the changed content (from the tree-sitter parse)
+#[derive(Debug, Clone, Serialize)]
+pub struct SyntaxIssue {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+/// A deleted definition that still has callers or importers
+#[derive(Debug, Clone, Serialize)]
+pub struct RemovedSymbol {
+    pub file: String,
+    pub name: String,
+    /// `name (file:line)` of each dependent outside the removed code
+    pub dependents: Vec<String>,
+}
+
 /// File change
 #[derive(Debug, Clone, Deserialize)]
 pub struct FileChange {
@@ -45,6 +73,9 @@ pub struct FileChange {
     pub modified_content: String,
     pub start_line: Option<usize>,
     pub end_line: Option<usize>,
+    /// SHA-256 of the file the edit is based on; the change is refused if the file differs now
+    #[serde(default)]
+    pub base_hash: Option<String>,
 }
 
 /// Change application result
@@ -54,6 +85,10 @@ pub struct ApplyResult {
     pub applied_files: Vec<String>,
     pub failed_files: Vec<FailedFile>,
     pub backup_dir: Option<String>,
+    /// Files written before a failure were restored: the project is as it was before the call
+    pub rolled_back: bool,
+    /// Files a rollback could not restore: they keep the new content (or still exist, if created)
+    pub rollback_failed: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -73,9 +108,15 @@ pub struct PreviewResult {
 #[derive(Debug, Clone, Serialize)]
 pub struct FileDiff {
     pub file_path: String,
+    /// Unified diff with `@@` hunks (line numbers of the whole file, also for partial changes)
     pub diff: String,
     pub additions: usize,
     pub deletions: usize,
+    /// SHA-256 of the file as it is now (None for new files), to pass back as `base_hash`
+    pub base_hash: Option<String>,
+    /// Word-level changes per line, when asked for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inline: Option<Vec<InlineChange>>,
 }
 
 /// Change manager
@@ -96,8 +137,7 @@ impl ChangeManager {
     /// Load graph for validation
     fn ensure_graph(&mut self) -> Result<&CodeGraph> {
         if self.graph.is_none() {
-            let mut scanner = Scanner::new()?;
-            self.graph = Some(scanner.scan_directory(&self.project_dir)?);
+            self.graph = Some(crate::daemon::load_graph(&self.project_dir)?);
         }
         Ok(self.graph.as_ref().unwrap())
     }
@@ -112,11 +152,20 @@ impl ChangeManager {
             affected_functions: Vec::new(),
             new_functions: Vec::new(),
             import_issues: Vec::new(),
+            syntax_errors: Vec::new(),
+            removed_symbols: Vec::new(),
         };
 
         // Load graph for dependency analysis
         self.ensure_graph()?;
         let graph = self.graph.as_ref().unwrap();
+        let mut resolver = ModuleResolver::new(&self.project_dir);
+        // Built when the first Rust file comes up
+        let mut rust_modules: Option<RustModuleTree> = None;
+        let pending_files: Vec<PathBuf> = changes
+            .iter()
+            .map(|c| self.project_dir.join(&c.file_path))
+            .collect();
 
         for change in changes {
             let file_path = self.project_dir.join(&change.file_path);
@@ -130,34 +179,74 @@ impl ChangeManager {
 
             result.affected_files.push(change.file_path.clone());
 
-            // Syntax check
-            if let Err(e) = self.check_syntax(&change.modified_content) {
-                result.errors.push(format!("Syntax error in {}: {}", change.file_path, e));
-                result.status = ValidationStatus::Error;
+            // Syntax check: parse the resulting file with the grammar of its language
+            let current = match &change.original_content {
+                Some(content) => Some(content.clone()),
+                None => std::fs::read_to_string(&file_path).ok(),
+            };
+            let resulting = resulting_content(change, current.as_deref());
+            let original = current.unwrap_or_default();
+            match parse_definitions(&file_path, &resulting) {
+                Ok(Some(parsed)) => {
+                    for error in parsed.errors {
+                        result.errors.push(format!(
+                            "Syntax error in {}:{}:{}: {}",
+                            change.file_path, error.line, error.column, error.message
+                        ));
+                        result.syntax_errors.push(SyntaxIssue {
+                            file: change.file_path.clone(),
+                            line: error.line,
+                            column: error.column,
+                            message: error.message,
+                        });
+                    }
+
+                    let before = parse_definitions(&file_path, &original)?.map(|p| p.functions).unwrap_or_default();
+                    for warning in duplicate_definitions(&before, &parsed.functions) {
+                        result.warnings.push(format!("Duplicate definition in {}: {}", change.file_path, warning));
+                    }
+
+                    // New and modified definitions, by scope and name
+                    let added: Vec<String> = definitions_missing_from(&parsed.functions, &before).into_iter().map(qualified).collect();
+                    if !added.is_empty() {
+                        result.warnings.push(format!("New functions added in {}: {}", change.file_path, added.join(", ")));
+                        result.new_functions.extend(added);
+                    }
+                    let modified: Vec<String> = modified_definitions(&before, &original, &parsed.functions, &resulting)
+                        .into_iter()
+                        .map(qualified)
+                        .collect();
+                    if !modified.is_empty() {
+                        result.warnings.push(format!("Functions modified in {}: {}", change.file_path, modified.join(", ")));
+                        result.affected_functions.extend(modified);
+                    }
+
+                    let removed = definitions_missing_from(&before, &parsed.functions);
+                    for symbol in self.removed_with_dependents(&change.file_path, &file_path, &removed, changes, graph) {
+                        result.errors.push(format!(
+                            "{} removed from {} is still used by {}",
+                            symbol.name,
+                            symbol.file,
+                            symbol.dependents.join(", ")
+                        ));
+                        result.removed_symbols.push(symbol);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => result.errors.push(format!("Failed to parse {}: {}", change.file_path, e)),
             }
 
-            // Import validation (最優先)
-            let import_issues = self.validate_imports(&change.modified_content, graph);
+            // Import validation (最優先): `use` paths for Rust, import specifiers otherwise
+            let import_issues = if file_path.extension().is_some_and(|ext| ext == "rs") {
+                let modules = rust_modules.get_or_insert_with(|| RustModuleTree::build(&self.project_dir));
+                self.validate_rust_uses(modules, &change.file_path, &resulting, changes, graph)
+            } else {
+                self.validate_imports(&mut resolver, &change.file_path, &resulting, &pending_files, graph)
+            };
             if !import_issues.is_empty() {
                 result.import_issues.extend(import_issues);
                 result.status = ValidationStatus::Error;
             }
-
-            // Detect new functions
-            let new_funcs = self.detect_new_functions(&change.modified_content, &file_path)?;
-            if !new_funcs.is_empty() {
-                result.warnings.push(format!("New functions added in {}: {}", 
-                    change.file_path, new_funcs.join(", ")));
-                result.new_functions.extend(new_funcs);
-            }
-
-            // Detect modified functions
-            let modified_funcs = self.detect_modified_functions(&change.modified_content, &file_path)?;
-            if !modified_funcs.is_empty() {
-                result.warnings.push(format!("Functions modified in {}: {}", 
-                    change.file_path, modified_funcs.join(", ")));
-                result.affected_functions.extend(modified_funcs);
-            }
         }
 
         // Set status based on errors
@@ -170,40 +259,78 @@ impl ChangeManager {
         Ok(result)
     }
 
-    /// Check TypeScript syntax
-    fn check_syntax(&self, content: &str) -> Result<()> {
-        // Basic syntax checks
-        let mut brace_count = 0;
-        let mut paren_count = 0;
-        let mut bracket_count = 0;
-
-        for ch in content.chars() {
-            match ch {
-                '{' => brace_count += 1,
-                '}' => brace_count -= 1,
-                '(' => paren_count += 1,
-                ')' => paren_count -= 1,
-                '[' => bracket_count += 1,
-                ']' => bracket_count -= 1,
-                _ => {}
+    /// Whole content of a file of the change set once its change is applied
+    fn resulting_file(&self, change: &FileChange) -> String {
+        let current = match &change.original_content {
+            Some(content) => Some(content.clone()),
+            None => std::fs::read_to_string(self.project_dir.join(&change.file_path)).ok(),
+        };
+        resulting_content(change, current.as_deref())
+    }
+
+    /// Which of the `removed` definitions of `file_path` the graph still has dependents for.
+    /// A dependent in a file of the same change set counts only if its new content still names the symbol.
+    fn removed_with_dependents(
+        &self,
+        relative: &str,
+        file_path: &Path,
+        removed: &[&FunctionInfo],
+        changes: &[FileChange],
+        graph: &CodeGraph,
+    ) -> Vec<RemovedSymbol> {
+        let same_file = |a: &Path, b: &Path| {
+            a == b || matches!((std::fs::canonicalize(a), std::fs::canonicalize(b)), (Ok(a), Ok(b)) if a == b)
+        };
+        let still_mentions = |path: &Path, name: &str| {
+            match changes.iter().find(|c| same_file(&self.project_dir.join(&c.file_path), path)) {
+                Some(change) => self
+                    .resulting_file(change)
+                    .lines()
+                    .any(|line| word_columns(line, name).next().is_some()),
+                None => true,
             }
-        }
+        };
 
-        if brace_count != 0 {
-            return Err(anyhow!("Unmatched braces: {}", brace_count));
-        }
-        if paren_count != 0 {
-            return Err(anyhow!("Unmatched parentheses: {}", paren_count));
-        }
-        if bracket_count != 0 {
-            return Err(anyhow!("Unmatched brackets: {}", bracket_count));
+        let mut symbols = Vec::new();
+        for function in removed {
+            let Some(node) = graph.nodes_by_name(&function.name).find(|n| {
+                same_file(&n.file_path, file_path) && n.line_range.0 == function.line_range.0
+            }) else {
+                continue;
+            };
+            let mut dependents: Vec<String> = graph
+                .incoming_edges(node.id)
+                .filter_map(|edge| graph.nodes.get(&edge.from))
+                .filter(|caller| {
+                    let removed_too = same_file(&caller.file_path, file_path)
+                        && removed.iter().any(|r| r.name == caller.name && r.line_range.0 == caller.line_range.0);
+                    !removed_too && still_mentions(&caller.file_path, &function.name)
+                })
+                .map(|caller| format!("{} ({}:{})", caller.name, caller.file_path.display(), caller.line_range.0))
+                .collect();
+            dependents.sort();
+            dependents.dedup();
+            if !dependents.is_empty() {
+                symbols.push(RemovedSymbol {
+                    file: relative.to_string(),
+                    name: function.name.clone(),
+                    dependents,
+                });
+            }
         }
-
-        Ok(())
+        symbols
     }
 
     /// Validate imports against dependency graph
-    fn validate_imports(&self, content: &str, graph: &CodeGraph) -> Vec<ImportIssue> {
+    fn validate_imports(
+        &self,
+        resolver: &mut ModuleResolver,
+        file_path: &str,
+        content: &str,
+        pending_files: &[PathBuf],
+        graph: &CodeGraph,
+    ) -> Vec<ImportIssue> {
+        let from_file = self.project_dir.join(file_path);
         let mut issues = Vec::new();
 
         // Extract import statements
@@ -213,12 +340,12 @@ impl ChangeManager {
                 // Parse import path
                 if let Some(import_path) = self.extract_import_path(trimmed) {
                     // Check if import exists in graph
-                    if !self.import_exists_in_graph(&import_path, graph) {
+                    if !self.import_exists_in_graph(resolver, &from_file, &import_path, pending_files, graph) {
                         issues.push(ImportIssue {
-                            file: "".to_string(),
+                            file: file_path.to_string(),
                             line: line_num + 1,
                             import_path: import_path.clone(),
-                            issue: format!("Import '{}' not found in dependency graph", import_path),
+                            issue: format!("Import '{}' could not be resolved to a project file or package", import_path),
                         });
                     }
                 }
@@ -228,6 +355,69 @@ impl ChangeManager {
         issues
     }
 
+    /// Check the `crate::`, `self::` and `super::` paths of Rust `use` declarations against the
+    /// module tree and the graph. Paths into other crates (std, dependencies) are not checked.
+    fn validate_rust_uses(
+        &self,
+        modules: &RustModuleTree,
+        file_path: &str,
+        content: &str,
+        changes: &[FileChange],
+        graph: &CodeGraph,
+    ) -> Vec<ImportIssue> {
+        let from_file = self.project_dir.join(file_path);
+        let Ok(Some(mut parser)) = CodeParser::for_path(&from_file) else {
+            return Vec::new();
+        };
+        let Ok(tree) = parser.parse_source(content) else {
+            return Vec::new();
+        };
+        let mut paths = Vec::new();
+        collect_use_paths(tree.root_node(), content, &mut paths);
+
+        // A name is defined in a module file if the file, as its change leaves it, mentions it
+        let defined_in = |module: &str, name: &str| {
+            let Some(file) = modules.module_file(module) else {
+                return false;
+            };
+            let mentions = |text: &str| text.lines().any(|line| word_columns(line, name).next().is_some());
+            match changes.iter().rfind(|c| normalize_path(&self.project_dir.join(&c.file_path)) == file) {
+                Some(change) => mentions(&self.resulting_file(change)),
+                None => std::fs::read_to_string(file).is_ok_and(|source| mentions(&source)),
+            }
+        };
+        // The longest known prefix decides: a module must define the next segment,
+        // an item (or inline module) of the graph is trusted with its members
+        let resolves = |absolute: &str| {
+            let segments: Vec<&str> = absolute.split("::").collect();
+            for len in (1..=segments.len()).rev() {
+                let prefix = segments[..len].join("::");
+                if modules.has_module(&prefix) {
+                    return len == segments.len() || defined_in(&prefix, segments[len]);
+                }
+                let nested = format!("{}::", prefix);
+                if graph.nodes.values().any(|n| n.qualified_name == prefix || n.qualified_name.starts_with(&nested)) {
+                    return true;
+                }
+            }
+            // Not even the crate is known (no Cargo.toml): nothing to check against
+            true
+        };
+
+        let module = modules.module_path(&from_file);
+        paths
+            .into_iter()
+            .filter(|(path, _)| matches!(path.split("::").next(), Some("crate" | "self" | "super")))
+            .filter(|(path, _)| !resolves(&modules.absolute_path(&module, path)))
+            .map(|(path, line)| ImportIssue {
+                file: file_path.to_string(),
+                line,
+                issue: format!("`use {}` does not name a module or item of the crate", path),
+                import_path: path,
+            })
+            .collect()
+    }
+
     /// Extract import path from import statement
     fn extract_import_path(&self, line: &str) -> Option<String> {
         // Simple regex-like extraction
@@ -244,93 +434,35 @@ impl ChangeManager {
         None
     }
 
-    /// Check if import exists in dependency graph
-    fn import_exists_in_graph(&self, import_path: &str, graph: &CodeGraph) -> bool {
-        // Check if any node's file path matches the import
-        // This is a simplified check - in production, you'd want more sophisticated resolution
-        
-        // Skip node_modules and built-in imports
-        if import_path.starts_with('.') || import_path.contains("node_modules") {
-            return true; // Assume relative imports are valid
-        }
-
-        // Check if any file in graph matches
-        graph.nodes.values().any(|node| {
-            node.file_path.to_string_lossy().contains(import_path)
-        })
-    }
-
-    /// Detect new functions in modified content
-    fn detect_new_functions(&self, content: &str, file_path: &Path) -> Result<Vec<String>> {
-        let mut new_functions = Vec::new();
-
-        // Simple function detection
-        for line in content.lines() {
-            let trimmed = line.trim();
-            if trimmed.starts_with("function ") || trimmed.contains("const ") && trimmed.contains(" = ") {
-                if let Some(func_name) = self.extract_function_name(trimmed) {
-                    // Check if it exists in current file
-                    if !self.function_exists_in_file(&func_name, file_path)? {
-                        new_functions.push(func_name);
-                    }
-                }
-            }
-        }
-
-        Ok(new_functions)
-    }
-
-    /// Detect modified functions
-    fn detect_modified_functions(&self, content: &str, file_path: &Path) -> Result<Vec<String>> {
-        let mut modified_functions = Vec::new();
-
-        // Simple function detection
-        for line in content.lines() {
-            let trimmed = line.trim();
-            if trimmed.starts_with("function ") || trimmed.contains("const ") && trimmed.contains(" = ") {
-                if let Some(func_name) = self.extract_function_name(trimmed) {
-                    // Check if it exists in current file
-                    if self.function_exists_in_file(&func_name, file_path)? {
-                        modified_functions.push(func_name);
-                    }
-                }
+    /// Check if import resolves to a file in the graph (or on disk), or to an installed package
+    fn import_exists_in_graph(
+        &self,
+        resolver: &mut ModuleResolver,
+        from_file: &Path,
+        import_path: &str,
+        pending_files: &[PathBuf],
+        graph: &CodeGraph,
+    ) -> bool {
+        match resolver.resolve(from_file, import_path) {
+            Resolution::File(path) => {
+                // Files without definitions (types only, assets) are not in the graph
+                graph.nodes.values().any(|node| node.file_path == path) || path.exists()
             }
-        }
-
-        Ok(modified_functions)
-    }
-
-    /// Extract function name from line
-    fn extract_function_name(&self, line: &str) -> Option<String> {
-        if line.starts_with("function ") {
-            let after_func = &line[9..];
-            if let Some(paren_pos) = after_func.find('(') {
-                return Some(after_func[..paren_pos].trim().to_string());
-            }
-        } else if line.contains("const ") {
-            if let Some(const_pos) = line.find("const ") {
-                let after_const = &line[const_pos + 6..];
-                if let Some(eq_pos) = after_const.find('=') {
-                    return Some(after_const[..eq_pos].trim().to_string());
-                }
+            Resolution::External(_) => true,
+            Resolution::Unresolved => {
+                // The import may point to a file created in the same change set
+                let base = from_file.parent().unwrap_or(Path::new(""));
+                let target = crate::resolver::normalize_path(&base.join(import_path));
+                pending_files.iter().any(|pending| {
+                    let pending = crate::resolver::normalize_path(pending);
+                    pending == target || pending.with_extension("") == target
+                })
             }
         }
-        None
     }
 
-    /// Check if function exists in file
-    fn function_exists_in_file(&self, func_name: &str, file_path: &Path) -> Result<bool> {
-        if !file_path.exists() {
-            return Ok(false);
-        }
-
-        let content = std::fs::read_to_string(file_path)?;
-        Ok(content.contains(&format!("function {}", func_name)) ||
-           content.contains(&format!("const {} =", func_name)))
-    }
-
-    /// Preview changes (generate diffs)
-    pub fn preview_changes(&self, changes: &[FileChange]) -> Result<PreviewResult> {
+    /// Preview changes (generate diffs); `inline` adds word-level changes for each file
+    pub fn preview_changes(&self, changes: &[FileChange], inline: bool) -> Result<PreviewResult> {
         let mut diffs = Vec::new();
         let mut total_additions = 0;
         let mut total_deletions = 0;
@@ -338,49 +470,26 @@ impl ChangeManager {
         for change in changes {
             let file_path = self.project_dir.join(&change.file_path);
 
-            // Handle partial vs full file changes
-            let (original, modified) = if let (Some(start), Some(end)) = (change.start_line, change.end_line) {
-                // Partial update: extract only affected lines
-                let full_content = if file_path.exists() {
-                    std::fs::read_to_string(&file_path)?
-                } else {
-                    String::new()
-                };
-                let lines: Vec<&str> = full_content.lines().collect();
-                let start_idx = start.saturating_sub(1);
-                let end_idx = end.min(lines.len());
-                let original_section = lines[start_idx..end_idx].join("\n");
-                (original_section, change.modified_content.clone())
-            } else {
-                // Full file replacement
-                let original = if let Some(orig) = &change.original_content {
-                    orig.clone()
-                } else if file_path.exists() {
-                    std::fs::read_to_string(&file_path)?
-                } else {
-                    String::new()
-                };
-                (original, change.modified_content.clone())
+            // Partial updates are spliced into the file, so the diff shows what will be written
+            let current = match &change.original_content {
+                Some(orig) if change.start_line.is_none() || change.end_line.is_none() => Some(orig.clone()),
+                _ if file_path.exists() => Some(std::fs::read_to_string(&file_path)?),
+                _ => None,
             };
+            let modified = resulting_content(change, current.as_deref());
+            let original = current.unwrap_or_default();
 
-            let diff = self.generate_diff(&original, &modified);
-            let (additions, deletions) = self.count_changes(&diff);
-
-            total_additions += additions;
-            total_deletions += deletions;
-
-            // Add line range info if partial update
-            let diff_header = if let (Some(start), Some(end)) = (change.start_line, change.end_line) {
-                format!("@@ Lines {}-{} @@\n{}", start, end, diff)
-            } else {
-                diff
-            };
+            let diff = unified_diff(&change.file_path, &original, &modified, 1);
+            total_additions += diff.additions;
+            total_deletions += diff.deletions;
 
             diffs.push(FileDiff {
                 file_path: change.file_path.clone(),
-                diff: diff_header,
-                additions,
-                deletions,
+                diff: diff.text,
+                additions: diff.additions,
+                deletions: diff.deletions,
+                base_hash: ScanCache::hash_file(&file_path).ok(),
+                inline: inline.then(|| inline_changes(&original, &modified, 1)),
             });
         }
 
@@ -391,127 +500,414 @@ impl ChangeManager {
         })
     }
 
-    /// Generate unified diff
-    fn generate_diff(&self, original: &str, modified: &str) -> String {
-        let mut diff = String::new();
-        let orig_lines: Vec<&str> = original.lines().collect();
-        let mod_lines: Vec<&str> = modified.lines().collect();
-
-        diff.push_str("--- original\n");
-        diff.push_str("+++ modified\n");
-
-        let max_len = orig_lines.len().max(mod_lines.len());
-        for i in 0..max_len {
-            let orig_line = orig_lines.get(i).copied().unwrap_or("");
-            let mod_line = mod_lines.get(i).copied().unwrap_or("");
+    /// Apply changes to files, all or nothing. Every file is checked against its `base_hash` and
+    /// written to a temporary file first; only then are they renamed into place. If a rename fails,
+    /// the files already replaced are restored.
+    pub fn apply_changes(&self, changes: &[FileChange], create_backup: bool) -> Result<ApplyResult> {
+        let staged = match self.stage_changes(changes) {
+            Ok(staged) => staged,
+            Err(failed_files) => {
+                return Ok(ApplyResult {
+                    success: false,
+                    applied_files: Vec::new(),
+                    failed_files,
+                    backup_dir: None,
+                    rolled_back: false,
+                    rollback_failed: Vec::new(),
+                })
+            }
+        };
 
-            if orig_line != mod_line {
-                if !orig_line.is_empty() {
-                    diff.push_str(&format!("-{}\n", orig_line));
-                }
-                if !mod_line.is_empty() {
-                    diff.push_str(&format!("+{}\n", mod_line));
+        let backup_dir = if create_backup {
+            match self.create_backup_dir().and_then(|dir| self.backup_files(&staged, &dir).map(|_| dir)) {
+                Ok(dir) => Some(dir),
+                Err(e) => {
+                    discard(&staged);
+                    return Err(e);
                 }
             }
-        }
+        } else {
+            None
+        };
 
-        diff
+        Ok(commit(staged, backup_dir))
     }
 
-    /// Count additions and deletions in diff
-    fn count_changes(&self, diff: &str) -> (usize, usize) {
-        let mut additions = 0;
-        let mut deletions = 0;
+    /// Create backup directory
+    fn create_backup_dir(&self) -> Result<PathBuf> {
+        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+        let backup_dir = self.project_dir.join(".index-chan").join("backups").join(timestamp.to_string());
+        std::fs::create_dir_all(&backup_dir)?;
+        Ok(backup_dir)
+    }
 
-        for line in diff.lines() {
-            if line.starts_with('+') && !line.starts_with("+++") {
-                additions += 1;
-            } else if line.starts_with('-') && !line.starts_with("---") {
-                deletions += 1;
+    fn backup_files(&self, staged: &[StagedFile], backup_dir: &Path) -> Result<()> {
+        for file in staged.iter().filter(|f| f.original.is_some()) {
+            let backup_file = backup_dir.join(&file.relative);
+            if let Some(parent) = backup_file.parent() {
+                std::fs::create_dir_all(parent)?;
             }
+            std::fs::copy(&file.path, backup_file)?;
         }
-
-        (additions, deletions)
+        Ok(())
     }
 
-    /// Apply changes to files
-    pub fn apply_changes(&self, changes: &[FileChange], create_backup: bool) -> Result<ApplyResult> {
-        let mut applied_files = Vec::new();
+    /// Check every change against the files on disk and write the results next to them.
+    /// Nothing is staged unless every change can be; changes to the same file (however its
+    /// path is spelled, e.g. `./a.ts` and `a.ts`) apply in order to one staged file.
+    fn stage_changes(&self, changes: &[FileChange]) -> Result<Vec<StagedFile>, Vec<FailedFile>> {
+        let mut staged: Vec<StagedFile> = Vec::new();
         let mut failed_files = Vec::new();
-        let backup_dir = if create_backup {
-            Some(self.create_backup_dir()?)
-        } else {
-            None
-        };
-
         for change in changes {
-            let file_path = self.project_dir.join(&change.file_path);
-            
-            match self.apply_single_change(change, &file_path, backup_dir.as_ref()) {
-                Ok(_) => {
-                    applied_files.push(change.file_path.clone());
-                }
-                Err(e) => {
-                    failed_files.push(FailedFile {
-                        file: change.file_path.clone(),
-                        error: e.to_string(),
-                    });
+            let path = self.target_path(change);
+            let index = match staged.iter().position(|f| f.path == path) {
+                Some(index) => index,
+                None => match self.read_target(change) {
+                    Ok(file) => {
+                        staged.push(file);
+                        staged.len() - 1
+                    }
+                    Err(e) => {
+                        failed_files.push(FailedFile { file: change.file_path.clone(), error: e.to_string() });
+                        continue;
+                    }
+                },
+            };
+            if let Err(e) = staged[index].apply(change) {
+                failed_files.push(FailedFile { file: change.file_path.clone(), error: e.to_string() });
+            }
+        }
+
+        if failed_files.is_empty() {
+            for file in &staged {
+                if let Err(e) = file.write_temp() {
+                    failed_files.push(FailedFile { file: file.relative.clone(), error: e.to_string() });
                 }
             }
         }
+        if !failed_files.is_empty() {
+            discard(&staged);
+            return Err(failed_files);
+        }
+        Ok(staged)
+    }
+
+    /// Where `change` writes, with `.` and `..` components and repeated separators gone
+    fn target_path(&self, change: &FileChange) -> PathBuf {
+        normalize_path(&self.project_dir.join(&change.file_path))
+    }
 
-        Ok(ApplyResult {
-            success: failed_files.is_empty(),
-            applied_files,
-            failed_files,
-            backup_dir: backup_dir.map(|p| p.display().to_string()),
+    fn read_target(&self, change: &FileChange) -> Result<StagedFile> {
+        let path = self.target_path(change);
+        let original = if path.exists() { Some(std::fs::read_to_string(&path)?) } else { None };
+        let parent = path.parent().unwrap_or(Path::new("."));
+        let file_name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        Ok(StagedFile {
+            relative: change.file_path.clone(),
+            temp_path: parent.join(format!(".{}.index-chan-tmp", file_name)),
+            missing_dirs: parent.ancestors().take_while(|dir| !dir.exists()).map(Path::to_path_buf).collect(),
+            path,
+            content: original.clone(),
+            original,
         })
     }
+}
 
-    /// Create backup directory
-    fn create_backup_dir(&self) -> Result<PathBuf> {
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        let backup_dir = self.project_dir.join(".index-chan").join("backups").join(timestamp.to_string());
-        std::fs::create_dir_all(&backup_dir)?;
-        Ok(backup_dir)
+/// A file's new content, written to a temporary file until every change is ready
+struct StagedFile {
+    relative: String,
+    path: PathBuf,
+    temp_path: PathBuf,
+    /// Directories `write_temp` creates for the file, innermost first
+    missing_dirs: Vec<PathBuf>,
+    /// Content before the change (None: the change creates the file)
+    original: Option<String>,
+    content: Option<String>,
+}
+
+impl StagedFile {
+    fn apply(&mut self, change: &FileChange) -> Result<()> {
+        if let Some(expected) = &change.base_hash {
+            let found = self.original.as_ref().map(|content| ScanCache::hash_content(content.as_bytes()));
+            if found.as_ref() != Some(expected) {
+                return Err(anyhow!(
+                    "Conflict: {} changed since it was read (expected {}, found {})",
+                    change.file_path,
+                    expected,
+                    found.as_deref().unwrap_or("no file")
+                ));
+            }
+        }
+
+        if change.start_line.is_some() && change.end_line.is_some() && self.content.is_none() {
+            return Err(anyhow!("Cannot apply a line range to a missing file: {}", change.file_path));
+        }
+        self.content = Some(resulting_content(change, self.content.as_deref()));
+        Ok(())
     }
 
-    /// Apply single file change
-    fn apply_single_change(&self, change: &FileChange, file_path: &Path, backup_dir: Option<&PathBuf>) -> Result<()> {
-        // Create backup if requested
-        if let Some(backup) = backup_dir {
-            if file_path.exists() {
-                let relative = file_path.strip_prefix(&self.project_dir)?;
-                let backup_file = backup.join(relative);
-                if let Some(parent) = backup_file.parent() {
-                    std::fs::create_dir_all(parent)?;
-                }
-                std::fs::copy(file_path, backup_file)?;
+    fn write_temp(&self) -> Result<()> {
+        if let Some(parent) = self.temp_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.temp_path, self.content.as_deref().unwrap_or_default())?;
+        if let Ok(metadata) = std::fs::metadata(&self.path) {
+            // Keep the mode (e.g. executable scripts) of the file being replaced
+            let _ = std::fs::set_permissions(&self.temp_path, metadata.permissions());
+        }
+        Ok(())
+    }
+
+    /// Put back the content the file had, or remove it if the change created it
+    fn restore(&self) -> Result<()> {
+        match &self.original {
+            Some(original) => {
+                std::fs::write(&self.temp_path, original)?;
+                std::fs::rename(&self.temp_path, &self.path)?;
+            }
+            None => {
+                std::fs::remove_file(&self.path)?;
+                self.remove_created_dirs();
             }
         }
+        Ok(())
+    }
 
-        // Apply change
-        if let (Some(start), Some(end)) = (change.start_line, change.end_line) {
-            // Partial file update
-            let original = std::fs::read_to_string(file_path)?;
-            let mut lines: Vec<&str> = original.lines().collect();
-            
-            let new_lines: Vec<&str> = change.modified_content.lines().collect();
-            lines.splice(start.saturating_sub(1)..end, new_lines);
-            
-            std::fs::write(file_path, lines.join("\n"))?;
-        } else {
-            // Full file replacement
-            if let Some(parent) = file_path.parent() {
-                std::fs::create_dir_all(parent)?;
+    /// Remove the directories created for the file, unless something else is in them now
+    fn remove_created_dirs(&self) {
+        for dir in &self.missing_dirs {
+            if std::fs::remove_dir(dir).is_err() {
+                break;
             }
-            std::fs::write(file_path, &change.modified_content)?;
         }
+    }
+}
 
-        Ok(())
+/// Rename the staged files into place; on the first failure, restore the ones already replaced
+fn commit(staged: Vec<StagedFile>, backup_dir: Option<PathBuf>) -> ApplyResult {
+    let mut failed_files = Vec::new();
+    let mut committed = 0;
+    for file in &staged {
+        if let Err(e) = std::fs::rename(&file.temp_path, &file.path) {
+            failed_files.push(FailedFile { file: file.relative.clone(), error: e.to_string() });
+            break;
+        }
+        committed += 1;
+    }
+
+    let success = failed_files.is_empty();
+    let mut rollback_failed = Vec::new();
+    if !success {
+        discard(&staged[committed..]);
+        for file in staged[..committed].iter().rev() {
+            if let Err(e) = file.restore() {
+                failed_files.push(FailedFile {
+                    file: file.relative.clone(),
+                    error: format!("Rollback failed: {}", e),
+                });
+                rollback_failed.push(file.relative.clone());
+            }
+        }
+    }
+
+    ApplyResult {
+        success,
+        applied_files: if success { staged.into_iter().map(|f| f.relative).collect() } else { Vec::new() },
+        failed_files,
+        backup_dir: backup_dir.map(|p| p.display().to_string()),
+        rolled_back: !success && rollback_failed.is_empty(),
+        rollback_failed,
+    }
+}
+
+fn discard(staged: &[StagedFile]) {
+    for file in staged {
+        let _ = std::fs::remove_file(&file.temp_path);
+        file.remove_created_dirs();
+    }
+}
+
+/// "\r\n" when the text uses Windows line endings, "\n" otherwise
+fn line_ending(text: &str) -> &'static str {
+    if text.contains("\r\n") {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+fn with_line_ending(text: &str, ending: &str) -> String {
+    let normalized = text.replace("\r\n", "\n");
+    if ending == "\n" {
+        normalized
+    } else {
+        normalized.replace('\n', ending)
+    }
+}
+
+/// Replace 1-based lines `start..=end` of `original` with `replacement`, keeping the file's
+/// line endings and whether it ends with a line break
+fn splice_lines(original: &str, start: usize, end: usize, replacement: &str) -> String {
+    let ending = line_ending(original);
+    let mut lines: Vec<&str> = original.lines().collect();
+    let end = end.min(lines.len());
+    let start = start.saturating_sub(1).min(end);
+    lines.splice(start..end, replacement.lines());
+
+    let mut content = lines.join(ending);
+    if original.ends_with('\n') && !content.is_empty() {
+        content.push_str(ending);
+    }
+    content
+}
+
+/// File content once `change` is applied to `current` (None: the file does not exist yet).
+/// What `apply_changes` writes, and what previews and validation look at.
+fn resulting_content(change: &FileChange, current: Option<&str>) -> String {
+    match (change.start_line, change.end_line, current) {
+        (Some(start), Some(end), current) => {
+            splice_lines(current.unwrap_or_default(), start, end, &change.modified_content)
+        }
+        // Full replacement, in the line endings the file already uses
+        (_, _, Some(current)) => with_line_ending(&change.modified_content, line_ending(current)),
+        (_, _, None) => change.modified_content.clone(),
+    }
+}
+
+/// Syntax errors and definitions of `content` parsed as the file at `path`
+struct ParsedContent {
+    errors: Vec<crate::parser::SyntaxError>,
+    functions: Vec<FunctionInfo>,
+}
+
+/// None when no parser handles the file's language
+fn parse_definitions(path: &Path, content: &str) -> Result<Option<ParsedContent>> {
+    let Some(mut parser) = CodeParser::for_path(path)? else {
+        return Ok(None);
+    };
+    // Vue/Svelte: only the <script> blocks (line numbers are kept)
+    let script = match SfcKind::from_path(path) {
+        Some(kind) => parse_sfc(content, kind).script_source,
+        None => content.to_string(),
+    };
+    let tree = parser.parse_source(&script)?;
+    Ok(Some(ParsedContent {
+        errors: syntax_errors(&tree, &script),
+        functions: parser.extract_functions(&tree, &script),
+    }))
+}
+
+/// Named definitions that the change made appear more than once in the same scope
+fn duplicate_definitions(before: &[FunctionInfo], after: &[FunctionInfo]) -> Vec<String> {
+    let count = |functions: &[FunctionInfo]| {
+        let mut counts: HashMap<(Vec<String>, String), Vec<usize>> = HashMap::new();
+        for f in functions {
+            counts.entry((f.scope.clone(), f.name.clone())).or_default().push(f.line_range.0);
+        }
+        counts
+    };
+    let before = count(before);
+    let mut duplicates: Vec<String> = count(after)
+        .into_iter()
+        .filter(|(key, lines)| lines.len() > 1 && lines.len() > before.get(key).map_or(0, Vec::len))
+        .map(|((scope, name), lines)| {
+            let qualified = scope.iter().chain(std::iter::once(&name)).cloned().collect::<Vec<_>>().join("::");
+            let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+            format!("{} (lines {})", qualified, lines.join(", "))
+        })
+        .collect();
+    duplicates.sort();
+    duplicates
+}
+
+/// Definitions of `after` that `before` has too (same scope and name) with other source lines
+fn modified_definitions<'a>(
+    before: &[FunctionInfo],
+    original: &str,
+    after: &'a [FunctionInfo],
+    resulting: &str,
+) -> Vec<&'a FunctionInfo> {
+    let source = |content: &str, f: &FunctionInfo| -> Vec<String> {
+        let (start, end) = f.line_range;
+        content
+            .lines()
+            .skip(start.saturating_sub(1))
+            .take((end + 1).saturating_sub(start))
+            .map(|line| line.trim_end().to_string())
+            .collect()
+    };
+    after
+        .iter()
+        .filter(|f| {
+            before
+                .iter()
+                .find(|b| b.name == f.name && b.scope == f.scope)
+                .is_some_and(|b| source(original, b) != source(resulting, f))
+        })
+        .collect()
+}
+
+/// `Scope::name` of a definition
+fn qualified(function: &FunctionInfo) -> String {
+    function.scope.iter().chain(std::iter::once(&function.name)).cloned().collect::<Vec<_>>().join("::")
+}
+
+/// Paths brought in by the Rust `use` declarations under `node`, with their lines
+/// (`use crate::a::{b, c::d as e}` gives `crate::a::b` and `crate::a::c::d`)
+fn collect_use_paths(node: Node, source: &str, paths: &mut Vec<(String, usize)>) {
+    if node.kind() == "use_declaration" {
+        if let Some(argument) = node.child_by_field_name("argument") {
+            expand_use_tree(argument, source, "", paths);
+        }
+        return;
+    }
+    let mut cursor = node.walk();
+    for child in node.named_children(&mut cursor) {
+        collect_use_paths(child, source, paths);
+    }
+}
+
+fn expand_use_tree(node: Node, source: &str, prefix: &str, paths: &mut Vec<(String, usize)>) {
+    let text = |n: Node| n.utf8_text(source.as_bytes()).unwrap_or_default().to_string();
+    let join = |path: String| if prefix.is_empty() { path } else { format!("{}::{}", prefix, path) };
+    let line = node.start_position().row + 1;
+    match node.kind() {
+        "scoped_use_list" => {
+            let base = node.child_by_field_name("path").map_or(prefix.to_string(), |p| join(text(p)));
+            if let Some(list) = node.child_by_field_name("list") {
+                expand_use_tree(list, source, &base, paths);
+            }
+        }
+        "use_list" => {
+            let mut cursor = node.walk();
+            for child in node.named_children(&mut cursor).filter(|c| !c.kind().ends_with("comment")) {
+                expand_use_tree(child, source, prefix, paths);
+            }
+        }
+        "use_as_clause" => {
+            if let Some(path) = node.child_by_field_name("path") {
+                expand_use_tree(path, source, prefix, paths);
+            }
+        }
+        // `a::*` is checked as `a`, a bare `*` or `self` in a list as the list's prefix
+        "use_wildcard" => match node.named_child(0) {
+            Some(path) => expand_use_tree(path, source, prefix, paths),
+            None => paths.push((prefix.to_string(), line)),
+        },
+        "self" if !prefix.is_empty() => paths.push((prefix.to_string(), line)),
+        _ => paths.push((join(text(node)), line)),
     }
 }
 
+/// Definitions of `defined` with no definition of the same scope and name in `other`
+/// (removed ones for (before, after), added ones for (after, before))
+fn definitions_missing_from<'a>(defined: &'a [FunctionInfo], other: &[FunctionInfo]) -> Vec<&'a FunctionInfo> {
+    defined
+        .iter()
+        .filter(|f| !other.iter().any(|o| o.name == f.name && o.scope == f.scope))
+        .collect()
+}
+
 /// Parse context file format (for LLM responses)
 pub fn parse_context_file(content: &str) -> Result<HashMap<String, FileChange>> {
     let mut changes = HashMap::new();
@@ -529,6 +925,7 @@ pub fn parse_context_file(content: &str) -> Result<HashMap<String, FileChange>>
                     modified_content: current_content.clone(),
                     start_line: None,
                     end_line: None,
+                    base_hash: None,
                 });
                 current_content.clear();
             }
@@ -548,6 +945,7 @@ pub fn parse_context_file(content: &str) -> Result<HashMap<String, FileChange>>
                     modified_content: current_content.clone(),
                     start_line: None,
                     end_line: None,
+                    base_hash: None,
                 });
             }
             in_file = false;
@@ -559,3 +957,202 @@ pub fn parse_context_file(content: &str) -> Result<HashMap<String, FileChange>>
 
     Ok(changes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_project;
+
+    fn change(file_path: &str, content: &str) -> FileChange {
+        FileChange {
+            file_path: file_path.to_string(),
+            original_content: None,
+            modified_content: content.to_string(),
+            start_line: None,
+            end_line: None,
+            base_hash: None,
+        }
+    }
+
+    #[test]
+    fn test_validation_parses_each_language() {
+        let temp = temp_project();
+        let dir = temp.path();
+        std::fs::write(dir.join("util.ts"), "export function greet() {\n  return 1;\n}\n").unwrap();
+        std::fs::write(dir.join("main.ts"), "import { greet } from './util';\nexport function main() {\n  greet();\n}\n").unwrap();
+        std::fs::write(dir.join("view.tsx"), "export const a = 1;\n").unwrap();
+        std::fs::write(dir.join("lib.rs"), "pub fn run() {}\n").unwrap();
+        let mut manager = ChangeManager::from_directory(dir).unwrap();
+
+        // Braces inside strings and JSX are fine
+        let valid = manager
+            .validate_changes(&[
+                change("util.ts", "export function greet() {\n  return `{${1}`;\n}\n"),
+                change("view.tsx", "export const View = () => <div className=\"a\">{'}'}</div>;\n"),
+            ])
+            .unwrap();
+        assert!(valid.errors.is_empty(), "{:?}", valid.errors);
+
+        // Rust is parsed as Rust, and every error has a position
+        let broken = manager.validate_changes(&[change("lib.rs", "pub fn run() {\n    let x = ;\n}\n")]).unwrap();
+        assert_eq!(broken.status, ValidationStatus::Error);
+        assert_eq!(broken.syntax_errors.len(), 1);
+        assert_eq!(broken.syntax_errors[0].line, 2);
+
+        // Removing a function that main.ts still calls, unless main.ts stops calling it
+        let removed = manager.validate_changes(&[change("util.ts", "export const other = 1;\n")]).unwrap();
+        assert_eq!(removed.removed_symbols.len(), 1);
+        assert_eq!(removed.removed_symbols[0].name, "greet");
+        assert!(removed.removed_symbols[0].dependents[0].starts_with("main ("));
+        let together = manager
+            .validate_changes(&[
+                change("util.ts", "export const other = 1;\n"),
+                change("main.ts", "export function main() {\n}\n"),
+            ])
+            .unwrap();
+        assert!(together.removed_symbols.is_empty());
+        // Editing only the signature line of main keeps its call
+        let mut signature = change("main.ts", "export function main(): void {");
+        signature.start_line = Some(2);
+        signature.end_line = Some(2);
+        let kept = manager
+            .validate_changes(&[change("util.ts", "export const other = 1;\n"), signature])
+            .unwrap();
+        assert_eq!(kept.removed_symbols.len(), 1);
+        assert_eq!(kept.removed_symbols[0].name, "greet");
+
+        let duplicated = manager
+            .validate_changes(&[change("util.ts", "export function greet() {}\nfunction greet() {}\n")])
+            .unwrap();
+        assert!(duplicated.warnings.iter().any(|w| w.contains("Duplicate definition in util.ts: greet (lines 1, 2)")));
+
+        // Imports of a line-range change are checked in the whole file, at file line numbers
+        let mut partial = change("main.ts", "import { nope } from './nope';\nexport function main() {");
+        partial.start_line = Some(2);
+        partial.end_line = Some(2);
+        let imports = manager.validate_changes(&[partial]).unwrap();
+        let issues: Vec<(usize, &str)> = imports.import_issues.iter().map(|i| (i.line, i.import_path.as_str())).collect();
+        assert_eq!(issues, vec![(2, "./nope")]);
+    }
+
+    #[test]
+    fn test_definitions_and_rust_uses_come_from_the_parse() {
+        let temp = temp_project();
+        let dir = temp.path();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"demo\"\nversion = \"0.1.0\"\n").unwrap();
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "mod util;\npub fn run() {}\n").unwrap();
+        std::fs::write(dir.join("src/util.rs"), "pub fn helper() {}\npub fn other() {}\n").unwrap();
+        let mut manager = ChangeManager::from_directory(dir).unwrap();
+
+        // Rust `fn`s are seen, a `const` is not a function
+        let result = manager
+            .validate_changes(&[change(
+                "src/util.rs",
+                "pub fn helper() {\n    other();\n}\npub fn other() {}\nfn added() {}\nconst MAX: u32 = 1;\n",
+            )])
+            .unwrap();
+        assert_eq!(result.new_functions, vec!["added"]);
+        assert_eq!(result.affected_functions, vec!["helper"]);
+
+        // Only crate paths are checked, against the module files
+        let uses = manager
+            .validate_changes(&[change(
+                "src/lib.rs",
+                "mod util;\nuse crate::util::{helper, missing};\nuse self::util::other as o;\nuse std::fmt;\npub fn run() {}\n",
+            )])
+            .unwrap();
+        let paths: Vec<&str> = uses.import_issues.iter().map(|i| i.import_path.as_str()).collect();
+        assert_eq!(paths, vec!["crate::util::missing"]);
+        assert_eq!(uses.import_issues[0].line, 2);
+
+        // A module file is read as its change leaves it: a line edit keeps the rest of the file,
+        // and a name the edit removes is gone although the file on disk still has it
+        std::fs::write(dir.join("src/util.rs"), "pub fn helper() {}\npub fn other() {}\npub const LIMIT: u32 = 1;\n").unwrap();
+        let uses_limit = change("src/lib.rs", "mod util;\nuse crate::util::LIMIT;\npub fn run() {}\n");
+        let mut edit = change("src/util.rs", "pub fn other() -> u32 { 1 }");
+        edit.start_line = Some(2);
+        edit.end_line = Some(2);
+        let uses = manager.validate_changes(&[edit, uses_limit.clone()]).unwrap();
+        assert!(uses.import_issues.is_empty(), "{:?}", uses.import_issues);
+        let mut edit = change("src/util.rs", "pub const MAX: u32 = 1;");
+        edit.start_line = Some(3);
+        edit.end_line = Some(3);
+        let uses = manager.validate_changes(&[edit, uses_limit]).unwrap();
+        let paths: Vec<&str> = uses.import_issues.iter().map(|i| i.import_path.as_str()).collect();
+        assert_eq!(paths, vec!["crate::util::LIMIT"]);
+    }
+
+    #[test]
+    fn test_apply_is_conflict_checked_and_all_or_nothing() {
+        let temp = temp_project();
+        let dir = temp.path();
+        let original = "fn a() {}\r\nfn b() {}\r\nfn c() {}\r\n";
+        std::fs::write(dir.join("lib.rs"), original).unwrap();
+        std::fs::write(dir.join("other.rs"), "fn d() {}\n").unwrap();
+        let manager = ChangeManager::from_directory(dir).unwrap();
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+
+        // A stale base_hash refuses every change, including the valid one
+        let mut edit = change("lib.rs", "fn b2() {}\n");
+        edit.start_line = Some(2);
+        edit.end_line = Some(2);
+        edit.base_hash = Some(ScanCache::hash_content(b"fn a() {}\n"));
+        let result = manager.apply_changes(&[change("other.rs", "fn e() {}\n"), edit.clone()], false).unwrap();
+        assert!(!result.success);
+        assert!(result.failed_files[0].error.starts_with("Conflict: lib.rs"));
+        assert_eq!(read("other.rs"), "fn d() {}\n");
+        assert_eq!(std::fs::read_dir(dir).unwrap().count(), 2);
+
+        // Line endings and the trailing newline survive a partial edit
+        edit.base_hash = Some(ScanCache::hash_content(original.as_bytes()));
+        let result = manager.apply_changes(&[edit], true).unwrap();
+        assert!(result.success, "{:?}", result.failed_files);
+        assert_eq!(read("lib.rs"), "fn a() {}\r\nfn b2() {}\r\nfn c() {}\r\n");
+
+        // A failed rename puts back the files already replaced and removes created ones
+        // with their directories
+        let mut staged = manager
+            .stage_changes(&[change("other.rs", "fn e() {}\n"), change("gen/new/new.rs", "fn f() {}\n"), change("lib.rs", "")])
+            .unwrap();
+        std::fs::remove_file(&staged[2].temp_path).unwrap();
+        staged[2].temp_path = dir.join("missing");
+        let result = commit(staged, None);
+        assert!(result.rolled_back && !result.success && result.rollback_failed.is_empty());
+        assert_eq!(read("other.rs"), "fn d() {}\n");
+        assert!(!dir.join("gen").exists());
+        assert_eq!(read("lib.rs"), "fn a() {}\r\nfn b2() {}\r\nfn c() {}\r\n");
+
+        // Two spellings of one path are one file: the edits apply in order
+        let mut second = change("./other.rs", "fn e2() {}");
+        second.start_line = Some(2);
+        second.end_line = Some(2);
+        let staged = manager
+            .stage_changes(&[change("other.rs", "fn d() {}\nfn e() {}\n"), second])
+            .unwrap();
+        assert_eq!(staged.len(), 1);
+        assert_eq!(staged[0].content.as_deref(), Some("fn d() {}\nfn e2() {}\n"));
+        discard(&staged);
+    }
+
+    #[test]
+    fn test_preview_shows_what_apply_writes() {
+        let temp = temp_project();
+        let dir = temp.path();
+        let original = "fn a() {}\r\nfn b() {}\r\n";
+        std::fs::write(dir.join("lib.rs"), original).unwrap();
+        let mut manager = ChangeManager::from_directory(dir).unwrap();
+
+        // A full replacement written with "\n" keeps the file's "\r\n"
+        let edit = change("lib.rs", "fn a() {}\nfn b2() {}\n");
+        let preview = manager.preview_changes(std::slice::from_ref(&edit), false).unwrap();
+        assert_eq!((preview.total_additions, preview.total_deletions), (1, 1));
+        let validation = manager.validate_changes(std::slice::from_ref(&edit)).unwrap();
+        assert!(validation.affected_functions.is_empty(), "{:?}", validation.affected_functions);
+
+        assert!(manager.apply_changes(&[edit], false).unwrap().success);
+        let written = std::fs::read_to_string(dir.join("lib.rs")).unwrap();
+        assert_eq!(written, "fn a() {}\r\nfn b2() {}\r\n");
+        assert_eq!(preview.diffs[0].diff, unified_diff("lib.rs", original, &written, 1).text);
+    }
+}
@@ -0,0 +1,274 @@
+// JSON-RPC 2.0 / MCP lifecycle conformance tests
+// スクリプト化したクライアントで stdio と同じ経路（Payload::parse → handle_payload）を検証する
+
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+
+use crate::mcp::protocol::{Payload, ServerMessage, SUPPORTED_PROTOCOL_VERSIONS};
+use crate::mcp::server::McpServer;
+use crate::test_support::temp_project;
+
+/// Sends lines the way an MCP client on stdio would and records what comes back
+struct ScriptedClient {
+    server: McpServer,
+    /// Server-initiated notifications and requests
+    sent: Arc<Mutex<Vec<ServerMessage>>>,
+}
+
+impl ScriptedClient {
+    fn new() -> Self {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&sent);
+        let mut server = McpServer::new(None);
+        server.set_notifier(Arc::new(move |m: &ServerMessage| sink.lock().unwrap().push(m.clone())));
+        Self { server, sent }
+    }
+
+    /// The reply line for `line`, if the server sends one
+    fn send(&mut self, line: &str) -> Option<Value> {
+        match Payload::parse(line.as_bytes()) {
+            Ok(mut payload) => {
+                self.server.in_flight().intercept(&mut payload);
+                self.server.handle_payload(payload)
+            }
+            Err(error) => Some(serde_json::to_value(error).unwrap()),
+        }
+    }
+
+    fn request(&mut self, id: Value, method: &str, params: Value) -> Value {
+        let line = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.send(&line.to_string()).expect("requests are answered")
+    }
+
+    fn notify(&mut self, method: &str, params: Value) -> Option<Value> {
+        self.send(&json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string())
+    }
+
+    fn initialize(&mut self, version: &str) -> Value {
+        self.initialize_with(version, json!({}))
+    }
+
+    fn initialize_with(&mut self, version: &str, capabilities: Value) -> Value {
+        let params = json!({
+            "protocolVersion": version,
+            "capabilities": capabilities,
+            "clientInfo": { "name": "conformance", "version": "1.0" }
+        });
+        let reply = self.request(json!(0), "initialize", params);
+        assert_eq!(self.notify("notifications/initialized", json!({})), None);
+        reply
+    }
+}
+
+fn error_code(reply: &Value) -> i64 {
+    reply["error"]["code"].as_i64().unwrap_or_else(|| panic!("not an error: {}", reply))
+}
+
+#[test]
+fn test_lifecycle_and_version_negotiation() {
+    let mut client = ScriptedClient::new();
+
+    // Only ping is allowed before initialize
+    let early = client.request(json!(1), "tools/list", json!({}));
+    assert_eq!(error_code(&early), -32600);
+    assert_eq!(early["id"], 1);
+    assert_eq!(client.request(json!(2), "ping", json!({}))["result"], json!({}));
+
+    // Missing protocolVersion is invalid
+    assert_eq!(error_code(&client.request(json!(3), "initialize", json!({}))), -32602);
+
+    let reply = client.initialize("2024-11-05");
+    assert_eq!(reply["result"]["protocolVersion"], "2024-11-05");
+    assert_eq!(reply["result"]["capabilities"]["logging"], json!({}));
+    assert!(client.request(json!(4), "tools/list", json!({}))["result"]["tools"].is_array());
+
+    // Unknown versions get the newest one we speak
+    let reply = ScriptedClient::new().initialize("1999-01-01");
+    assert_eq!(reply["result"]["protocolVersion"], SUPPORTED_PROTOCOL_VERSIONS[0]);
+}
+
+#[test]
+fn test_notifications_are_never_answered() {
+    let mut client = ScriptedClient::new();
+    // Even before initialize, and even when unknown or malformed
+    assert_eq!(client.notify("notifications/initialized", json!({})), None);
+    client.initialize("2025-06-18");
+    assert_eq!(client.notify("notifications/unknown", json!({})), None);
+    assert_eq!(client.notify("notifications/cancelled", json!({ "requestId": 42 })), None);
+    assert_eq!(client.notify("tools/call", json!({ "name": "nope" })), None);
+    // A client's response to a server request is not answered either
+    assert_eq!(client.send(r#"{"jsonrpc":"2.0","id":9,"result":{}}"#), None);
+}
+
+#[test]
+fn test_batches() {
+    let mut client = ScriptedClient::new();
+    client.initialize("2025-03-26");
+
+    let reply = client
+        .send(r#"[{"jsonrpc":"2.0","id":1,"method":"ping"},{"jsonrpc":"2.0","method":"notifications/initialized"},{"jsonrpc":"2.0","id":"b","method":"nope"}]"#)
+        .unwrap();
+    let replies = reply.as_array().unwrap();
+    assert_eq!(replies.len(), 2);
+    assert_eq!(replies[0]["id"], 1);
+    assert_eq!(replies[0]["result"], json!({}));
+    assert_eq!(replies[1]["id"], "b");
+    assert_eq!(error_code(&replies[1]), -32601);
+
+    // Only notifications: nothing at all comes back
+    assert_eq!(client.send(r#"[{"jsonrpc":"2.0","method":"notifications/initialized"}]"#), None);
+
+    let empty = client.send("[]").unwrap();
+    assert_eq!(error_code(&empty), -32600);
+    assert!(empty["id"].is_null());
+
+    // Invalid members are answered in place, and the result is still a batch
+    let reply = client.send(r#"[1, {"jsonrpc":"2.0","id":2,"method":"ping"}]"#).unwrap();
+    let replies = reply.as_array().unwrap();
+    assert_eq!(error_code(&replies[0]), -32600);
+    assert_eq!(replies[1]["id"], 2);
+}
+
+#[test]
+fn test_initialize_is_rejected_in_a_batch() {
+    let mut client = ScriptedClient::new();
+    let initialize = json!({
+        "jsonrpc": "2.0", "id": 1, "method": "initialize",
+        "params": { "protocolVersion": "2025-03-26", "capabilities": {}, "clientInfo": { "name": "c", "version": "1" } }
+    });
+    let batch = json!([initialize, { "jsonrpc": "2.0", "id": 2, "method": "ping" }]);
+    let reply = client.send(&batch.to_string()).unwrap();
+    let replies = reply.as_array().unwrap();
+    assert_eq!(error_code(&replies[0]), -32600);
+    assert_eq!(replies[0]["id"], 1);
+    assert_eq!(replies[1]["result"], json!({}));
+
+    // The session was not initialized by it
+    assert_eq!(error_code(&client.request(json!(3), "tools/list", json!({}))), -32600);
+}
+
+#[test]
+fn test_batches_are_rejected_after_2025_06_18() {
+    let mut client = ScriptedClient::new();
+    client.initialize("2025-06-18");
+
+    let reply = client.send(r#"[{"jsonrpc":"2.0","id":1,"method":"ping"}]"#).unwrap();
+    assert!(!reply.is_array());
+    assert_eq!(error_code(&reply), -32600);
+    assert!(reply["id"].is_null());
+    // Single messages still work
+    assert_eq!(client.request(json!(2), "ping", json!({}))["result"], json!({}));
+}
+
+#[test]
+fn test_errors_keep_the_request_id() {
+    let mut client = ScriptedClient::new();
+    client.initialize("2025-06-18");
+
+    // Unparseable JSON: the id is unknown and sent as null
+    let reply = client.send("{not json").unwrap();
+    assert_eq!(error_code(&reply), -32700);
+    assert!(reply.as_object().unwrap().contains_key("id") && reply["id"].is_null());
+
+    let cases = [
+        (r#"{"jsonrpc":"2.0","id":5}"#, json!(5)),
+        (r#"{"jsonrpc":"1.0","id":"a","method":"ping"}"#, json!("a")),
+        (r#"{"jsonrpc":"2.0","id":6,"method":3}"#, json!(6)),
+        (r#"{"jsonrpc":"2.0","id":7,"method":"ping","params":"x"}"#, json!(7)),
+        (r#"{"jsonrpc":"2.0","id":{"x":1},"method":"ping"}"#, Value::Null),
+        (r#"{"jsonrpc":"2.0","id":1.5,"method":"ping"}"#, Value::Null),
+    ];
+    for (line, id) in cases {
+        let reply = client.send(line).unwrap();
+        assert_eq!(error_code(&reply), -32600, "{}", line);
+        assert_eq!(reply["id"], id, "{}", line);
+    }
+
+    let reply = client.request(json!(8), "no/such/method", json!({}));
+    assert_eq!(error_code(&reply), -32601);
+    assert_eq!(reply["id"], 8);
+}
+
+#[test]
+fn test_logging_set_level() {
+    let mut client = ScriptedClient::new();
+    client.initialize("2025-06-18");
+
+    assert_eq!(error_code(&client.request(json!(1), "logging/setLevel", json!({ "level": "loud" }))), -32602);
+    assert_eq!(client.request(json!(2), "logging/setLevel", json!({ "level": "warning" }))["result"], json!({}));
+    client.notify("notifications/unknown", json!({}));
+    assert!(client.sent.lock().unwrap().is_empty());
+
+    client.request(json!(3), "logging/setLevel", json!({ "level": "debug" }));
+    client.notify("notifications/unknown", json!({}));
+    let sent = client.sent.lock().unwrap();
+    let message = sent.iter().find(|m| m.method() == "notifications/message").unwrap();
+    let params = message.params().unwrap();
+    assert_eq!(params["level"], "debug");
+    assert_eq!(params["logger"], "index-chan");
+    assert!(params["data"].as_str().unwrap().contains("notifications/unknown"));
+}
+
+#[test]
+fn test_roots() {
+    let temp = temp_project();
+    let base = temp.path();
+    for (root, function) in [("api", "handleOrder"), ("web", "renderOrder")] {
+        std::fs::create_dir_all(base.join(root)).unwrap();
+        let source = format!("export function {}() {{}}\n", function);
+        std::fs::write(base.join(root).join("order.ts"), source).unwrap();
+    }
+    let uri = |root: &str| format!("file://{}", base.join(root).display());
+
+    let mut client = ScriptedClient::new();
+    client.initialize_with("2025-06-18", json!({ "roots": { "listChanged": true } }));
+
+    // The server asks for the roots as soon as the client is initialized
+    let request_id = |client: &ScriptedClient| {
+        let sent = client.sent.lock().unwrap();
+        match sent.iter().rev().find(|m| m.method() == "roots/list") {
+            Some(ServerMessage::Request(request)) => request.id.clone().unwrap(),
+            other => panic!("no roots/list request: {:?}", other),
+        }
+    };
+    let id = request_id(&client);
+    let answer = json!({ "jsonrpc": "2.0", "id": id, "result": { "roots": [
+        { "uri": uri("api"), "name": "api" },
+        { "uri": uri("web") }
+    ]}});
+    assert_eq!(client.send(&answer.to_string()), None);
+
+    let call = |client: &mut ScriptedClient, name: &str, arguments: Value| {
+        client.request(json!(1), "tools/call", json!({ "name": name, "arguments": arguments }))["result"].clone()
+    };
+
+    // With several roots a tool needs to be told which one
+    let ambiguous = call(&mut client, "stats", json!({}));
+    assert_eq!(ambiguous["isError"], true);
+    assert!(ambiguous["content"][0]["text"].as_str().unwrap().contains("api, web"));
+    let stats = call(&mut client, "stats", json!({ "root": "web" }));
+    assert_eq!(stats["structuredContent"]["directory"], base.join("web").display().to_string());
+
+    // "*" searches every root and tags each result
+    let found = call(&mut client, "search", json!({ "root": "*", "query": "Order", "graph_depth": 0 }));
+    let mut tagged: Vec<(String, String)> = found["structuredContent"]["results"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| (r["root"].as_str().unwrap().to_string(), r["name"].as_str().unwrap().to_string()))
+        .collect();
+    tagged.sort();
+    assert_eq!(
+        tagged,
+        vec![("api".to_string(), "handleOrder".to_string()), ("web".to_string(), "renderOrder".to_string())]
+    );
+
+    // A change notification asks again; the new list replaces the old one
+    client.notify("notifications/roots/list_changed", json!({}));
+    let id = request_id(&client);
+    let answer = json!({ "jsonrpc": "2.0", "id": id, "result": { "roots": [{ "uri": uri("web") }] } });
+    client.send(&answer.to_string());
+    let stats = call(&mut client, "stats", json!({}));
+    assert_eq!(stats["structuredContent"]["directory"], base.join("web").display().to_string());
+    assert_eq!(call(&mut client, "stats", json!({ "root": "api" }))["isError"], true);
+}
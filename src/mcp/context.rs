@@ -2,11 +2,22 @@
 // gather_context の実装
 
 use std::path::Path;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 use anyhow::Result;
 
-use crate::graph::{CodeGraph, CodeNode, NodeId, NodeType};
-use crate::scanner::Scanner;
+use crate::graph::{CodeGraph, CodeNode, DependencyEdge, EdgeType, NodeId, NodeType, SourcePosition};
+use crate::mcp::tokens::count_tokens;
+use crate::search::GraphSearcher;
+
+/// Markers and line headers around each function in the rendered context
+const NODE_OVERHEAD_TOKENS: usize = 12;
+
+/// How many search hits seed a query-driven context
+const QUERY_SEEDS: usize = 5;
+
+/// Query words too common to say anything about a function name
+const QUERY_STOPWORDS: &[&str] = &["the", "and", "for", "with", "from", "into", "that", "this", "how", "what", "where"];
 
 /// Context output mode
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -38,13 +49,82 @@ pub struct ContextResult {
     pub total_lines: usize,
     /// Context quality metrics
     pub quality: ContextQuality,
+    /// Functions cut down to their signature to fit the token budget
+    pub skeletonized: Vec<String>,
+    /// Functions left out to fit the token budget
+    pub dropped: Vec<String>,
+}
+
+/// Nodes that fit a token budget, and which of them are shown as signatures only
+struct BudgetFit<'a> {
+    nodes: Vec<&'a CodeNode>,
+    skeletons: HashSet<NodeId>,
+}
+
+/// Node bodies of one context, read once: fitting the token budget renders it many times
+struct NodeBodies {
+    mode: ContextMode,
+    /// Code of each node (Full mode only)
+    full: HashMap<NodeId, String>,
+    /// Signature of each node
+    skeleton: HashMap<NodeId, String>,
+}
+
+impl NodeBodies {
+    /// Each file is read once, however many of the nodes it holds
+    fn read(nodes: &[&CodeNode], mode: ContextMode) -> Self {
+        let mut files: HashMap<&Path, Option<String>> = HashMap::new();
+        let mut bodies = Self { mode, full: HashMap::new(), skeleton: HashMap::new() };
+        for node in nodes {
+            let signature = if node.signature.is_empty() {
+                format!("{} {:?}\n", node.name, node.node_type)
+            } else {
+                format!("{}\n", node.signature)
+            };
+            bodies.skeleton.insert(node.id, signature);
+            if mode == ContextMode::Full {
+                let content = files
+                    .entry(&node.file_path)
+                    .or_insert_with(|| std::fs::read_to_string(&node.file_path).ok());
+                let code = match content {
+                    Some(content) => format!("{}\n", line_range(content, node.line_range)),
+                    None => format!("// {} {:?}\n", node.name, node.node_type),
+                };
+                bodies.full.insert(node.id, code);
+            }
+        }
+        bodies
+    }
+
+    /// Code of a node (Full) or its signature (Skeleton, or `skeleton`), ending with a line break
+    fn get(&self, id: NodeId, skeleton: bool) -> &str {
+        if skeleton || self.mode != ContextMode::Full {
+            &self.skeleton[&id]
+        } else {
+            &self.full[&id]
+        }
+    }
+}
+
+/// Lines `range.0..=range.1` (1-based) of `content`
+fn line_range(content: &str, range: (usize, usize)) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let start = range.0.saturating_sub(1);
+    let end = range.1.min(lines.len());
+    lines.get(start..end).unwrap_or_default().join("\n")
+}
+
+#[derive(Clone, Copy)]
+enum BudgetStep {
+    Skeleton(NodeId),
+    Drop(NodeId),
 }
 
 /// Context quality metrics based on S/N ratio analysis
 /// Inspired by Concept Transformer Phase 9b findings
 #[derive(Debug, Clone, Default)]
 pub struct ContextQuality {
-    /// Estimated token count
+    /// Token count of the content (see `count_tokens`)
     pub estimated_tokens: usize,
     /// Ratio of meaningful identifiers (3+ chars) to short variables (1-2 chars)
     pub sn_ratio: f32,
@@ -64,23 +144,31 @@ pub struct ContextQuality {
 
 /// Context generator
 pub struct ContextGenerator {
-    graph: CodeGraph,
+    graph: Arc<CodeGraph>,
+    /// Node embeddings for semantic query lookup (empty: match query words against names)
+    embeddings: HashMap<NodeId, Vec<f32>>,
 }
 
 impl ContextGenerator {
-    /// Create from directory scan
+    /// Create from directory scan (served by the daemon when one is running)
     pub fn from_directory(directory: &Path) -> Result<Self> {
-        let mut scanner = Scanner::new()?;
-        let graph = scanner.scan_directory(directory)?;
-        Ok(Self { graph })
+        let graph = crate::daemon::load_graph(directory)?;
+        Ok(Self::from_graph(graph))
+    }
+
+    /// Create from existing graph (an `Arc` is shared, not copied)
+    pub fn from_graph(graph: impl Into<Arc<CodeGraph>>) -> Self {
+        Self { graph: graph.into(), embeddings: HashMap::new() }
     }
 
-    /// Create from existing graph
-    pub fn from_graph(graph: CodeGraph) -> Self {
-        Self { graph }
+    /// Use embeddings (see `EmbeddingCache`) to pick the functions a query is about
+    pub fn with_embeddings(mut self, embeddings: HashMap<NodeId, Vec<f32>>) -> Self {
+        self.embeddings = embeddings;
+        self
     }
 
-    /// Gather context for a function with dependencies
+    /// Gather context for a function with dependencies.
+    /// With a `token_budget` the content is fitted to it: far dependencies become signatures, then drop out.
     pub fn gather_context(
         &self,
         entry_point: Option<&str>,
@@ -88,14 +176,17 @@ impl ContextGenerator {
         depth: usize,
         mode: ContextMode,
         format: ContextFormat,
+        token_budget: Option<usize>,
     ) -> Result<ContextResult> {
         let mut collected_nodes: Vec<&CodeNode> = Vec::new();
         let mut visited: HashSet<NodeId> = HashSet::new();
 
         // Find entry point node(s)
         let start_nodes: Vec<&CodeNode> = if let Some(entry) = entry_point {
-            // Support qualified names like "context.rs::gather_context"
-            if let Some((file_part, func_part)) = entry.split_once("::") {
+            if let Some(node) = self.graph.find_by_symbol_id(entry) {
+                vec![node]
+            } else if let Some((file_part, func_part)) = entry.split_once("::") {
+                // Support qualified names like "context.rs::gather_context"
                 self.graph.nodes.values()
                     .filter(|n| {
                         let file_matches = n.file_path.to_string_lossy().contains(file_part);
@@ -108,9 +199,20 @@ impl ContextGenerator {
                     .filter(|n| n.name == entry || n.name.contains(entry))
                     .collect()
             }
-        } else if let Some(_q) = query {
-            // TODO: Use search index for query-based lookup
-            self.graph.nodes.values().take(10).collect()
+        } else if let Some(q) = query {
+            let seeds = self.seeds_for_query(q);
+            if seeds.is_empty() {
+                return Ok(ContextResult {
+                    content: format!("// No code matched query: {}", q),
+                    files_count: 0,
+                    functions_count: 0,
+                    total_lines: 0,
+                    quality: ContextQuality::default(),
+                    skeletonized: Vec::new(),
+                    dropped: Vec::new(),
+                });
+            }
+            seeds
         } else {
             return Ok(ContextResult {
                 content: "// No entry point or query specified".to_string(),
@@ -118,6 +220,8 @@ impl ContextGenerator {
                 functions_count: 0,
                 total_lines: 0,
                 quality: ContextQuality::default(),
+                skeletonized: Vec::new(),
+                dropped: Vec::new(),
             });
         };
 
@@ -128,13 +232,31 @@ impl ContextGenerator {
 
         // Sort by importance for skeleton mode (prioritize high-density nodes)
         // This is based on Concept Transformer insights: type definitions > core logic > getters
+        let entry_point_ids: HashSet<NodeId> = start_nodes.iter().map(|n| n.id).collect();
         if mode == ContextMode::Skeleton {
-            let entry_point_ids: HashSet<NodeId> = start_nodes.iter().map(|n| n.id).collect();
             self.sort_by_importance(&mut collected_nodes, &entry_point_ids);
         }
 
-        // Generate output
-        let content = self.format_context(&collected_nodes, mode, format, entry_point, query);
+        // Fit the token budget, then generate output
+        let bodies = NodeBodies::read(&collected_nodes, mode);
+        let render = |nodes: &[&CodeNode], skeletons: &HashSet<NodeId>| {
+            self.format_context(nodes, &bodies, skeletons, format, entry_point, query)
+        };
+        let mut skeletons: HashSet<NodeId> = HashSet::new();
+        let mut dropped: Vec<String> = Vec::new();
+        if let Some(budget) = token_budget {
+            let fit = self.fit_token_budget(&collected_nodes, &entry_point_ids, &bodies, budget, &render);
+            let kept: HashSet<NodeId> = fit.nodes.iter().map(|n| n.id).collect();
+            dropped = collected_nodes.iter().filter(|n| !kept.contains(&n.id)).map(|n| n.name.clone()).collect();
+            collected_nodes = fit.nodes;
+            skeletons = fit.skeletons;
+        }
+        let skeletonized = collected_nodes
+            .iter()
+            .filter(|n| skeletons.contains(&n.id))
+            .map(|n| n.name.clone())
+            .collect();
+        let content = render(&collected_nodes, &skeletons);
         let files: HashSet<_> = collected_nodes.iter().map(|n| &n.file_path).collect();
         let total_lines: usize = collected_nodes.iter().map(|n| n.line_range.1 - n.line_range.0 + 1).sum();
 
@@ -152,9 +274,146 @@ impl ContextGenerator {
             functions_count: collected_nodes.len(),
             total_lines,
             quality,
+            skeletonized,
+            dropped,
         })
     }
 
+    /// Cut `nodes` down to `budget` tokens of rendered context. Ranks them by importance and by how
+    /// strongly the entry points reach them (edge traversal weights multiply along the path), then
+    /// turns the lowest-ranked dependencies into signatures, drops them, and finally does the same
+    /// to the entry points (keeping one).
+    fn fit_token_budget<'a>(
+        &'a self,
+        nodes: &[&'a CodeNode],
+        entry_point_ids: &HashSet<NodeId>,
+        bodies: &NodeBodies,
+        budget: usize,
+        render: &dyn Fn(&[&CodeNode], &HashSet<NodeId>) -> String,
+    ) -> BudgetFit<'a> {
+        let relevance = self.reach_weights(nodes, entry_point_ids);
+        let mut ranked: Vec<&CodeNode> = nodes.to_vec();
+        ranked.sort_by(|a, b| {
+            let key = |n: &CodeNode| {
+                let entry = entry_point_ids.contains(&n.id);
+                (entry, relevance.get(&n.id).copied().unwrap_or(0.0), self.calculate_node_importance(n, entry))
+            };
+            let (ka, kb) = (key(a), key(b));
+            kb.0.cmp(&ka.0)
+                .then(kb.1.total_cmp(&ka.1))
+                .then(kb.2.cmp(&ka.2))
+                .then(a.id.cmp(&b.id))
+        });
+
+        // Degrade from the bottom of the ranking: dependencies before entry points
+        let (entries, dependencies): (Vec<&CodeNode>, Vec<&CodeNode>) =
+            ranked.iter().partition(|n| entry_point_ids.contains(&n.id));
+        let mut steps: Vec<BudgetStep> = Vec::new();
+        for (group, keep) in [(&dependencies, 0), (&entries, 1)] {
+            if bodies.mode == ContextMode::Full {
+                steps.extend(group.iter().rev().map(|n| BudgetStep::Skeleton(n.id)));
+            }
+            steps.extend(group.iter().skip(keep).rev().map(|n| BudgetStep::Drop(n.id)));
+        }
+
+        // Estimate per node first, then confirm on the rendered text
+        let cost = |node: &CodeNode, skeleton: bool| count_tokens(bodies.get(node.id, skeleton)) + NODE_OVERHEAD_TOKENS;
+        let full_costs: HashMap<NodeId, usize> = nodes.iter().map(|n| (n.id, cost(n, false))).collect();
+        let skeleton_costs: HashMap<NodeId, usize> = nodes.iter().map(|n| (n.id, cost(n, true))).collect();
+        let overhead = count_tokens(&render(&[], &HashSet::new()));
+
+        let mut skeletons: HashSet<NodeId> = HashSet::new();
+        let mut dropped: HashSet<NodeId> = HashSet::new();
+        let estimate = |skeletons: &HashSet<NodeId>, dropped: &HashSet<NodeId>| {
+            overhead
+                + nodes
+                    .iter()
+                    .filter(|n| !dropped.contains(&n.id))
+                    .map(|n| if skeletons.contains(&n.id) { skeleton_costs[&n.id] } else { full_costs[&n.id] })
+                    .sum::<usize>()
+        };
+
+        let mut steps = steps.into_iter();
+        let apply = |step: BudgetStep, skeletons: &mut HashSet<NodeId>, dropped: &mut HashSet<NodeId>| match step {
+            BudgetStep::Skeleton(id) => {
+                skeletons.insert(id);
+            }
+            BudgetStep::Drop(id) => {
+                dropped.insert(id);
+            }
+        };
+        while estimate(&skeletons, &dropped) > budget {
+            let Some(step) = steps.next() else { break };
+            apply(step, &mut skeletons, &mut dropped);
+        }
+        let kept = |dropped: &HashSet<NodeId>| -> Vec<&'a CodeNode> {
+            nodes.iter().copied().filter(|n| !dropped.contains(&n.id)).collect()
+        };
+        while count_tokens(&render(&kept(&dropped), &skeletons)) > budget {
+            let Some(step) = steps.next() else { break };
+            apply(step, &mut skeletons, &mut dropped);
+        }
+
+        BudgetFit { nodes: kept(&dropped), skeletons }
+    }
+
+    /// How strongly the entry points reach each node: the best product of edge traversal weights
+    fn reach_weights(&self, nodes: &[&CodeNode], entry_point_ids: &HashSet<NodeId>) -> HashMap<NodeId, f32> {
+        let members: HashSet<NodeId> = nodes.iter().map(|n| n.id).collect();
+        let mut weights: HashMap<NodeId, f32> = entry_point_ids.iter().map(|&id| (id, 1.0)).collect();
+        let mut queue: VecDeque<NodeId> = entry_point_ids.iter().copied().collect();
+        while let Some(id) = queue.pop_front() {
+            let weight = weights[&id];
+            for edge in self.graph.outgoing_edges(id) {
+                if !members.contains(&edge.to) {
+                    continue;
+                }
+                let reached = weight * edge.edge_type.to_semantic().traversal_weight();
+                if weights.get(&edge.to).is_none_or(|&w| reached > w + f32::EPSILON) {
+                    weights.insert(edge.to, reached);
+                    queue.push_back(edge.to);
+                }
+            }
+        }
+        weights
+    }
+
+    /// Functions a natural-language query is about; their dependencies are expanded from there.
+    /// Semantic search when embeddings are loaded, otherwise the names matching most query words.
+    fn seeds_for_query(&self, query: &str) -> Vec<&CodeNode> {
+        let searcher = GraphSearcher::new(Arc::clone(&self.graph));
+
+        if !self.embeddings.is_empty() {
+            #[cfg(feature = "semantic-search")]
+            return searcher
+                .search_semantic(query, &self.embeddings, QUERY_SEEDS, 0)
+                .iter()
+                .filter_map(|hit| hit.path.last().and_then(|id| self.graph.get_node(*id)))
+                .collect();
+        }
+
+        let terms = query_terms(query);
+        let mut matched: HashMap<NodeId, usize> = HashMap::new();
+        for term in &terms {
+            for hit in searcher.search_with_graph(term, usize::MAX, 0) {
+                if let Some(&id) = hit.path.last() {
+                    *matched.entry(id).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let mut seeds: Vec<(&CodeNode, usize)> = matched
+            .into_iter()
+            .filter_map(|(id, hits)| self.graph.get_node(id).map(|node| (node, hits)))
+            .collect();
+        // Most query words first, then exact names, then the shortest (most specific) names
+        seeds.sort_by_key(|(node, hits)| {
+            let exact = terms.iter().any(|t| node.name.eq_ignore_ascii_case(t));
+            (std::cmp::Reverse(*hits), !exact, node.name.len(), node.id)
+        });
+        seeds.into_iter().take(QUERY_SEEDS).map(|(node, _)| node).collect()
+    }
+
     /// Calculate context quality metrics based on S/N ratio
     /// Enhanced with Concept Transformer Phase 9b insights
     fn calculate_quality(
@@ -165,8 +424,7 @@ impl ContextGenerator {
         collected_nodes: &[&CodeNode],
         entry_point_lines: usize,
     ) -> ContextQuality {
-        // Estimate tokens (rough: ~4 chars per token for code)
-        let estimated_tokens = content.len() / 4;
+        let estimated_tokens = count_tokens(content);
 
         // Count short identifiers (1-2 chars) vs meaningful names (3+ chars)
         // This is based on Concept Transformer's finding that short variables are "noise"
@@ -318,12 +576,10 @@ impl ContextGenerator {
         collected.push(node);
 
         if depth > 0 {
-            // Find edges from this node
-            for edge in &self.graph.edges {
-                if edge.from == node.id {
-                    if let Some(target) = self.graph.nodes.get(&edge.to) {
-                        self.collect_dependencies(target, depth - 1, collected, visited);
-                    }
+            // Follow edges from this node
+            for target_id in self.neighbours(node.id, true) {
+                if let Some(target) = self.graph.nodes.get(&target_id) {
+                    self.collect_dependencies(target, depth - 1, collected, visited);
                 }
             }
         }
@@ -396,17 +652,19 @@ impl ContextGenerator {
     }
 
     /// Format collected nodes into context string
+    /// Nodes in `skeletons` are shown as signatures whatever the mode
     fn format_context(
         &self,
         nodes: &[&CodeNode],
-        mode: ContextMode,
+        bodies: &NodeBodies,
+        skeletons: &HashSet<NodeId>,
         format: ContextFormat,
         entry_point: Option<&str>,
         query: Option<&str>,
     ) -> String {
         match format {
-            ContextFormat::Standard => self.format_standard(nodes, mode, entry_point, query),
-            ContextFormat::LlmEdit => self.format_llm_edit(nodes, mode, entry_point, query),
+            ContextFormat::Standard => self.format_standard(nodes, bodies, skeletons, entry_point, query),
+            ContextFormat::LlmEdit => self.format_llm_edit(nodes, bodies, skeletons, entry_point, query),
         }
     }
 
@@ -414,7 +672,8 @@ impl ContextGenerator {
     fn format_standard(
         &self,
         nodes: &[&CodeNode],
-        mode: ContextMode,
+        bodies: &NodeBodies,
+        skeletons: &HashSet<NodeId>,
         entry_point: Option<&str>,
         query: Option<&str>,
     ) -> String {
@@ -445,26 +704,9 @@ impl ContextGenerator {
             for node in file_nodes {
                 output.push_str(&format!("// Lines: {}-{}\n\n", node.line_range.0, node.line_range.1));
 
-                match mode {
-                    ContextMode::Full => {
-                        // Read actual code from file
-                        if let Ok(code) = self.read_code_range(file_path, node.line_range) {
-                            output.push_str(&code);
-                        } else {
-                            output.push_str(&format!("// {} {:?}\n", node.name, node.node_type));
-                        }
-                    }
-                    ContextMode::Skeleton => {
-                        // Output signature if available, otherwise just name
-                        if !node.signature.is_empty() {
-                            output.push_str(&node.signature);
-                            output.push_str("\n");
-                        } else {
-                            output.push_str(&format!("{} {:?}\n", node.name, node.node_type));
-                        }
-                    }
-                }
-                output.push_str("\n");
+                // Signature only in skeleton mode, or when cut down for the token budget
+                output.push_str(bodies.get(node.id, skeletons.contains(&node.id)));
+                output.push('\n');
             }
         }
 
@@ -480,7 +722,8 @@ impl ContextGenerator {
     fn format_llm_edit(
         &self,
         nodes: &[&CodeNode],
-        mode: ContextMode,
+        bodies: &NodeBodies,
+        skeletons: &HashSet<NodeId>,
         entry_point: Option<&str>,
         query: Option<&str>,
     ) -> String {
@@ -520,24 +763,7 @@ impl ContextGenerator {
                     node.line_range.1
                 ));
 
-                match mode {
-                    ContextMode::Full => {
-                        if let Ok(code) = self.read_code_range(file_path, node.line_range) {
-                            output.push_str(&code);
-                            output.push_str("\n");
-                        } else {
-                            output.push_str(&format!("// {} {:?}\n", node.name, node.node_type));
-                        }
-                    }
-                    ContextMode::Skeleton => {
-                        if !node.signature.is_empty() {
-                            output.push_str(&node.signature);
-                            output.push_str("\n");
-                        } else {
-                            output.push_str(&format!("{} {:?}\n", node.name, node.node_type));
-                        }
-                    }
-                }
+                output.push_str(bodies.get(node.id, skeletons.contains(&node.id)));
 
                 output.push_str("<<<END FILE>>>\n\n");
             }
@@ -546,29 +772,36 @@ impl ContextGenerator {
         output
     }
 
-    /// Read code from file at specific line range
-    fn read_code_range(&self, file_path: &Path, range: (usize, usize)) -> Result<String> {
-        let content = std::fs::read_to_string(file_path)?;
-        let lines: Vec<&str> = content.lines().collect();
-
-        let start = range.0.saturating_sub(1);
-        let end = range.1.min(lines.len());
-
-        Ok(lines[start..end].join("\n"))
-    }
-
     /// Find a node by qualified name
     /// Supports formats:
     /// - "function_name" - matches by function name only
     /// - "file.rs::function_name" - matches by file name and function name
     /// - "Type::function_name" - matches by signature containing "Type::" or "impl Type"
-    fn find_node_by_qualified_name(&self, qualified_name: &str) -> Option<&CodeNode> {
+    pub fn find_node_by_qualified_name(&self, qualified_name: &str) -> Option<&CodeNode> {
+        // Stable symbol id (`rs:src/scanner.rs#Scanner.scan_file`)
+        if let Some(node) = self.graph.find_by_symbol_id(qualified_name) {
+            return Some(node);
+        }
+
+        // Module path stored on Rust nodes (exact, then trailing segments like `Type::method`)
+        let suffix = format!("::{}", qualified_name);
+        let last_segment = qualified_name.rsplit("::").next().unwrap_or(qualified_name);
+        if let Some(node) = self
+            .graph
+            .nodes_by_qualified_name(qualified_name)
+            .next()
+            .or_else(|| {
+                self.graph
+                    .nodes_by_name(last_segment)
+                    .find(|n| n.qualified_name.ends_with(&suffix))
+            })
+        {
+            return Some(node);
+        }
+
         if let Some((qualifier, func_name)) = qualified_name.split_once("::") {
             // Qualified search
-            self.graph.nodes.values().find(|n| {
-                if n.name != func_name {
-                    return false;
-                }
+            self.graph.nodes_by_name(func_name).find(|n| {
                 // Check if qualifier matches file name
                 let file_name = n.file_path.file_name()
                     .and_then(|f| f.to_str())
@@ -585,7 +818,16 @@ impl ContextGenerator {
             })
         } else {
             // Simple name search
-            self.graph.nodes.values().find(|n| n.name == qualified_name)
+            self.graph.nodes_by_name(qualified_name).next()
+        }
+    }
+
+    /// Adjacent node ids: callees when `forward`, callers otherwise
+    fn neighbours(&self, id: NodeId, forward: bool) -> Vec<NodeId> {
+        if forward {
+            self.graph.outgoing_edges(id).map(|edge| edge.to).collect()
+        } else {
+            self.graph.incoming_edges(id).map(|edge| edge.from).collect()
         }
     }
 
@@ -642,36 +884,29 @@ impl ContextGenerator {
             return;
         }
 
-        for edge in &self.graph.edges {
-            let (source, target) = if forward {
-                (&edge.from, &edge.to)
-            } else {
-                (&edge.to, &edge.from)
-            };
-
-            if source == &node.id {
-                if let Some(target_node) = self.graph.nodes.get(target) {
-                    // 重複チェック: 同じ(name, file_path, line)は追加しない
-                    let key = (
-                        target_node.name.clone(),
-                        target_node.file_path.display().to_string(),
-                        target_node.line_range.0,
-                    );
-                    if !seen_results.contains(&key) {
-                        seen_results.insert(key);
-                        // 依存先のノードを結果に追加
-                        result.push(DependencyInfo {
-                            name: target_node.name.clone(),
-                            file_path: target_node.file_path.display().to_string(),
-                            line: target_node.line_range.0,
-                            node_type: format!("{:?}", target_node.node_type),
-                        });
-                    }
+        for target in self.neighbours(node.id, forward) {
+            if let Some(target_node) = self.graph.nodes.get(&target) {
+                // 重複チェック: 同じ(name, file_path, line)は追加しない
+                let key = (
+                    target_node.name.clone(),
+                    target_node.file_path.display().to_string(),
+                    target_node.line_range.0,
+                );
+                if !seen_results.contains(&key) {
+                    seen_results.insert(key);
+                    // 依存先のノードを結果に追加
+                    result.push(DependencyInfo {
+                        name: target_node.name.clone(),
+                        symbol_id: target_node.symbol_id.clone(),
+                        file_path: target_node.file_path.display().to_string(),
+                        line: target_node.line_range.0,
+                        node_type: format!("{:?}", target_node.node_type),
+                    });
+                }
 
-                    // 再帰的に深堀りする (depth を減らして)
-                    if depth > 1 {
-                        self.collect_dependency_info(target_node, depth - 1, result, visited, seen_results, forward);
-                    }
+                // 再帰的に深堀りする (depth を減らして)
+                if depth > 1 {
+                    self.collect_dependency_info(target_node, depth - 1, result, visited, seen_results, forward);
                 }
             }
         }
@@ -682,6 +917,7 @@ impl ContextGenerator {
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct DependencyInfo {
     pub name: String,
+    pub symbol_id: String,
     pub file_path: String,
     pub line: usize,
     pub node_type: String,
@@ -691,6 +927,7 @@ pub struct DependencyInfo {
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct CallChainStep {
     pub name: String,
+    pub symbol_id: String,
     pub file_path: String,
     pub line: usize,
     pub node_type: String,
@@ -744,6 +981,7 @@ impl ContextGenerator {
                     .filter_map(|id| self.graph.nodes.get(id))
                     .map(|node| CallChainStep {
                         name: node.name.clone(),
+                        symbol_id: node.symbol_id.clone(),
                         file_path: node.file_path.display().to_string(),
                         line: node.line_range.0,
                         node_type: format!("{:?}", node.node_type),
@@ -758,13 +996,12 @@ impl ContextGenerator {
                 };
             }
 
-            // Find edges from current node
-            for edge in &self.graph.edges {
-                if edge.from == current_id && !visited.contains(&edge.to) {
-                    visited.insert(edge.to);
+            // Follow edges from current node
+            for next_id in self.neighbours(current_id, true) {
+                if visited.insert(next_id) {
                     let mut new_path = path.clone();
-                    new_path.push(edge.to);
-                    queue.push_back((edge.to, new_path));
+                    new_path.push(next_id);
+                    queue.push_back((next_id, new_path));
                 }
             }
         }
@@ -812,32 +1049,226 @@ impl ContextGenerator {
 
         result.push(CallTreeNode {
             name: node.name.clone(),
+            symbol_id: node.symbol_id.clone(),
             file_path: node.file_path.display().to_string(),
             line: node.line_range.0,
             depth: current_depth,
             node_type: format!("{:?}", node.node_type),
         });
 
-        for edge in &self.graph.edges {
-            let (source, target) = if forward {
-                (&edge.from, &edge.to)
-            } else {
-                (&edge.to, &edge.from)
-            };
+        for target in self.neighbours(node.id, forward) {
+            if let Some(next_node) = self.graph.nodes.get(&target) {
+                self.build_call_tree(next_node, max_depth, forward, current_depth + 1, result, visited);
+            }
+        }
+    }
+}
 
-            if source == &node.id {
-                if let Some(next_node) = self.graph.nodes.get(target) {
-                    self.build_call_tree(next_node, max_depth, forward, current_depth + 1, result, visited);
-                }
+impl ContextGenerator {
+    /// Definitions a symbol names: every definition with that name for a plain name,
+    /// otherwise the one a symbol id or qualified name resolves to
+    pub fn definitions_of(&self, symbol: &str) -> Vec<&CodeNode> {
+        if !symbol.contains("::") && !symbol.contains('#') {
+            let mut nodes: Vec<&CodeNode> = self.graph.nodes_by_name(symbol).collect();
+            if !nodes.is_empty() {
+                nodes.sort_by_key(|n| n.id);
+                return nodes;
             }
         }
+        self.find_node_by_qualified_name(symbol).into_iter().collect()
+    }
+
+    /// Every recorded use of `symbol` (calls, imports, template references) with its exact position,
+    /// preceded by the definitions themselves when `include_definition` is set
+    pub fn find_references(&self, symbol: &str, include_definition: bool) -> Vec<SymbolLocation> {
+        let mut sources = SourceLines::default();
+        let mut locations = Vec::new();
+        for node in self.definitions_of(symbol) {
+            if include_definition {
+                locations.push(self.definition_location(node, &mut sources));
+            }
+
+            let mut sites: Vec<(&SourcePosition, &DependencyEdge)> = self
+                .graph
+                .incoming_edges(node.id)
+                .filter_map(|edge| edge.site.as_ref().map(|site| (site, edge)))
+                .collect();
+            sites.sort_by(|(a, _), (b, _)| {
+                (&a.file_path, a.line, a.column).cmp(&(&b.file_path, b.line, b.column))
+            });
+            sites.dedup_by(|(a, _), (b, _)| a == b);
+
+            for (site, edge) in sites {
+                locations.push(SymbolLocation {
+                    kind: match edge.edge_type {
+                        EdgeType::Calls => "call",
+                        EdgeType::References => "reference",
+                        EdgeType::Instantiates => "instantiation",
+                        EdgeType::Imports => "import",
+                    }
+                    .to_string(),
+                    name: node.name.clone(),
+                    symbol_id: node.symbol_id.clone(),
+                    file_path: site.file_path.display().to_string(),
+                    line: site.line,
+                    column: site.column,
+                    source_line: sources.line(&site.file_path, site.line),
+                    container: self.graph.nodes.get(&edge.from).map(|n| n.name.clone()),
+                });
+            }
+        }
+        locations
+    }
+
+    /// Definitions behind the identifier at `line`:`column` of `file_path` (both 1-based).
+    /// Uses the resolved reference there if the scanner recorded one, else the identifier's name.
+    pub fn definition_at(&self, file_path: &Path, line: usize, column: usize) -> Vec<SymbolLocation> {
+        let same_file = |path: &Path| {
+            path == file_path
+                || matches!(
+                    (std::fs::canonicalize(path), std::fs::canonicalize(file_path)),
+                    (Ok(a), Ok(b)) if a == b
+                )
+        };
+
+        let mut sources = SourceLines::default();
+        let resolved: Vec<&CodeNode> = self
+            .graph
+            .edges
+            .iter()
+            .filter(|edge| {
+                edge.site.as_ref().is_some_and(|site| {
+                    let name_len = self.graph.nodes.get(&edge.to).map_or(0, |n| n.name.chars().count());
+                    site.line == line
+                        && (site.column..site.column + name_len.max(1)).contains(&column)
+                        && same_file(&site.file_path)
+                })
+            })
+            .filter_map(|edge| self.graph.nodes.get(&edge.to))
+            .collect();
+
+        let mut nodes = if resolved.is_empty() {
+            let text = sources.line(file_path, line);
+            identifier_at(&text, column)
+                .map(|name| self.graph.nodes_by_name(&name).collect())
+                .unwrap_or_default()
+        } else {
+            resolved
+        };
+        nodes.sort_by_key(|n| n.id);
+        nodes.dedup_by_key(|n| n.id);
+        nodes.into_iter().map(|node| self.definition_location(node, &mut sources)).collect()
+    }
+
+    /// Where `symbol` is defined
+    pub fn go_to_definition(&self, symbol: &str) -> Vec<SymbolLocation> {
+        let mut sources = SourceLines::default();
+        self.definitions_of(symbol)
+            .into_iter()
+            .map(|node| self.definition_location(node, &mut sources))
+            .collect()
+    }
+
+    fn definition_location(&self, node: &CodeNode, sources: &mut SourceLines) -> SymbolLocation {
+        let line = node.line_range.0;
+        let source_line = sources.line(&node.file_path, line);
+        SymbolLocation {
+            kind: "definition".to_string(),
+            name: node.name.clone(),
+            symbol_id: node.symbol_id.clone(),
+            file_path: node.file_path.display().to_string(),
+            line,
+            column: word_column(&source_line, &node.name).unwrap_or(1),
+            source_line,
+            container: None,
+        }
     }
 }
 
+/// A place in the source where a symbol is defined or used
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SymbolLocation {
+    /// "definition", "call", "reference", "instantiation" or "import"
+    pub kind: String,
+    /// The symbol defined or referenced here
+    pub name: String,
+    pub symbol_id: String,
+    pub file_path: String,
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    /// The whole line, without its line break
+    pub source_line: String,
+    /// Function the reference is written in (None at top level and for definitions)
+    pub container: Option<String>,
+}
+
+/// Source files read once per lookup
+#[derive(Default)]
+struct SourceLines(HashMap<std::path::PathBuf, Vec<String>>);
+
+impl SourceLines {
+    fn line(&mut self, path: &Path, line: usize) -> String {
+        let lines = self.0.entry(path.to_path_buf()).or_insert_with(|| {
+            std::fs::read_to_string(path)
+                .map(|content| content.lines().map(str::to_string).collect())
+                .unwrap_or_default()
+        });
+        line.checked_sub(1)
+            .and_then(|i| lines.get(i))
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+pub(crate) fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// 1-based columns where `word` appears as a whole identifier in `line`
+pub(crate) fn word_columns<'a>(line: &'a str, word: &'a str) -> impl Iterator<Item = usize> + 'a {
+    line.match_indices(word)
+        .filter(move |(start, _)| {
+            let before = line[..*start].chars().next_back();
+            let after = line[start + word.len()..].chars().next();
+            !before.is_some_and(is_ident_char) && !after.is_some_and(is_ident_char)
+        })
+        .map(move |(start, _)| line[..start].chars().count() + 1)
+}
+
+fn word_column(line: &str, word: &str) -> Option<usize> {
+    word_columns(line, word).next()
+}
+
+/// Lowercased search words of a query: "auth token refresh" → ["auth", "token", "refresh"]
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for word in query.split(|c: char| !c.is_alphanumeric() && c != '_') {
+        let word = word.to_lowercase();
+        if word.chars().count() >= 3 && !QUERY_STOPWORDS.contains(&word.as_str()) && !terms.contains(&word) {
+            terms.push(word);
+        }
+    }
+    if terms.is_empty() && !query.trim().is_empty() {
+        terms.push(query.trim().to_lowercase());
+    }
+    terms
+}
+
+/// The identifier covering 1-based `column` of `line`
+fn identifier_at(line: &str, column: usize) -> Option<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let at = column.checked_sub(1).filter(|&i| chars.get(i).is_some_and(|&c| is_ident_char(c)))?;
+    let start = (0..=at).rev().take_while(|&i| is_ident_char(chars[i])).last()?;
+    let end = (at..chars.len()).take_while(|&i| is_ident_char(chars[i])).last()?;
+    Some(chars[start..=end].iter().collect())
+}
+
 /// Call tree node
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct CallTreeNode {
     pub name: String,
+    pub symbol_id: String,
     pub file_path: String,
     pub line: usize,
     pub depth: usize,
@@ -893,6 +1324,7 @@ pub fn parse_llm_edits(content: &str) -> Result<Vec<ParsedFileChange>> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{copy_fixture, temp_project};
     use crate::graph::{CodeGraph, CodeNode, DependencyEdge, EdgeType, NodeType};
     use std::path::PathBuf;
 
@@ -909,6 +1341,9 @@ mod tests {
             is_exported: false,
             is_used: false,
             signature: "fn main()".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         });
 
         let scan_id = graph.add_node(CodeNode {
@@ -920,6 +1355,9 @@ mod tests {
             is_exported: true,
             is_used: false,
             signature: "pub fn scan_directory(&mut self, dir: &Path) -> Result<CodeGraph>".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         });
 
         let detect_id = graph.add_node(CodeNode {
@@ -931,6 +1369,9 @@ mod tests {
             is_exported: true,
             is_used: false,
             signature: "pub fn detect_dead_code(graph: &CodeGraph) -> Vec<DeadCode>".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         });
 
         // エッジ: main -> scan_directory
@@ -938,6 +1379,7 @@ mod tests {
             from: main_id,
             to: scan_id,
             edge_type: EdgeType::Calls,
+            site: None,
         });
 
         // エッジ: main -> detect_dead_code
@@ -945,6 +1387,7 @@ mod tests {
             from: main_id,
             to: detect_id,
             edge_type: EdgeType::Calls,
+            site: None,
         });
 
         // エッジ: scan_directory -> detect_dead_code (間接的)
@@ -952,6 +1395,7 @@ mod tests {
             from: scan_id,
             to: detect_id,
             edge_type: EdgeType::Calls,
+            site: None,
         });
 
         graph
@@ -997,6 +1441,67 @@ mod tests {
         assert!(dep_names.contains(&"scan_directory"));
     }
 
+    #[test]
+    fn test_query_picks_seeds_by_name_words() {
+        let graph = create_test_graph();
+        let generator = ContextGenerator::from_graph(graph);
+
+        // "scan" と "directory" の両方に一致する関数が起点になり、依存先まで展開される
+        let seeds = generator.seeds_for_query("how does the directory scan work?");
+        assert_eq!(seeds[0].name, "scan_directory");
+
+        let result = generator
+            .gather_context(None, Some("directory scan"), 2, ContextMode::Skeleton, ContextFormat::Standard, None)
+            .unwrap();
+        assert_eq!(result.functions_count, 2);
+        assert!(result.content.contains("detect_dead_code"));
+
+        let none = generator
+            .gather_context(None, Some("payment gateway"), 1, ContextMode::Skeleton, ContextFormat::Standard, None)
+            .unwrap();
+        assert_eq!(none.functions_count, 0);
+    }
+
+    #[test]
+    fn test_token_budget_degrades_far_dependencies_first() {
+        use crate::scanner::Scanner;
+
+        let temp = temp_project();
+        let dir = temp.path();
+        let body = "  const total = values.reduce((sum, value) => sum + value, 0);\n  return total * 2;\n";
+        std::fs::write(
+            dir.join("app.ts"),
+            format!(
+                "export function main(values: number[]) {{\n  return near(values);\n}}\n\
+                 function near(values: number[]) {{\n{body}  return far(values);\n}}\n\
+                 function far(values: number[]) {{\n{body}}}\n"
+            ),
+        )
+        .unwrap();
+        let graph = Scanner::new().unwrap().scan_directory(dir).unwrap();
+        let generator = ContextGenerator::from_graph(graph);
+        let gather = |budget| {
+            generator
+                .gather_context(Some("main"), None, 3, ContextMode::Full, ContextFormat::Standard, budget)
+                .unwrap()
+        };
+
+        let unlimited = gather(None);
+        assert_eq!(unlimited.functions_count, 3);
+        assert!(unlimited.skeletonized.is_empty() && unlimited.dropped.is_empty());
+
+        // 少し足りない: 遠い依存から先にシグネチャだけになる
+        let tight = gather(Some(unlimited.quality.estimated_tokens - 10));
+        assert_eq!(tight.skeletonized, vec!["far".to_string()]);
+        assert!(tight.quality.estimated_tokens <= unlimited.quality.estimated_tokens - 10);
+
+        // ほぼゼロ: エントリーポイントだけが残る
+        let minimal = gather(Some(1));
+        assert_eq!(minimal.functions_count, 1);
+        assert_eq!(minimal.dropped.len(), 2);
+        assert!(minimal.content.contains("function main"));
+    }
+
     #[test]
     fn test_get_dependencies_nonexistent() {
         let graph = create_test_graph();
@@ -1008,17 +1513,44 @@ mod tests {
     }
 
     #[test]
-    fn test_scanner_graph_edges() {
-        // test_rust_projectをスキャンしてエッジとノードを確認
+    fn test_symbol_ids_stable_across_scans() {
         use crate::scanner::Scanner;
         use std::path::Path;
 
         let test_dir = Path::new("test_rust_project");
         if !test_dir.exists() {
-            eprintln!("test_rust_project does not exist, skipping test");
             return;
         }
 
+        let ids = |graph: &CodeGraph| {
+            let mut ids: Vec<String> = graph.nodes.values().map(|n| n.symbol_id.clone()).collect();
+            ids.sort();
+            ids
+        };
+        let first = Scanner::with_incremental(false).unwrap().scan_directory(test_dir).unwrap();
+        let second = Scanner::with_incremental(false).unwrap().scan_directory(test_dir).unwrap();
+
+        let first_ids = ids(&first);
+        assert_eq!(first_ids, ids(&second));
+        assert!(first_ids.iter().all(|id| id.starts_with("rs:")));
+        let unique: HashSet<&String> = first_ids.iter().collect();
+        assert_eq!(unique.len(), first_ids.len(), "symbol ids must be unique");
+
+        // Symbol ids are accepted wherever a function name is
+        let generator = ContextGenerator::from_graph(first);
+        let node = generator.graph.nodes.values().find(|n| n.name == "used_function").unwrap();
+        let deps = generator.get_dependencies(&node.symbol_id, 1);
+        assert!(deps.iter().any(|d| d.name == "helper_function"));
+    }
+
+    #[test]
+    fn test_scanner_graph_edges() {
+        // test_rust_projectをスキャンしてエッジとノードを確認（コピーをスキャンしてキャッシュを残さない）
+        use crate::scanner::Scanner;
+
+        let fixture = copy_fixture("test_rust_project");
+        let test_dir = fixture.path();
+
         let mut scanner = Scanner::new().unwrap();
         let graph = scanner.scan_directory(test_dir).unwrap();
 
@@ -1070,4 +1602,45 @@ mod tests {
         assert!(deps.len() > 0, "used_function should have dependencies");
         assert!(deps.iter().any(|d| d.name == "helper_function"), "used_function should depend on helper_function");
     }
+
+    #[test]
+    fn test_references_and_definitions_with_positions() {
+        use crate::scanner::Scanner;
+
+        let temp = temp_project();
+        let dir = temp.path();
+        std::fs::write(dir.join("a.ts"), "export function greet(name: string) {\n  return name;\n}\n").unwrap();
+        std::fs::write(
+            dir.join("b.ts"),
+            "import { greet } from \"./a\";\n\nexport function main() {\n  const msg = greet(\"x\");\n  return msg;\n}\n",
+        )
+        .unwrap();
+
+        let mut scanner = Scanner::new().unwrap();
+        let generator = ContextGenerator::from_graph(scanner.scan_directory(dir).unwrap());
+
+        let found: Vec<(String, usize, usize, Option<String>)> = generator
+            .find_references("greet", true)
+            .into_iter()
+            .map(|r| (r.kind, r.line, r.column, r.container))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("definition".to_string(), 1, 17, None),
+                ("import".to_string(), 1, 10, None),
+                ("call".to_string(), 4, 15, Some("main".to_string())),
+            ]
+        );
+        let call = generator.find_references("greet", false).pop().unwrap();
+        assert_eq!(call.source_line, "  const msg = greet(\"x\");");
+
+        // Anywhere on the identifier of a use
+        let definitions = generator.definition_at(&dir.join("b.ts"), 4, 18);
+        assert_eq!(definitions.len(), 1);
+        assert!(definitions[0].file_path.ends_with("a.ts"));
+        assert_eq!((definitions[0].line, definitions[0].column), (1, 17));
+        assert!(generator.definition_at(&dir.join("b.ts"), 4, 3).is_empty());
+        assert_eq!(generator.go_to_definition("main")[0].source_line, "export function main() {");
+    }
 }
@@ -0,0 +1,368 @@
+// Line and word diffs for change previews
+// Myers の O(ND) 差分。共通の先頭・末尾を除いてから計算し、編集距離が大きすぎるときは中間を丸ごと置換として扱う
+
+use serde::Serialize;
+
+/// Unchanged lines shown around each change
+const CONTEXT_LINES: usize = 3;
+
+/// Beyond this many edits (after trimming common ends) the middle is shown as replaced
+/// instead of searched for a minimal diff: the search keeps O(D²) state
+const MAX_EDIT_DISTANCE: usize = 2000;
+
+/// One step turning `a` into `b` (indices into the two sequences)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Shortest edit script from `a` to `b`
+pub fn diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Edit> {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut edits: Vec<Edit> = (0..prefix).map(|i| Edit::Equal(i, i)).collect();
+    match myers(a_mid, b_mid) {
+        Some(middle) => edits.extend(middle.into_iter().map(|edit| match edit {
+            Edit::Equal(i, j) => Edit::Equal(i + prefix, j + prefix),
+            Edit::Delete(i) => Edit::Delete(i + prefix),
+            Edit::Insert(j) => Edit::Insert(j + prefix),
+        })),
+        None => {
+            edits.extend((0..a_mid.len()).map(|i| Edit::Delete(i + prefix)));
+            edits.extend((0..b_mid.len()).map(|j| Edit::Insert(j + prefix)));
+        }
+    }
+    edits.extend((0..suffix).map(|i| Edit::Equal(a.len() - suffix + i, b.len() - suffix + i)));
+    edits
+}
+
+/// Myers' greedy search; None when the edit distance exceeds `MAX_EDIT_DISTANCE`
+fn myers<T: PartialEq>(a: &[T], b: &[T]) -> Option<Vec<Edit>> {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let max = (n + m).min(MAX_EDIT_DISTANCE as isize);
+    let offset = max + 1;
+    // v[k + offset]: furthest x reached on diagonal k
+    let mut v = vec![0isize; (2 * max + 3) as usize];
+    // v before each round d, diagonals -d..=d
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=max {
+        trace.push(v[(offset - d) as usize..=(offset + d) as usize].to_vec());
+        for k in (-d..=d).step_by(2) {
+            let at = (offset + k) as usize;
+            let mut x = if k == -d || (k != d && v[at - 1] < v[at + 1]) { v[at + 1] } else { v[at - 1] + 1 };
+            let mut y = x - k;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at] = x;
+            if x >= n && y >= m {
+                return Some(backtrack(&trace, n, m));
+            }
+        }
+    }
+    None
+}
+
+fn backtrack(trace: &[Vec<isize>], n: isize, m: isize) -> Vec<Edit> {
+    let mut edits = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().skip(1).rev() {
+        let d = d as isize;
+        let at = |k: isize| v[(k + d) as usize];
+        let k = x - y;
+        let prev_k = if k == -d || (k != d && at(k - 1) < at(k + 1)) { k + 1 } else { k - 1 };
+        let prev_x = at(prev_k);
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            edits.push(Edit::Equal(x as usize, y as usize));
+        }
+        if x == prev_x {
+            edits.push(Edit::Insert(prev_y as usize));
+        } else {
+            edits.push(Edit::Delete(prev_x as usize));
+        }
+        x = prev_x;
+        y = prev_y;
+    }
+    while x > 0 && y > 0 {
+        x -= 1;
+        y -= 1;
+        edits.push(Edit::Equal(x as usize, y as usize));
+    }
+    edits.reverse();
+    edits
+}
+
+/// A unified diff and its line counts
+#[derive(Debug, Clone)]
+pub struct UnifiedDiff {
+    pub text: String,
+    pub additions: usize,
+    pub deletions: usize,
+}
+
+/// Unified diff of the file at `path` with `@@ -a,b +c,d @@` hunks and context lines.
+/// `first_line` is the file line `original` starts at (1 for whole files).
+pub fn unified_diff(path: &str, original: &str, modified: &str, first_line: usize) -> UnifiedDiff {
+    // Lines keep their endings, so CRLF changes and a missing final newline show up
+    let old: Vec<&str> = original.split_inclusive('\n').collect();
+    let new: Vec<&str> = modified.split_inclusive('\n').collect();
+    let edits = diff(&old, &new);
+
+    let mut text = format!("--- a/{}\n+++ b/{}\n", path, path);
+    let additions = edits.iter().filter(|e| matches!(e, Edit::Insert(_))).count();
+    let deletions = edits.iter().filter(|e| matches!(e, Edit::Delete(_))).count();
+
+    // Lines of each side before edit i
+    let mut before = Vec::with_capacity(edits.len() + 1);
+    let (mut old_pos, mut new_pos) = (0, 0);
+    for edit in &edits {
+        before.push((old_pos, new_pos));
+        match edit {
+            Edit::Equal(..) => {
+                old_pos += 1;
+                new_pos += 1;
+            }
+            Edit::Delete(_) => old_pos += 1,
+            Edit::Insert(_) => new_pos += 1,
+        }
+    }
+
+    let offset = first_line.saturating_sub(1);
+    for (start, end) in hunk_ranges(&edits) {
+        let hunk = &edits[start..end];
+        let old_count = hunk.iter().filter(|e| !matches!(e, Edit::Insert(_))).count();
+        let new_count = hunk.iter().filter(|e| !matches!(e, Edit::Delete(_))).count();
+        // An empty side names the line before the hunk
+        let old_start = before[start].0 + offset + usize::from(old_count > 0);
+        let new_start = before[start].1 + offset + usize::from(new_count > 0);
+        text.push_str(&format!("@@ -{},{} +{},{} @@\n", old_start, old_count, new_start, new_count));
+        for edit in hunk {
+            let (marker, line) = match *edit {
+                Edit::Equal(i, _) => (' ', old[i]),
+                Edit::Delete(i) => ('-', old[i]),
+                Edit::Insert(j) => ('+', new[j]),
+            };
+            text.push(marker);
+            text.push_str(line);
+            if !line.ends_with('\n') {
+                text.push_str("\n\\ No newline at end of file\n");
+            }
+        }
+    }
+
+    UnifiedDiff { text, additions, deletions }
+}
+
+/// Edit index ranges of the hunks: changes plus context, merged when their context overlaps
+fn hunk_ranges(edits: &[Edit]) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for (i, edit) in edits.iter().enumerate() {
+        if matches!(edit, Edit::Equal(..)) {
+            continue;
+        }
+        let start = i.saturating_sub(CONTEXT_LINES);
+        let end = (i + 1 + CONTEXT_LINES).min(edits.len());
+        match ranges.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => ranges.push((start, end)),
+        }
+    }
+    ranges
+}
+
+/// A run of text in a word-level diff
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct InlineSegment {
+    /// "equal", "delete" or "insert"
+    pub kind: String,
+    pub text: String,
+}
+
+/// A changed line with word-level segments. Replaced lines have both line numbers,
+/// added or removed lines only one.
+#[derive(Debug, Clone, Serialize)]
+pub struct InlineChange {
+    pub old_line: Option<usize>,
+    pub new_line: Option<usize>,
+    pub segments: Vec<InlineSegment>,
+}
+
+/// Word-level changes per line: within each block of changed lines, the n-th removed line
+/// is compared with the n-th added one
+pub fn inline_changes(original: &str, modified: &str, first_line: usize) -> Vec<InlineChange> {
+    let old: Vec<&str> = original.lines().collect();
+    let new: Vec<&str> = modified.lines().collect();
+    let offset = first_line.saturating_sub(1);
+
+    let mut changes = Vec::new();
+    let (mut deleted, mut inserted): (Vec<usize>, Vec<usize>) = (Vec::new(), Vec::new());
+    let mut flush = |deleted: &mut Vec<usize>, inserted: &mut Vec<usize>| {
+        for pair in 0..deleted.len().max(inserted.len()) {
+            let (d, i) = (deleted.get(pair).copied(), inserted.get(pair).copied());
+            let segments = match (d, i) {
+                (Some(d), Some(i)) => word_diff(old[d], new[i]),
+                (Some(d), None) => vec![segment("delete", old[d])],
+                (None, Some(i)) => vec![segment("insert", new[i])],
+                (None, None) => Vec::new(),
+            };
+            changes.push(InlineChange {
+                old_line: d.map(|d| d + 1 + offset),
+                new_line: i.map(|i| i + 1 + offset),
+                segments,
+            });
+        }
+        deleted.clear();
+        inserted.clear();
+    };
+    for edit in diff(&old, &new) {
+        match edit {
+            Edit::Delete(i) => deleted.push(i),
+            Edit::Insert(j) => inserted.push(j),
+            Edit::Equal(..) => flush(&mut deleted, &mut inserted),
+        }
+    }
+    flush(&mut deleted, &mut inserted);
+    changes
+}
+
+/// Diff of two lines by words, whitespace runs and single symbols
+pub fn word_diff(old: &str, new: &str) -> Vec<InlineSegment> {
+    let (old, new) = (words(old), words(new));
+    let mut segments: Vec<InlineSegment> = Vec::new();
+    for edit in diff(&old, &new) {
+        let (kind, text) = match edit {
+            Edit::Equal(i, _) => ("equal", old[i]),
+            Edit::Delete(i) => ("delete", old[i]),
+            Edit::Insert(j) => ("insert", new[j]),
+        };
+        match segments.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(text),
+            _ => segments.push(segment(kind, text)),
+        }
+    }
+    segments
+}
+
+fn segment(kind: &str, text: &str) -> InlineSegment {
+    InlineSegment { kind: kind.to_string(), text: text.to_string() }
+}
+
+fn words(line: &str) -> Vec<&str> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        }
+    };
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut previous: Option<(usize, char)> = None;
+    for (i, c) in line.char_indices() {
+        if let Some((_, p)) = previous {
+            // Symbols stand alone; words and whitespace form runs
+            if class(c) != class(p) || class(c) == 2 {
+                words.push(&line[start..i]);
+                start = i;
+            }
+        }
+        previous = Some((i, c));
+    }
+    if start < line.len() {
+        words.push(&line[start..]);
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_at_top_is_one_hunk() {
+        let original = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let modified = "new\na\nb\nc\nd\ne\nf\ng\nh\n";
+        let diff = unified_diff("src/a.rs", original, modified, 1);
+        assert_eq!(diff.additions, 1);
+        assert_eq!(diff.deletions, 0);
+        assert_eq!(diff.text, "--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1,3 +1,4 @@\n+new\n a\n b\n c\n");
+
+        // Blank lines count, and distant changes get separate hunks at file line numbers
+        let original = "a\nb\nc\nd\ne\nf\ng\nh\ni\n";
+        let modified = "a\n\nb\nc\nd\ne\nf\ng\nh\nI\n";
+        let diff = unified_diff("a", original, modified, 10);
+        assert_eq!((diff.additions, diff.deletions), (2, 1));
+        assert!(diff.text.contains("@@ -10,4 +10,5 @@\n a\n+\n b\n c\n d\n"));
+        assert!(diff.text.contains("@@ -15,4 +16,4 @@\n f\n g\n h\n-i\n+I\n"), "{}", diff.text);
+
+        assert_eq!(unified_diff("a", original, original, 1).text, "--- a/a\n+++ b/a\n");
+    }
+
+    #[test]
+    fn test_line_endings_are_part_of_the_diff() {
+        let diff = unified_diff("a", "x\ny\n", "x\ny", 1);
+        assert_eq!((diff.additions, diff.deletions), (1, 1));
+        assert_eq!(diff.text, "--- a/a\n+++ b/a\n@@ -1,2 +1,2 @@\n x\n-y\n+y\n\\ No newline at end of file\n");
+
+        let diff = unified_diff("a", "x\ny\n", "x\r\ny\n", 1);
+        assert_eq!((diff.additions, diff.deletions), (1, 1));
+        assert!(diff.text.contains("-x\n+x\r\n y\n"), "{}", diff.text);
+    }
+
+    #[test]
+    fn test_edit_script_rebuilds_the_target() {
+        let mut seed = 7u32;
+        let mut next = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (seed >> 16) % 4
+        };
+        for _ in 0..200 {
+            let a: Vec<u32> = (0..next() * 3).map(|_| next()).collect();
+            let b: Vec<u32> = (0..next() * 3).map(|_| next()).collect();
+            let edits = diff(&a, &b);
+            let rebuilt: Vec<u32> = edits
+                .iter()
+                .filter_map(|e| match *e {
+                    Edit::Equal(i, j) => {
+                        assert_eq!(a[i], b[j]);
+                        Some(a[i])
+                    }
+                    Edit::Insert(j) => Some(b[j]),
+                    Edit::Delete(_) => None,
+                })
+                .collect();
+            assert_eq!(rebuilt, b);
+            assert_eq!(edits.iter().filter(|e| !matches!(e, Edit::Insert(_))).count(), a.len());
+        }
+    }
+
+    #[test]
+    fn test_inline_word_diff() {
+        let changes = inline_changes("let x = foo(1);\nkeep\n", "let x = bar(1, 2);\nkeep\nadded\n", 1);
+        assert_eq!(changes.len(), 2);
+        assert_eq!((changes[0].old_line, changes[0].new_line), (Some(1), Some(1)));
+        let kinds: Vec<(&str, &str)> = changes[0].segments.iter().map(|s| (s.kind.as_str(), s.text.as_str())).collect();
+        assert_eq!(
+            kinds,
+            vec![("equal", "let x = "), ("delete", "foo"), ("insert", "bar"), ("equal", "(1"), ("insert", ", 2"), ("equal", ");")]
+        );
+        assert_eq!((changes[1].old_line, changes[1].new_line), (None, Some(3)));
+        assert_eq!(changes[1].segments, vec![segment("insert", "added")]);
+    }
+}
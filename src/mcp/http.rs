@@ -0,0 +1,477 @@
+// MCP Streamable HTTP transport
+// 1つの温まったインデックスを複数エージェントで共有する（POST/GET/DELETE /mcp, SSE 通知）
+// セッションごとに状態を持ち、共有するのはグラフキャッシュだけ
+#![cfg(feature = "web")]
+
+use anyhow::{Context, Result};
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::post,
+    Json, Router,
+};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use subtle::ConstantTimeEq;
+use tokio::sync::mpsc;
+
+use crate::mcp::protocol::{JsonRpcResponse, McpError, Payload, ServerMessage};
+use crate::mcp::server::{InFlight, McpServer};
+
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Where and how to listen
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    pub host: IpAddr,
+    pub port: u16,
+    /// Required as `Authorization: Bearer <token>` when set
+    pub token: Option<String>,
+    /// Sessions with no request and no open stream for this long are dropped
+    pub session_ttl: Duration,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            host: IpAddr::from([127, 0, 0, 1]),
+            port: 3939,
+            token: None,
+            session_ttl: Duration::from_secs(30 * 60),
+        }
+    }
+}
+
+/// SSE streams open for a session
+type Streams = Arc<Mutex<Vec<mpsc::UnboundedSender<String>>>>;
+
+/// One client: its own MCP state (initialize, roots, subscriptions, running requests)
+#[derive(Clone)]
+struct Session {
+    server: Arc<Mutex<McpServer>>,
+    /// Cancels this session's running requests without waiting for the server lock they hold
+    in_flight: InFlight,
+    streams: Streams,
+    /// Time of the session's last request
+    last_seen: Arc<Mutex<Instant>>,
+}
+
+impl Session {
+    fn start(template: &McpServer) -> Self {
+        let streams: Streams = Arc::default();
+        let mut server = template.new_session();
+
+        // Server-initiated messages (progress, resource updates, roots/list) go to this session's streams only
+        let targets = Arc::clone(&streams);
+        server.set_notifier(Arc::new(move |message: &ServerMessage| {
+            let Ok(message) = serde_json::to_string(message) else {
+                return;
+            };
+            targets.lock().unwrap().retain(|sender| sender.send(message.clone()).is_ok());
+        }));
+
+        Self {
+            in_flight: server.in_flight(),
+            server: Arc::new(Mutex::new(server)),
+            streams,
+            last_seen: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Nothing sent for `ttl` and no SSE stream still listening
+    fn is_expired(&self, now: Instant, ttl: Duration) -> bool {
+        let idle = now.duration_since(*self.last_seen.lock().unwrap()) >= ttl;
+        idle && self.streams.lock().unwrap().iter().all(|sender| sender.is_closed())
+    }
+}
+
+type Sessions = Arc<Mutex<HashMap<String, Session>>>;
+
+#[derive(Clone)]
+struct HttpState {
+    /// Sessions start from this server and share its graph cache, so all agents reuse the warm graphs
+    template: Arc<Mutex<McpServer>>,
+    sessions: Sessions,
+    session_ttl: Duration,
+    token: Option<String>,
+    /// Bound to a loopback address: only accept browser origins on localhost
+    local_only: bool,
+}
+
+/// Build the `/mcp` router around `server`
+pub fn router(server: McpServer, options: &HttpOptions) -> Router {
+    let state = HttpState {
+        template: Arc::new(Mutex::new(server)),
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+        session_ttl: options.session_ttl,
+        token: options.token.clone(),
+        local_only: options.host.is_loopback(),
+    };
+
+    Router::new()
+        .route("/mcp", post(post_handler).get(get_handler).delete(delete_handler))
+        .with_state(state)
+}
+
+/// Serve until the process is stopped
+pub async fn serve(server: McpServer, options: HttpOptions) -> Result<()> {
+    let addr = SocketAddr::new(options.host, options.port);
+    if !options.host.is_loopback() && options.token.is_none() {
+        eprintln!("⚠️  {} で公開していますが認証トークンが設定されていません (--token)", addr);
+    }
+
+    let app = router(server, &options);
+    let listener = tokio::net::TcpListener::bind(addr)
+        .await
+        .context(format!("Failed to bind {}", addr))?;
+    eprintln!("🔌 MCP server (HTTP): http://{}/mcp", listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+/// Why a request was turned away before reaching the MCP handlers
+enum Rejection {
+    Unauthorized,
+    ForeignOrigin,
+    MissingSession,
+    UnknownSession,
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> Response {
+        match self {
+            Rejection::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, "Bearer")],
+                "Missing or invalid bearer token",
+            )
+                .into_response(),
+            Rejection::ForeignOrigin => (StatusCode::FORBIDDEN, "Origin not allowed").into_response(),
+            Rejection::MissingSession => {
+                (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response()
+            }
+            Rejection::UnknownSession => (StatusCode::NOT_FOUND, "Unknown session").into_response(),
+        }
+    }
+}
+
+/// Bearer token and Origin checks shared by every method
+fn check_access(state: &HttpState, headers: &HeaderMap) -> Result<(), Rejection> {
+    if let Some(token) = &state.token {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.strip_prefix("Bearer "));
+        // Constant time, so response timing does not reveal how much of the token matched
+        let matches = presented.is_some_and(|presented| bool::from(presented.as_bytes().ct_eq(token.as_bytes())));
+        if !matches {
+            return Err(Rejection::Unauthorized);
+        }
+    }
+
+    // DNS rebinding protection: a page on another site must not reach a local server
+    if state.local_only {
+        if let Some(origin) = headers.get(header::ORIGIN).and_then(|v| v.to_str().ok()) {
+            if !is_local_origin(origin) {
+                return Err(Rejection::ForeignOrigin);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn is_local_origin(origin: &str) -> bool {
+    let host = origin.split_once("://").map_or(origin, |(_, rest)| rest);
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(v6),
+        None => host.split(':').next().unwrap_or(host),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// The session named by the request, if it exists (and has not expired)
+fn find_session(state: &HttpState, headers: &HeaderMap) -> Result<(String, Session), Rejection> {
+    let Some(id) = headers.get(SESSION_HEADER).and_then(|v| v.to_str().ok()) else {
+        return Err(Rejection::MissingSession);
+    };
+    let mut sessions = state.sessions.lock().unwrap();
+    evict_expired(&mut sessions, state.session_ttl);
+    match sessions.get(id) {
+        Some(session) => {
+            *session.last_seen.lock().unwrap() = Instant::now();
+            Ok((id.to_string(), session.clone()))
+        }
+        None => Err(Rejection::UnknownSession),
+    }
+}
+
+fn evict_expired(sessions: &mut HashMap<String, Session>, ttl: Duration) {
+    let now = Instant::now();
+    sessions.retain(|_, session| !session.is_expired(now, ttl));
+}
+
+/// 128 bits from the OS CSPRNG
+fn new_session_id() -> Result<String, getrandom::Error> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// POST /mcp: one JSON-RPC message or a batch
+async fn post_handler(State(state): State<HttpState>, headers: HeaderMap, body: Bytes) -> Response {
+    if let Err(rejection) = check_access(&state, &headers) {
+        return rejection.into_response();
+    }
+
+    let mut payload = match Payload::parse(&body) {
+        Ok(payload) => payload,
+        Err(error) => return Json(error).into_response(),
+    };
+
+    // Everything but initialize needs a live session
+    let initializing = payload.requests().any(|r| r.method == "initialize");
+    let (id, session) = if initializing {
+        let id = match new_session_id() {
+            Ok(id) => id,
+            Err(e) => {
+                let error = JsonRpcResponse::error(None, McpError::INTERNAL_ERROR, &e.to_string());
+                return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+            }
+        };
+        let session = Session::start(&state.template.lock().unwrap());
+        let mut sessions = state.sessions.lock().unwrap();
+        evict_expired(&mut sessions, state.session_ttl);
+        sessions.insert(id.clone(), session.clone());
+        (id, session)
+    } else {
+        match find_session(&state, &headers) {
+            Ok(found) => found,
+            Err(rejection) => return rejection.into_response(),
+        }
+    };
+
+    session.in_flight.intercept(&mut payload);
+
+    // Tool calls scan and read files: keep them off the async workers
+    let server = Arc::clone(&session.server);
+    let handled = tokio::task::spawn_blocking(move || {
+        // Tool calls run on a copy of the session, so they do not hold it while they work
+        if McpServer::runs_on_worker(&payload) {
+            let mut worker = server.lock().unwrap().worker();
+            worker.handle_payload(payload)
+        } else {
+            server.lock().unwrap().handle_payload(payload)
+        }
+    })
+    .await;
+    let reply = match handled {
+        Ok(reply) => reply,
+        Err(e) => {
+            let error = JsonRpcResponse::error(None, McpError::INTERNAL_ERROR, &e.to_string());
+            return (StatusCode::INTERNAL_SERVER_ERROR, Json(error)).into_response();
+        }
+    };
+
+    // Only notifications and responses: accepted, nothing to return
+    let mut response = match reply {
+        Some(reply) => Json(reply).into_response(),
+        None => StatusCode::ACCEPTED.into_response(),
+    };
+    if initializing {
+        if let Ok(value) = id.parse() {
+            response.headers_mut().insert(SESSION_HEADER, value);
+        }
+    }
+    response
+}
+
+/// GET /mcp: SSE stream of server-initiated messages for a session
+async fn get_handler(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    if let Err(rejection) = check_access(&state, &headers) {
+        return rejection.into_response();
+    }
+    let accepts_sse = headers
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("text/event-stream"));
+    if !accepts_sse {
+        return (StatusCode::NOT_ACCEPTABLE, "Accept: text/event-stream required").into_response();
+    }
+    let session = match find_session(&state, &headers) {
+        Ok((_, session)) => session,
+        Err(rejection) => return rejection.into_response(),
+    };
+
+    let (sender, receiver) = mpsc::unbounded_channel::<String>();
+    session.streams.lock().unwrap().push(sender);
+    let events = futures_util::stream::unfold(receiver, |mut receiver| async move {
+        let message = receiver.recv().await?;
+        Some((Ok::<_, Infallible>(Event::default().event("message").data(message)), receiver))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default()).into_response()
+}
+
+/// DELETE /mcp: end a session (its streams close)
+async fn delete_handler(State(state): State<HttpState>, headers: HeaderMap) -> Response {
+    if let Err(rejection) = check_access(&state, &headers) {
+        return rejection.into_response();
+    }
+    match find_session(&state, &headers) {
+        Ok((id, _)) => {
+            state.sessions.lock().unwrap().remove(&id);
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(rejection) => rejection.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    async fn start(options: HttpOptions) -> String {
+        let listener = tokio::net::TcpListener::bind((options.host, 0)).await.unwrap();
+        let url = format!("http://{}/mcp", listener.local_addr().unwrap());
+        let app = router(McpServer::new(None), &options);
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        url
+    }
+
+    #[tokio::test]
+    async fn test_sessions_and_auth() {
+        let url = start(HttpOptions { token: Some("secret".to_string()), ..Default::default() }).await;
+        let client = reqwest::Client::new();
+        let initialize = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "capabilities": {}, "clientInfo": { "name": "test", "version": "1" } }
+        });
+
+        let unauthorized = client.post(&url).json(&initialize).send().await.unwrap();
+        assert_eq!(unauthorized.status(), 401);
+
+        let response = client.post(&url).bearer_auth("secret").json(&initialize).send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        let session = response.headers()[SESSION_HEADER].to_str().unwrap().to_string();
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body["result"]["serverInfo"]["name"], "index-chan");
+
+        let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+        let missing = client.post(&url).bearer_auth("secret").json(&list).send().await.unwrap();
+        assert_eq!(missing.status(), 400);
+
+        let batch = json!([list, { "jsonrpc": "2.0", "method": "notifications/initialized" }]);
+        let response = client
+            .post(&url)
+            .bearer_auth("secret")
+            .header(SESSION_HEADER, &session)
+            .json(&batch)
+            .send()
+            .await
+            .unwrap();
+        let body: Value = response.json().await.unwrap();
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert!(body[0]["result"]["tools"].as_array().is_some_and(|tools| !tools.is_empty()));
+
+        let foreign = client
+            .post(&url)
+            .bearer_auth("secret")
+            .header(SESSION_HEADER, &session)
+            .header("origin", "https://evil.example")
+            .json(&list)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(foreign.status(), 403);
+
+        let deleted = client.delete(&url).bearer_auth("secret").header(SESSION_HEADER, &session).send().await.unwrap();
+        assert_eq!(deleted.status(), 204);
+        let gone = client.post(&url).bearer_auth("secret").header(SESSION_HEADER, &session).json(&list).send().await.unwrap();
+        assert_eq!(gone.status(), 404);
+    }
+
+    #[tokio::test]
+    async fn test_idle_sessions_expire() {
+        let url = start(HttpOptions { session_ttl: Duration::from_millis(50), ..Default::default() }).await;
+        let client = reqwest::Client::new();
+        let session = open_session(&client, &url, json!({})).await;
+        assert_eq!(session.len(), 32);
+        send(&client, &url, &session, json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await;
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+        let expired = client.post(&url).header(SESSION_HEADER, &session).json(&list).send().await.unwrap();
+        assert_eq!(expired.status(), 404);
+    }
+
+    async fn open_session(client: &reqwest::Client, url: &str, capabilities: Value) -> String {
+        let initialize = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "capabilities": capabilities, "clientInfo": { "name": "test", "version": "1" } }
+        });
+        let response = client.post(url).json(&initialize).send().await.unwrap();
+        response.headers()[SESSION_HEADER].to_str().unwrap().to_string()
+    }
+
+    async fn send(client: &reqwest::Client, url: &str, session: &str, message: Value) {
+        let response = client.post(url).header(SESSION_HEADER, session).json(&message).send().await.unwrap();
+        assert!(response.status().is_success());
+    }
+
+    /// Next server-initiated message on an SSE stream
+    async fn next_message(stream: &mut reqwest::Response, buffer: &mut String) -> Value {
+        loop {
+            if let Some(end) = buffer.find("\n\n") {
+                let event: String = buffer.drain(..end + 2).collect();
+                if let Some(data) = event.lines().find_map(|line| line.strip_prefix("data:")) {
+                    return serde_json::from_str(data.trim()).unwrap();
+                }
+                continue;
+            }
+            let chunk = stream.chunk().await.unwrap().expect("stream closed");
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sessions_keep_their_own_state() {
+        let url = start(HttpOptions::default()).await;
+        let client = reqwest::Client::new();
+        // Only the first client supports roots; the second initialize must not overwrite that
+        let first = open_session(&client, &url, json!({ "roots": {} })).await;
+        let second = open_session(&client, &url, json!({})).await;
+
+        let mut streams = Vec::new();
+        for session in [&first, &second] {
+            let stream = client
+                .get(&url)
+                .header(SESSION_HEADER, session)
+                .header("accept", "text/event-stream")
+                .send()
+                .await
+                .unwrap();
+            streams.push((stream, String::new()));
+        }
+
+        send(&client, &url, &first, json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await;
+        send(&client, &url, &second, json!({ "jsonrpc": "2.0", "id": 2, "method": "logging/setLevel", "params": { "level": "debug" } })).await;
+        send(&client, &url, &second, json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await;
+        send(&client, &url, &second, json!({ "jsonrpc": "2.0", "method": "notifications/unknown" })).await;
+
+        // Each message reaches the stream of the session that caused it
+        let (stream, buffer) = &mut streams[0];
+        assert_eq!(next_message(stream, buffer).await["method"], "roots/list");
+        let (stream, buffer) = &mut streams[1];
+        let message = next_message(stream, buffer).await;
+        assert_eq!(message["method"], "notifications/message");
+        assert!(message["params"]["data"].as_str().unwrap().contains("notifications/unknown"));
+    }
+}
@@ -5,7 +5,20 @@ pub mod protocol;
 pub mod server;
 pub mod tools;
 pub mod context;
+pub mod diff;
 pub mod changes;
+pub mod resources;
+pub mod paging;
+pub mod prompts;
+pub mod rename;
+pub mod roots;
+pub mod schema;
+pub mod tokens;
+#[cfg(feature = "web")]
+pub mod http;
+
+#[cfg(test)]
+mod conformance;
 
 pub use server::McpServer;
 pub use protocol::{JsonRpcRequest, JsonRpcResponse, McpError};
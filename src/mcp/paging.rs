@@ -0,0 +1,183 @@
+// Pagination and size budgets for list results
+// 大きな一覧をカーソルで分割し、max_results / max_tokens に収めて返す（エージェントのコンテキスト溢れ対策）
+
+use serde_json::{json, Map, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+
+use crate::mcp::tokens::count_tokens;
+
+/// Arguments that only shape the page, not the result set
+const PAGE_ARGUMENTS: &[&str] = &["cursor", "max_results", "max_tokens"];
+
+/// Which part of a list the client asked for
+#[derive(Debug, Clone, Default)]
+pub struct PageRequest {
+    pub offset: usize,
+    pub max_results: Option<usize>,
+    /// Budget for the returned items, counted as cl100k_base tokens of their JSON
+    pub max_tokens: Option<usize>,
+    /// Hash of the other arguments: a cursor only continues the listing it came from
+    fingerprint: u64,
+}
+
+impl PageRequest {
+    /// Read `cursor`, `max_results` and `max_tokens` from tool arguments
+    pub fn from_args(args: &Value) -> Result<Self, String> {
+        let fingerprint = fingerprint(args);
+        let offset = match args.get("cursor").and_then(Value::as_str) {
+            Some(cursor) => decode_cursor(cursor, fingerprint)?,
+            None => 0,
+        };
+        let limit = |name: &str| args.get(name).and_then(Value::as_u64).map(|n| n.max(1) as usize);
+        Ok(Self {
+            offset,
+            max_results: limit("max_results"),
+            max_tokens: limit("max_tokens"),
+            fingerprint,
+        })
+    }
+}
+
+/// One page of a list, plus what the client needs to ask for the rest
+#[derive(Debug, Clone)]
+pub struct Page {
+    pub items: Vec<Value>,
+    /// `{ offset, returned, total, next_cursor, summary }`; summary is null unless truncated
+    pub info: Value,
+}
+
+/// Cut `items` to the page asked for. When something is left out, `summary` counts every item
+/// (not just this page) by the given `(key, field)` pairs, e.g. `("by_file", "file")`.
+pub fn paginate(items: Vec<Value>, request: &PageRequest, summary: &[(&str, &str)]) -> Page {
+    let total = items.len();
+    let offset = request.offset.min(total);
+
+    let mut end = offset;
+    let mut tokens = 0;
+    while end < total && request.max_results.is_none_or(|max| end - offset < max) {
+        let cost = item_tokens(&items[end]);
+        // Always return at least one item so paging makes progress
+        if request.max_tokens.is_some_and(|budget| end > offset && tokens + cost > budget) {
+            break;
+        }
+        tokens += cost;
+        end += 1;
+    }
+
+    let truncated = offset > 0 || end < total;
+    let summary = if truncated { summarize(&items, summary) } else { Value::Null };
+    let next_cursor = (end < total).then(|| encode_cursor(end, request.fingerprint));
+    let info = json!({
+        "offset": offset,
+        "returned": end - offset,
+        "total": total,
+        "next_cursor": next_cursor,
+        "summary": summary
+    });
+
+    let items = items.into_iter().skip(offset).take(end - offset).collect();
+    Page { items, info }
+}
+
+/// Tokens an item adds to the page: its JSON, plus the separator after it
+fn item_tokens(item: &Value) -> usize {
+    count_tokens(&item.to_string()) + 1
+}
+
+fn summarize(items: &[Value], fields: &[(&str, &str)]) -> Value {
+    let mut summary = Map::new();
+    for (key, field) in fields {
+        let mut counts: BTreeMap<String, u64> = BTreeMap::new();
+        for item in items {
+            let value = match item.get(*field) {
+                Some(Value::String(s)) => s.clone(),
+                Some(Value::Null) | None => continue,
+                Some(other) => other.to_string(),
+            };
+            *counts.entry(value).or_insert(0) += 1;
+        }
+        summary.insert(key.to_string(), json!(counts));
+    }
+    Value::Object(summary)
+}
+
+fn fingerprint(args: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    if let Some(object) = args.as_object() {
+        for (name, value) in object {
+            if !PAGE_ARGUMENTS.contains(&name.as_str()) {
+                name.hash(&mut hasher);
+                value.to_string().hash(&mut hasher);
+            }
+        }
+    }
+    hasher.finish()
+}
+
+fn encode_cursor(offset: usize, fingerprint: u64) -> String {
+    format!("{}.{:016x}", offset, fingerprint)
+}
+
+fn decode_cursor(cursor: &str, fingerprint: u64) -> Result<usize, String> {
+    let invalid = || format!("Invalid cursor: {}", cursor);
+    let (offset, hash) = cursor.split_once('.').ok_or_else(invalid)?;
+    let offset = offset.parse().map_err(|_| invalid())?;
+    let hash = u64::from_str_radix(hash, 16).map_err(|_| invalid())?;
+    if hash != fingerprint {
+        return Err("Cursor belongs to a different query: repeat the other arguments unchanged".to_string());
+    }
+    Ok(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<Value> {
+        (0..5)
+            .map(|i| json!({ "name": format!("f{}", i), "file": if i < 3 { "a.ts" } else { "b.ts" } }))
+            .collect()
+    }
+
+    #[test]
+    fn test_pages_follow_the_cursor() {
+        let args = json!({ "directory": ".", "max_results": 2 });
+        let first = paginate(items(), &PageRequest::from_args(&args).unwrap(), &[("by_file", "file")]);
+        assert_eq!(first.items.len(), 2);
+        assert_eq!(first.info["total"], 5);
+        assert_eq!(first.info["summary"]["by_file"], json!({ "a.ts": 3, "b.ts": 2 }));
+
+        let cursor = first.info["next_cursor"].as_str().unwrap();
+        let args = json!({ "directory": ".", "max_results": 10, "cursor": cursor });
+        let rest = paginate(items(), &PageRequest::from_args(&args).unwrap(), &[]);
+        assert_eq!(rest.items[0]["name"], "f2");
+        assert_eq!(rest.info["returned"], 3);
+        assert!(rest.info["next_cursor"].is_null());
+
+        // A cursor does not carry over to other arguments
+        let other = json!({ "directory": "elsewhere", "cursor": cursor });
+        assert!(PageRequest::from_args(&other).is_err());
+        assert!(PageRequest::from_args(&json!({ "cursor": "x" })).is_err());
+
+        // Without limits nothing is cut and no summary is sent
+        let all = paginate(items(), &PageRequest::default(), &[("by_file", "file")]);
+        assert_eq!(all.items.len(), 5);
+        assert!(all.info["summary"].is_null());
+    }
+
+    #[test]
+    fn test_token_budget_keeps_at_least_one_item() {
+        let request = PageRequest::from_args(&json!({ "max_tokens": 1 })).unwrap();
+        let page = paginate(items(), &request, &[]);
+        assert_eq!(page.items.len(), 1);
+        assert!(page.info["next_cursor"].is_string());
+
+        // A budget of exactly two items returns those two
+        let two: usize = items()[..2].iter().map(item_tokens).sum();
+        let request = PageRequest::from_args(&json!({ "max_tokens": two })).unwrap();
+        let page = paginate(items(), &request, &[]);
+        assert_eq!(page.items.len(), 2);
+    }
+}
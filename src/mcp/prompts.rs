@@ -0,0 +1,299 @@
+// MCP Prompts
+// よく使うリファクタリング手順（安全なデッドコード削除・変更の影響範囲など）をプロンプトとして提供する
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::detector::{detect_dead_code, DeadCode, SafetyLevel};
+use crate::graph::{CodeGraph, CodeNode};
+use crate::mcp::context::{ContextFormat, ContextGenerator, ContextMode, DependencyInfo};
+use crate::mcp::protocol::{GetPromptResult, PromptArgument, PromptDefinition, PromptMessage, ToolContent};
+
+/// Get all prompt definitions
+pub fn get_prompt_definitions() -> Vec<PromptDefinition> {
+    let symbol = PromptArgument {
+        name: "symbol".to_string(),
+        description: "Symbol id, qualified name (Type::method, file::name) or function name".to_string(),
+        required: true,
+    };
+    vec![
+        PromptDefinition {
+            name: "remove_dead_code".to_string(),
+            description: "Remove an unused function or class safely, with its safety level, dependents and context"
+                .to_string(),
+            arguments: vec![symbol.clone()],
+        },
+        PromptDefinition {
+            name: "blast_radius".to_string(),
+            description: "Explain what is affected by changing a function: dependents, files and packages".to_string(),
+            arguments: vec![
+                symbol,
+                PromptArgument {
+                    name: "depth".to_string(),
+                    description: "How many levels of dependents to include (default: 2)".to_string(),
+                    required: false,
+                },
+            ],
+        },
+        PromptDefinition {
+            name: "triage_dead_code".to_string(),
+            description: "Sort all dead code findings into delete / keep / investigate".to_string(),
+            arguments: vec![PromptArgument {
+                name: "package".to_string(),
+                description: "Only findings in this workspace package".to_string(),
+                required: false,
+            }],
+        },
+    ]
+}
+
+/// Render a prompt against the current graph
+pub fn get_prompt(
+    graph: impl Into<Arc<CodeGraph>>,
+    name: &str,
+    arguments: &HashMap<String, String>,
+) -> Result<GetPromptResult, String> {
+    let graph: Arc<CodeGraph> = graph.into();
+    let generator = ContextGenerator::from_graph(Arc::clone(&graph));
+    let graph = graph.as_ref();
+    let (description, text) = match name {
+        "remove_dead_code" => {
+            let node = resolve_symbol(&generator, graph, required(arguments, "symbol")?)?;
+            (format!("Remove `{}` safely", node.name), remove_dead_code(graph, &generator, node)?)
+        }
+        "blast_radius" => {
+            let node = resolve_symbol(&generator, graph, required(arguments, "symbol")?)?;
+            let depth = match arguments.get("depth") {
+                Some(depth) => depth.parse().map_err(|_| format!("Invalid depth: {}", depth))?,
+                None => 2,
+            };
+            (format!("Blast radius of `{}`", node.name), blast_radius(graph, &generator, node, depth)?)
+        }
+        "triage_dead_code" => {
+            let package = arguments.get("package").map(String::as_str).filter(|p| !p.is_empty());
+            ("Triage dead code".to_string(), triage_dead_code(graph, package)?)
+        }
+        _ => return Err(format!("Unknown prompt: {}", name)),
+    };
+
+    Ok(GetPromptResult {
+        description,
+        messages: vec![PromptMessage {
+            role: "user".to_string(),
+            content: ToolContent::Text { text },
+        }],
+    })
+}
+
+fn required<'a>(arguments: &'a HashMap<String, String>, name: &str) -> Result<&'a str, String> {
+    arguments
+        .get(name)
+        .map(String::as_str)
+        .filter(|v| !v.is_empty())
+        .ok_or_else(|| format!("Missing argument: {}", name))
+}
+
+/// Plain names must be unambiguous; otherwise the candidates' symbol ids are listed
+fn resolve_symbol<'a>(generator: &'a ContextGenerator, graph: &CodeGraph, symbol: &str) -> Result<&'a CodeNode, String> {
+    let node = generator
+        .find_node_by_qualified_name(symbol)
+        .ok_or_else(|| format!("Symbol not found: {}", symbol))?;
+    if node.symbol_id != symbol && node.qualified_name != symbol {
+        let mut candidates: Vec<&str> = graph.nodes_by_name(symbol).map(|n| n.symbol_id.as_str()).collect();
+        if candidates.len() > 1 {
+            candidates.sort();
+            return Err(format!("Ambiguous symbol `{}`, use one of: {}", symbol, candidates.join(", ")));
+        }
+    }
+    Ok(node)
+}
+
+fn location(node: &CodeNode) -> String {
+    format!("{}:{}-{}", node.file_path.display(), node.line_range.0, node.line_range.1)
+}
+
+fn context(generator: &ContextGenerator, node: &CodeNode, depth: usize) -> Result<String, String> {
+    let entry = if node.symbol_id.is_empty() { &node.name } else { &node.symbol_id };
+    generator
+        .gather_context(Some(entry), None, depth, ContextMode::Full, ContextFormat::Standard, None)
+        .map(|result| result.content)
+        .map_err(|e| e.to_string())
+}
+
+fn dependency_list(out: &mut String, deps: &[DependencyInfo]) {
+    if deps.is_empty() {
+        out.push_str("- (none)\n");
+    }
+    for dep in deps {
+        let _ = writeln!(out, "- `{}` ({}) {}:{}", dep.name, dep.node_type, dep.file_path, dep.line);
+    }
+}
+
+fn remove_dead_code(graph: &CodeGraph, generator: &ContextGenerator, node: &CodeNode) -> Result<String, String> {
+    let finding = detect_dead_code(graph).into_iter().find(|d| d.node.id == node.id);
+    let dependents = generator.get_dependents(&node.symbol_id, 1);
+    let dependencies = generator.get_dependencies(&node.symbol_id, 1);
+
+    let mut out = String::new();
+    let _ = writeln!(out, "Remove the unused {:?} `{}` ({}) safely.\n", node.node_type, node.name, location(node));
+
+    out.push_str("## Dead code analysis\n");
+    match &finding {
+        Some(dead) => {
+            let _ = writeln!(out, "- Safety level: {:?}", dead.safety_level);
+            let _ = writeln!(out, "- Reason: {}", dead.reason);
+            if dead.safety_level == SafetyLevel::NeedsReview {
+                out.push_str("- ⚠️ Needs review: explain the risk and ask before deleting.\n");
+            }
+        }
+        None => {
+            out.push_str("- ⚠️ Not reported as dead code (it is used, exported or an entry point). ");
+            out.push_str("Do not delete it unless every dependent below is removed first.\n");
+        }
+    }
+
+    out.push_str("\n## Dependents (callers / importers)\n");
+    dependency_list(&mut out, &dependents);
+    out.push_str("\n## Dependencies (may become unused after removal)\n");
+    dependency_list(&mut out, &dependencies);
+
+    let _ = writeln!(out, "\n## Context\n```\n{}\n```\n", context(generator, node, 1)?);
+
+    out.push_str("## Steps\n");
+    out.push_str("1. Check for references the graph cannot see: dynamic lookups by string, reflection, framework hooks, public API consumed outside the project.\n");
+    out.push_str("2. Delete the definition and any imports that only served it.\n");
+    out.push_str("3. List dependencies that would become unused; do not delete them in the same change.\n");
+    out.push_str("4. Run `validate_changes`, show the diff with `preview_changes`, then `apply_changes`.\n");
+    Ok(out)
+}
+
+fn blast_radius(graph: &CodeGraph, generator: &ContextGenerator, node: &CodeNode, depth: usize) -> Result<String, String> {
+    let direct = generator.get_dependents(&node.symbol_id, 1);
+    let all = generator.get_dependents(&node.symbol_id, depth);
+
+    let files: BTreeSet<&str> = all.iter().map(|d| d.file_path.as_str()).collect();
+    let packages: BTreeSet<&str> = all
+        .iter()
+        .filter_map(|d| graph.find_by_symbol_id(&d.symbol_id))
+        .map(|n| n.package.as_str())
+        .filter(|p| !p.is_empty() && *p != node.package)
+        .collect();
+
+    let mut out = String::new();
+    let _ = writeln!(out, "Explain the blast radius of changing `{}` ({}).\n", node.name, location(node));
+    if !node.signature.is_empty() {
+        let _ = writeln!(out, "Signature: `{}`\n", node.signature);
+    }
+
+    out.push_str("## Summary\n");
+    let _ = writeln!(out, "- Direct dependents: {}", direct.len());
+    let _ = writeln!(out, "- Dependents within depth {}: {}", depth, all.len());
+    let _ = writeln!(out, "- Files affected: {}", files.len());
+    if !packages.is_empty() {
+        let _ = writeln!(out, "- Other packages affected: {}", packages.into_iter().collect::<Vec<_>>().join(", "));
+    }
+    if node.is_exported {
+        out.push_str("- Exported: callers outside the project may also depend on it\n");
+    }
+
+    out.push_str("\n## Direct dependents\n");
+    dependency_list(&mut out, &direct);
+    if all.len() > direct.len() {
+        let _ = writeln!(out, "\n## Indirect dependents (depth {})", depth);
+        let indirect: Vec<DependencyInfo> = all
+            .iter()
+            .filter(|d| !direct.iter().any(|x| x.symbol_id == d.symbol_id))
+            .cloned()
+            .collect();
+        dependency_list(&mut out, &indirect);
+    }
+
+    let _ = writeln!(out, "\n## Context\n```\n{}\n```\n", context(generator, node, 1)?);
+
+    out.push_str("## Answer with\n");
+    out.push_str("1. What breaks if the signature changes, and what if only the behaviour changes.\n");
+    out.push_str("2. Dependents grouped by file (and package), with the riskiest first.\n");
+    out.push_str("3. Which tests or entry points to run to verify the change.\n");
+    Ok(out)
+}
+
+fn triage_dead_code(graph: &CodeGraph, package: Option<&str>) -> Result<String, String> {
+    if let Some(name) = package {
+        if !graph.nodes.values().any(|n| n.package == name) {
+            return Err(format!("Package not found: {}", name));
+        }
+    }
+    let mut findings: Vec<DeadCode> = detect_dead_code(graph)
+        .into_iter()
+        .filter(|d| package.is_none_or(|name| d.node.package == name))
+        .collect();
+    findings.sort_by(|a, b| (&a.node.file_path, a.node.line_range.0).cmp(&(&b.node.file_path, b.node.line_range.0)));
+
+    let mut out = String::new();
+    match package {
+        Some(name) => {
+            let _ = writeln!(out, "Triage the {} dead code findings in package `{}`.\n", findings.len(), name);
+        }
+        None => {
+            let _ = writeln!(out, "Triage the {} dead code findings of the project.\n", findings.len());
+        }
+    }
+
+    for level in [SafetyLevel::DefinitelySafe, SafetyLevel::ProbablySafe, SafetyLevel::NeedsReview] {
+        let group: Vec<&DeadCode> = findings.iter().filter(|d| d.safety_level == level).collect();
+        if group.is_empty() {
+            continue;
+        }
+        let _ = writeln!(out, "## {:?} ({})", level, group.len());
+        for dead in group {
+            let _ = writeln!(out, "- `{}` {} ({}) — {}", dead.node.name, location(&dead.node), dead.node.symbol_id, dead.reason);
+        }
+        out.push('\n');
+    }
+
+    out.push_str("## Answer with\n");
+    out.push_str("Sort every finding into **delete**, **keep** (planned or externally used: suggest an annotation) or **investigate**, ");
+    out.push_str("with one line of reasoning each. Use the `remove_dead_code` prompt for the ones to delete.\n");
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_project;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn test_prompts_embed_graph_facts() {
+        let temp = temp_project();
+        let root = temp.path();
+        std::fs::write(
+            root.join("app.ts"),
+            "function main() {\n  parse();\n}\nfunction parse() {\n  tokenize();\n}\nfunction tokenize() {}\nfunction legacy() {}\n",
+        )
+        .unwrap();
+        let graph = Arc::new(Scanner::with_incremental(false).unwrap().scan_directory(root).unwrap());
+        let args = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+        };
+        let text = |result: GetPromptResult| match &result.messages[0].content {
+            ToolContent::Text { text } => text.clone(),
+        };
+
+        let removal = text(get_prompt(Arc::clone(&graph), "remove_dead_code", &args(&[("symbol", "legacy")])).unwrap());
+        assert!(removal.contains("Safety level: DefinitelySafe"));
+        assert!(removal.contains("function legacy() {}"));
+
+        let radius = text(get_prompt(Arc::clone(&graph), "blast_radius", &args(&[("symbol", "tokenize")])).unwrap());
+        assert!(radius.contains("- Direct dependents: 1"));
+        assert!(radius.contains("- Dependents within depth 2: 2"));
+        assert!(radius.contains("`main`"));
+
+        let triage = text(get_prompt(Arc::clone(&graph), "triage_dead_code", &HashMap::new()).unwrap());
+        assert!(triage.contains("1 dead code findings"));
+
+        assert!(get_prompt(Arc::clone(&graph), "blast_radius", &HashMap::new()).is_err());
+        assert!(get_prompt(Arc::clone(&graph), "remove_dead_code", &args(&[("symbol", "missing")])).is_err());
+    }
+}
@@ -9,7 +9,7 @@ pub struct JsonRpcRequest {
     pub jsonrpc: String,
     pub id: Option<Value>,
     pub method: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub params: Option<Value>,
 }
 
@@ -17,7 +17,7 @@ pub struct JsonRpcRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JsonRpcResponse {
     pub jsonrpc: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// `null` when the request id could not be determined
     pub id: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<Value>,
@@ -43,6 +43,9 @@ impl McpError {
     pub const METHOD_NOT_FOUND: i32 = -32601;
     pub const INVALID_PARAMS: i32 = -32602;
     pub const INTERNAL_ERROR: i32 = -32603;
+    pub const RESOURCE_NOT_FOUND: i32 = -32002;
+    /// The request was cancelled by `notifications/cancelled` (never sent to the client)
+    pub const REQUEST_CANCELLED: i32 = -32800;
 }
 
 impl JsonRpcResponse {
@@ -55,6 +58,11 @@ impl JsonRpcResponse {
         }
     }
 
+    /// Responses to cancelled requests are dropped rather than sent
+    pub fn is_cancelled(&self) -> bool {
+        self.error.as_ref().is_some_and(|e| e.code == McpError::REQUEST_CANCELLED)
+    }
+
     pub fn error(id: Option<Value>, code: i32, message: &str) -> Self {
         Self {
             jsonrpc: "2.0".to_string(),
@@ -67,6 +75,202 @@ impl JsonRpcResponse {
             }),
         }
     }
+
+    /// Error with machine-readable details in `data`
+    pub fn error_with_data(id: Option<Value>, code: i32, message: &str, data: Value) -> Self {
+        let mut response = Self::error(id, code, message);
+        if let Some(error) = response.error.as_mut() {
+            error.data = Some(data);
+        }
+        response
+    }
+}
+
+/// Protocol revisions this server speaks, newest first
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &["2025-06-18", "2025-03-26", "2024-11-05"];
+
+/// Whether clients may send JSON-RPC batches under this protocol revision (2025-06-18 removed them)
+pub fn supports_batches(version: &str) -> bool {
+    version < "2025-06-18"
+}
+
+/// Version to answer `initialize` with: the client's if we speak it, otherwise our latest
+pub fn negotiate_protocol_version(requested: &str) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|v| **v == requested)
+        .unwrap_or(&SUPPORTED_PROTOCOL_VERSIONS[0])
+}
+
+/// One message received from the client
+#[derive(Debug, Clone)]
+pub enum Incoming {
+    /// Request (with id) or notification (without)
+    Request(JsonRpcRequest),
+    /// Response to a server-initiated request (e.g. `roots/list`): nothing to answer
+    Response(JsonRpcResponse),
+    /// Not a valid message: send this error back
+    Invalid(JsonRpcResponse),
+}
+
+impl Incoming {
+    pub fn parse(value: Value) -> Self {
+        let invalid = |id: Option<Value>, message: &str| {
+            Incoming::Invalid(JsonRpcResponse::error(id, McpError::INVALID_REQUEST, message))
+        };
+        let Value::Object(object) = value else {
+            return invalid(None, "Invalid Request: expected an object");
+        };
+
+        // MCP ids are strings or integers; anything else cannot be echoed back
+        let id = match object.get("id") {
+            None => None,
+            Some(id @ Value::String(_)) => Some(id.clone()),
+            Some(id @ Value::Number(n)) if n.is_i64() || n.is_u64() => Some(id.clone()),
+            Some(_) => return invalid(None, "Invalid Request: id must be a string or an integer"),
+        };
+        if object.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return invalid(id, "Invalid Request: jsonrpc must be \"2.0\"");
+        }
+        let Some(method) = object.get("method") else {
+            if id.is_some() && (object.contains_key("result") || object.contains_key("error")) {
+                return Incoming::Response(JsonRpcResponse {
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                    result: object.get("result").cloned(),
+                    error: object.get("error").and_then(|e| serde_json::from_value(e.clone()).ok()),
+                });
+            }
+            return invalid(id, "Invalid Request: missing method");
+        };
+        let Some(method) = method.as_str() else {
+            return invalid(id, "Invalid Request: method must be a string");
+        };
+        let params = match object.get("params") {
+            None => None,
+            Some(params @ (Value::Object(_) | Value::Array(_))) => Some(params.clone()),
+            Some(_) => return invalid(id, "Invalid Request: params must be an object or an array"),
+        };
+
+        Incoming::Request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id,
+            method: method.to_string(),
+            params,
+        })
+    }
+
+    /// `initialize` must be sent on its own: inside a batch it is an invalid request
+    fn outside_batch_only(self) -> Self {
+        match self {
+            Incoming::Request(request) if request.method == "initialize" => Incoming::Invalid(JsonRpcResponse::error(
+                request.id,
+                McpError::INVALID_REQUEST,
+                "Invalid Request: initialize must not be part of a batch",
+            )),
+            other => other,
+        }
+    }
+}
+
+/// A single message or a batch, as received in one line (stdio) or body (HTTP)
+#[derive(Debug, Clone)]
+pub struct Payload {
+    pub messages: Vec<Incoming>,
+    pub batch: bool,
+}
+
+impl Payload {
+    /// Err is the response to send back: invalid JSON or an empty batch
+    pub fn parse(text: &[u8]) -> Result<Self, Box<JsonRpcResponse>> {
+        let value: Value = serde_json::from_slice(text).map_err(|e| {
+            Box::new(JsonRpcResponse::error(None, McpError::PARSE_ERROR, &format!("Parse error: {}", e)))
+        })?;
+        match value {
+            Value::Array(items) if items.is_empty() => Err(Box::new(JsonRpcResponse::error(
+                None,
+                McpError::INVALID_REQUEST,
+                "Invalid Request: empty batch",
+            ))),
+            Value::Array(items) => Ok(Self {
+                messages: items.into_iter().map(Incoming::parse).map(Incoming::outside_batch_only).collect(),
+                batch: true,
+            }),
+            single => Ok(Self {
+                messages: vec![Incoming::parse(single)],
+                batch: false,
+            }),
+        }
+    }
+
+    /// Requests (not notifications or responses) in this payload
+    pub fn requests(&self) -> impl Iterator<Item = &JsonRpcRequest> {
+        self.messages.iter().filter_map(|m| match m {
+            Incoming::Request(request) => Some(request),
+            _ => None,
+        })
+    }
+
+    /// What to send back for these responses: nothing, one response, or a batch
+    pub fn reply(&self, responses: Vec<JsonRpcResponse>) -> Option<Value> {
+        if responses.is_empty() {
+            None
+        } else if self.batch {
+            serde_json::to_value(responses).ok()
+        } else {
+            responses.into_iter().next().and_then(|r| serde_json::to_value(r).ok())
+        }
+    }
+}
+
+/// JSON-RPC 2.0 Notification (no id, never answered)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcNotification {
+    pub jsonrpc: String,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+impl JsonRpcNotification {
+    pub fn new(method: &str, params: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            method: method.to_string(),
+            params: Some(params),
+        }
+    }
+}
+
+/// Message sent on the server's own initiative
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum ServerMessage {
+    Notification(JsonRpcNotification),
+    /// Expects a response from the client (e.g. `roots/list`)
+    Request(JsonRpcRequest),
+}
+
+impl ServerMessage {
+    pub fn method(&self) -> &str {
+        match self {
+            ServerMessage::Notification(n) => &n.method,
+            ServerMessage::Request(r) => &r.method,
+        }
+    }
+
+    pub fn params(&self) -> Option<&Value> {
+        match self {
+            ServerMessage::Notification(n) => n.params.as_ref(),
+            ServerMessage::Request(r) => r.params.as_ref(),
+        }
+    }
+}
+
+impl From<JsonRpcNotification> for ServerMessage {
+    fn from(notification: JsonRpcNotification) -> Self {
+        ServerMessage::Notification(notification)
+    }
 }
 
 /// MCP Initialize Request
@@ -111,7 +315,14 @@ pub struct InitializeResult {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerCapabilities {
+    /// Present (as `{}`) when the server sends `notifications/message`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<Value>,
     pub tools: Option<ToolsCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourcesCapability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<PromptsCapability>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -120,6 +331,39 @@ pub struct ToolsCapability {
     pub list_changed: bool,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourcesCapability {
+    #[serde(default)]
+    pub subscribe: bool,
+    #[serde(rename = "listChanged", default)]
+    pub list_changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptsCapability {
+    #[serde(rename = "listChanged", default)]
+    pub list_changed: bool,
+}
+
+/// Syslog severities used by `logging/setLevel` and `notifications/message`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetLevelParams {
+    pub level: LogLevel,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerInfo {
     pub name: String,
@@ -133,6 +377,9 @@ pub struct ToolDefinition {
     pub description: String,
     #[serde(rename = "inputSchema")]
     pub input_schema: Value,
+    /// Shape of `structuredContent` in the result; None for tools that return plain text
+    #[serde(rename = "outputSchema", skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
 }
 
 /// Tool Call Request
@@ -141,12 +388,25 @@ pub struct CallToolParams {
     pub name: String,
     #[serde(default)]
     pub arguments: Option<Value>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<RequestMeta>,
+}
+
+/// `_meta` of a request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RequestMeta {
+    /// Set by clients that want `notifications/progress` for this request
+    #[serde(rename = "progressToken", default, skip_serializing_if = "Option::is_none")]
+    pub progress_token: Option<Value>,
 }
 
 /// Tool Call Response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallToolResult {
     pub content: Vec<ToolContent>,
+    /// The same result as JSON, conforming to the tool's `outputSchema`
+    #[serde(rename = "structuredContent", default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
     #[serde(rename = "isError", default)]
     pub is_error: bool,
 }
@@ -157,3 +417,78 @@ pub enum ToolContent {
     #[serde(rename = "text")]
     Text { text: String },
 }
+
+/// Resource Definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Resource Template Definition (RFC 6570 URI template)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceTemplate {
+    #[serde(rename = "uriTemplate")]
+    pub uri_template: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// resources/read, resources/subscribe and resources/unsubscribe params
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceUriParams {
+    pub uri: String,
+}
+
+/// Text contents of a resource
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    pub text: String,
+}
+
+/// Prompt Definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptDefinition {
+    pub name: String,
+    pub description: String,
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    pub description: String,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// prompts/get params (argument values are strings)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPromptParams {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Option<std::collections::HashMap<String, String>>,
+}
+
+/// prompts/get result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GetPromptResult {
+    pub description: String,
+    pub messages: Vec<PromptMessage>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptMessage {
+    pub role: String,
+    pub content: ToolContent,
+}
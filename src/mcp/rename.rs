@@ -0,0 +1,230 @@
+// Rename refactoring
+// 解決済みの参照エッジ（定義・呼び出し・import）の位置だけを書き換える。テキスト置換はしない
+
+use anyhow::{anyhow, bail, Result};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::graph::CodeGraph;
+use crate::mcp::changes::FileChange;
+use crate::mcp::context::{is_ident_char, word_columns, ContextGenerator};
+use crate::scanner::ScanCache;
+
+/// Everything a rename will touch, ready for `ChangeManager::preview_changes` / `apply_changes`
+#[derive(Debug, Clone)]
+pub struct RenamePlan {
+    pub symbol_id: String,
+    pub old_name: String,
+    pub new_name: String,
+    /// Positions rewritten: the definition, then every resolved use
+    pub edits: Vec<RenameEdit>,
+    /// Whole-word mentions of the old name that no edge points at (comments, strings,
+    /// unresolved dynamic calls, other symbols of the same name); left for review
+    pub unresolved: Vec<RenameEdit>,
+    /// Definitions already named `new_name`: the rename would clash, so it is not applied unless forced
+    pub warnings: Vec<String>,
+    /// One full-content change per file, paths relative to the project directory
+    pub changes: Vec<FileChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RenameEdit {
+    /// "definition", "call", "reference", "instantiation", "import" or "mention"
+    pub kind: String,
+    /// Relative to the project directory
+    pub file_path: String,
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    pub source_line: String,
+}
+
+/// Plan renaming `symbol` (name, qualified name or symbol id) to `new_name` in the project at `directory`.
+/// The graph should be fresh: a position that no longer holds the old name aborts the plan.
+pub fn plan_rename(
+    graph: impl Into<Arc<CodeGraph>>,
+    directory: &Path,
+    symbol: &str,
+    new_name: &str,
+) -> Result<RenamePlan> {
+    if !is_identifier(new_name) {
+        bail!("Not a valid identifier: {}", new_name);
+    }
+    let graph: Arc<CodeGraph> = graph.into();
+
+    let files: BTreeSet<PathBuf> = graph.nodes.values().map(|n| n.file_path.clone()).collect();
+    let existing: Vec<String> = graph
+        .nodes_by_name(new_name)
+        .map(|n| format!("{} is already defined at {}:{}", new_name, n.file_path.display(), n.line_range.0))
+        .collect();
+    let generator = ContextGenerator::from_graph(graph);
+
+    let definitions = generator.definitions_of(symbol);
+    let definition = match definitions.as_slice() {
+        [] => bail!("Symbol not found: {}", symbol),
+        [only] => *only,
+        many => bail!(
+            "{} matches {} definitions; pass one of: {}",
+            symbol,
+            many.len(),
+            many.iter().map(|n| n.symbol_id.as_str()).collect::<Vec<_>>().join(", ")
+        ),
+    };
+    let symbol_id = definition.symbol_id.clone();
+    let old_name = definition.name.clone();
+    if old_name == new_name {
+        bail!("{} is already named {}", symbol, new_name);
+    }
+
+    // Rewrite positions per file, right to left on each line so earlier columns stay valid
+    let mut by_file: BTreeMap<PathBuf, Vec<(usize, usize)>> = BTreeMap::new();
+    let mut edits = Vec::new();
+    for location in generator.find_references(&symbol_id, true) {
+        let path = PathBuf::from(&location.file_path);
+        let positions = by_file.entry(path.clone()).or_default();
+        if positions.contains(&(location.line, location.column)) {
+            continue;
+        }
+        positions.push((location.line, location.column));
+        edits.push(RenameEdit {
+            kind: location.kind,
+            file_path: relative_path(directory, &path),
+            line: location.line,
+            column: location.column,
+            source_line: location.source_line,
+        });
+    }
+
+    let mut changes = Vec::new();
+    for (path, positions) in &by_file {
+        let original = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let modified = rewrite(&original, positions, &old_name, new_name).map_err(|(line, column)| {
+            anyhow!(
+                "{}:{}:{} does not read {}; the index is out of date, rescan and retry",
+                path.display(),
+                line,
+                column,
+                old_name
+            )
+        })?;
+        changes.push(FileChange {
+            file_path: relative_path(directory, path),
+            // Refuse to apply if the file changes between planning and applying
+            base_hash: Some(ScanCache::hash_content(original.as_bytes())),
+            original_content: Some(original),
+            modified_content: modified,
+            start_line: None,
+            end_line: None,
+        });
+    }
+
+    let rewritten: HashSet<(PathBuf, usize, usize)> = by_file
+        .iter()
+        .flat_map(|(path, positions)| positions.iter().map(move |&(l, c)| (path.clone(), l, c)))
+        .collect();
+    let mut unresolved = Vec::new();
+    for path in &files {
+        let Ok(content) = std::fs::read_to_string(path) else { continue };
+        for (index, text) in content.lines().enumerate() {
+            for column in word_columns(text, &old_name) {
+                if !rewritten.contains(&(path.clone(), index + 1, column)) {
+                    unresolved.push(RenameEdit {
+                        kind: "mention".to_string(),
+                        file_path: relative_path(directory, path),
+                        line: index + 1,
+                        column,
+                        source_line: text.to_string(),
+                    });
+                }
+            }
+        }
+    }
+
+    Ok(RenamePlan {
+        symbol_id,
+        old_name,
+        new_name: new_name.to_string(),
+        edits,
+        unresolved,
+        warnings: existing,
+        changes,
+    })
+}
+
+/// Replace `old` at each 1-based (line, column); Err with the first position that does not hold it
+fn rewrite(content: &str, positions: &[(usize, usize)], old: &str, new: &str) -> Result<String, (usize, usize)> {
+    let mut lines: Vec<String> = content.split('\n').map(str::to_string).collect();
+    let mut sorted = positions.to_vec();
+    sorted.sort_by(|a, b| b.cmp(a));
+    for (line, column) in sorted {
+        let text = line.checked_sub(1).and_then(|i| lines.get_mut(i)).ok_or((line, column))?;
+        let start = text
+            .char_indices()
+            .nth(column.saturating_sub(1))
+            .map(|(i, _)| i)
+            .ok_or((line, column))?;
+        if !text[start..].starts_with(old) || text[start + old.len()..].chars().next().is_some_and(is_ident_char) {
+            return Err((line, column));
+        }
+        text.replace_range(start..start + old.len(), new);
+    }
+    Ok(lines.join("\n"))
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$') && chars.all(is_ident_char)
+}
+
+fn relative_path(directory: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(directory).map(Path::to_path_buf).or_else(|_| {
+        let directory = std::fs::canonicalize(directory)?;
+        let path = std::fs::canonicalize(path)?;
+        path.strip_prefix(&directory)
+            .map(Path::to_path_buf)
+            .map_err(|e| std::io::Error::other(e.to_string()))
+    });
+    relative.unwrap_or_else(|_| path.to_path_buf()).display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_project;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn test_rename_rewrites_definition_calls_and_imports() {
+        let temp = temp_project();
+        let dir = temp.path();
+        std::fs::write(
+            dir.join("util.ts"),
+            "export function greet(name: string) {\n  return name;\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("main.ts"),
+            "import { greet } from './util';\n// greet is called twice\nexport function main() {\n  greet('a'); greet('b');\n}\n",
+        )
+        .unwrap();
+
+        let graph = Scanner::new().unwrap().scan_directory(dir).unwrap();
+        assert!(plan_rename(graph.clone(), dir, "greet", "1nope").is_err());
+
+        let plan = plan_rename(graph, dir, "greet", "welcome").unwrap();
+        assert_eq!(plan.old_name, "greet");
+        let main = plan.changes.iter().find(|c| c.file_path == "main.ts").unwrap();
+        assert_eq!(
+            main.modified_content,
+            "import { welcome } from './util';\n// greet is called twice\nexport function main() {\n  welcome('a'); welcome('b');\n}\n"
+        );
+        let util = plan.changes.iter().find(|c| c.file_path == "util.ts").unwrap();
+        assert!(util.modified_content.starts_with("export function welcome(name"));
+        // The comment is reported, not rewritten
+        assert_eq!(plan.unresolved.len(), 1);
+        assert_eq!(plan.unresolved[0].line, 2);
+    }
+}
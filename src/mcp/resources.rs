@@ -0,0 +1,313 @@
+// MCP Resources
+// プロジェクトのファイル・シンボル・デッドコードレポート・依存グラフを resources として公開する
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Component, Path, PathBuf};
+
+use crate::detector::detect_dead_code;
+use crate::graph::CodeGraph;
+use crate::mcp::protocol::{Resource, ResourceContents, ResourceTemplate};
+use crate::parser::Language;
+use crate::reporter::generate_json_report;
+
+pub const GRAPH_URI: &str = "index-chan://graph";
+pub const DEAD_CODE_URI: &str = "index-chan://report/dead-code";
+const FILE_PREFIX: &str = "index-chan://file/";
+const SYMBOL_PREFIX: &str = "index-chan://symbol/";
+
+/// A parsed `index-chan://` URI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceUri {
+    /// Project file, relative to the project directory
+    File(PathBuf),
+    /// Source of one definition, by symbol id
+    Symbol(String),
+    DeadCodeReport,
+    Graph,
+}
+
+impl ResourceUri {
+    pub fn parse(uri: &str) -> Option<Self> {
+        match uri {
+            GRAPH_URI => return Some(Self::Graph),
+            DEAD_CODE_URI => return Some(Self::DeadCodeReport),
+            _ => {}
+        }
+        if let Some(path) = uri.strip_prefix(FILE_PREFIX) {
+            let path = PathBuf::from(percent_decode(path)?);
+            // Only plain relative paths: nothing may escape the project directory
+            let plain = path.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+            return (plain && !path.as_os_str().is_empty()).then_some(Self::File(path));
+        }
+        let id = percent_decode(uri.strip_prefix(SYMBOL_PREFIX)?)?;
+        (!id.is_empty()).then_some(Self::Symbol(id))
+    }
+
+    pub fn to_uri(&self) -> String {
+        match self {
+            Self::File(path) => format!("{}{}", FILE_PREFIX, percent_encode(&path.to_string_lossy().replace('\\', "/"))),
+            Self::Symbol(id) => format!("{}{}", SYMBOL_PREFIX, percent_encode(id)),
+            Self::DeadCodeReport => DEAD_CODE_URI.to_string(),
+            Self::Graph => GRAPH_URI.to_string(),
+        }
+    }
+}
+
+/// Templates for the resources too numerous to list (one per file / symbol)
+pub fn resource_templates() -> Vec<ResourceTemplate> {
+    vec![
+        ResourceTemplate {
+            uri_template: format!("{}{{+path}}", FILE_PREFIX),
+            name: "Project file".to_string(),
+            description: Some("Source file, path relative to the project directory".to_string()),
+            mime_type: None,
+        },
+        ResourceTemplate {
+            uri_template: format!("{}{{symbol_id}}", SYMBOL_PREFIX),
+            name: "Symbol source".to_string(),
+            description: Some(
+                "Source of one function/class/method by symbol id (e.g. `ts:src/app.ts#main`, `#` encoded as %23)"
+                    .to_string(),
+            ),
+            mime_type: None,
+        },
+    ]
+}
+
+/// The graph, the dead-code report and every scanned source file
+pub fn list_resources(dir: &Path, graph: &CodeGraph) -> Vec<Resource> {
+    let mut resources = vec![
+        Resource {
+            uri: GRAPH_URI.to_string(),
+            name: "Dependency graph".to_string(),
+            description: Some("Nodes and edges of the project dependency graph".to_string()),
+            mime_type: Some("application/json".to_string()),
+        },
+        Resource {
+            uri: DEAD_CODE_URI.to_string(),
+            name: "Dead code report".to_string(),
+            description: Some("Unused functions and classes from the latest scan".to_string()),
+            mime_type: Some("application/json".to_string()),
+        },
+    ];
+
+    let files: BTreeSet<PathBuf> = graph
+        .nodes
+        .values()
+        .map(|node| relative_to(dir, &node.file_path))
+        .collect();
+    resources.extend(files.into_iter().map(|path| Resource {
+        uri: ResourceUri::File(path.clone()).to_uri(),
+        name: path.to_string_lossy().replace('\\', "/"),
+        description: None,
+        mime_type: Some(mime_type(&path).to_string()),
+    }));
+    resources
+}
+
+/// Read one resource; errors mean the resource does not exist
+pub fn read_resource(dir: &Path, graph: &CodeGraph, uri: &str) -> Result<ResourceContents, String> {
+    let parsed = ResourceUri::parse(uri).ok_or_else(|| format!("Unknown resource: {}", uri))?;
+    let (mime, text) = match &parsed {
+        ResourceUri::File(path) => {
+            // Only the scanned sources that list_resources advertises, never `.env` or `.git/config`
+            let path: PathBuf = path.components().filter(|c| *c != Component::CurDir).collect();
+            if !graph.nodes.values().any(|node| relative_to(dir, &node.file_path) == path) {
+                return Err(format!("Unknown resource: {}", uri));
+            }
+            let text = std::fs::read_to_string(dir.join(&path))
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            (mime_type(&path), text)
+        }
+        ResourceUri::Symbol(id) => {
+            let node = graph
+                .find_by_symbol_id(id)
+                .ok_or_else(|| format!("Symbol not found: {}", id))?;
+            let source = std::fs::read_to_string(&node.file_path)
+                .map_err(|e| format!("Failed to read {}: {}", node.file_path.display(), e))?;
+            let (start, end) = node.line_range;
+            let lines: Vec<&str> = source
+                .lines()
+                .skip(start.saturating_sub(1))
+                .take(end + 1 - start.max(1))
+                .collect();
+            (mime_type(&node.file_path), lines.join("\n"))
+        }
+        ResourceUri::DeadCodeReport => {
+            let dead_code = detect_dead_code(graph);
+            let files: HashSet<&PathBuf> = graph.nodes.values().map(|n| &n.file_path).collect();
+            let report = generate_json_report(&dead_code, files.len(), graph.nodes.len());
+            ("application/json", serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?)
+        }
+        ResourceUri::Graph => ("application/json", serde_json::to_string(graph).map_err(|e| e.to_string())?),
+    };
+
+    Ok(ResourceContents {
+        uri: uri.to_string(),
+        mime_type: Some(mime.to_string()),
+        text,
+    })
+}
+
+/// Subscribed URIs whose content may have changed with `changed` files.
+/// Paths may be absolute (file events) or under `dir`.
+pub fn affected_uris<'a>(dir: &Path, subscriptions: &'a HashSet<String>, changed: &[PathBuf]) -> Vec<&'a String> {
+    let changed = relative_paths(dir, changed);
+    let source_changed = changed.iter().any(|path| is_source_file(path));
+
+    let mut affected: Vec<&String> = subscriptions
+        .iter()
+        .filter(|uri| match ResourceUri::parse(uri) {
+            Some(ResourceUri::File(path)) => changed.contains(&path),
+            Some(ResourceUri::Symbol(id)) => {
+                symbol_file(&id).is_some_and(|file| changed.iter().any(|c| *c == Path::new(file)))
+            }
+            Some(ResourceUri::DeadCodeReport | ResourceUri::Graph) => source_changed,
+            None => false,
+        })
+        .collect();
+    affected.sort();
+    affected
+}
+
+/// Whether any of the changed paths can affect the graph
+pub fn sources_changed(dir: &Path, changed: &[PathBuf]) -> bool {
+    relative_paths(dir, changed).iter().any(|path| is_source_file(path))
+}
+
+/// Paths under `dir`, relative to it; paths elsewhere are dropped
+fn relative_paths(dir: &Path, paths: &[PathBuf]) -> Vec<PathBuf> {
+    let canonical = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+    paths
+        .iter()
+        .filter_map(|path| {
+            path.strip_prefix(&canonical)
+                .or_else(|_| path.strip_prefix(dir))
+                .ok()
+                .map(Path::to_path_buf)
+        })
+        .collect()
+}
+
+/// Supported source file outside tool/vendor directories (`path` relative to the project)
+fn is_source_file(path: &Path) -> bool {
+    let ignored = path.components().any(|c| {
+        matches!(c.as_os_str().to_str(), Some(".index-chan" | ".git" | "node_modules" | "target"))
+    });
+    !ignored
+        && path
+            .extension()
+            .and_then(|e| e.to_str())
+            .and_then(Language::from_extension)
+            .is_some()
+}
+
+/// File part of a symbol id (`ts:src/app.ts#main` -> `src/app.ts`)
+fn symbol_file(id: &str) -> Option<&str> {
+    let (_, rest) = id.split_once(':')?;
+    rest.split_once('#').map(|(file, _)| file)
+}
+
+fn relative_to(dir: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(dir).unwrap_or(path).to_path_buf()
+}
+
+fn mime_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("ts" | "tsx" | "mts" | "cts") => "text/x-typescript",
+        Some("js" | "jsx" | "mjs" | "cjs") => "text/javascript",
+        Some("rs") => "text/x-rust",
+        Some("vue") => "text/x-vue",
+        Some("svelte") => "text/x-svelte",
+        Some("json") => "application/json",
+        _ => "text/plain",
+    }
+}
+
+/// Escape everything but unreserved characters and sub-delims, keeping `/` and `:` readable
+pub(crate) fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || b"-._~/:@!$&'()*+,;=".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+pub(crate) fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_project;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn test_uri_round_trip() {
+        let symbol = ResourceUri::Symbol("ts:src/app.ts#Shape.area~2".to_string());
+        assert_eq!(symbol.to_uri(), "index-chan://symbol/ts:src/app.ts%23Shape.area~2");
+        assert_eq!(ResourceUri::parse(&symbol.to_uri()), Some(symbol));
+
+        let file = ResourceUri::File(PathBuf::from("src/my file.ts"));
+        assert_eq!(file.to_uri(), "index-chan://file/src/my%20file.ts");
+        assert_eq!(ResourceUri::parse(&file.to_uri()), Some(file));
+
+        assert_eq!(ResourceUri::parse("index-chan://file/../secret"), None);
+        assert_eq!(ResourceUri::parse("index-chan://file/%2Fetc%2Fpasswd"), None);
+        assert_eq!(ResourceUri::parse("index-chan://symbol/"), None);
+    }
+
+    #[test]
+    fn test_read_and_affected_resources() {
+        let temp = temp_project();
+        let root = temp.path();
+        std::fs::write(root.join("app.ts"), "function main() {\n  helper();\n}\n\nfunction helper() {\n  return 1;\n}\n").unwrap();
+        std::fs::write(root.join("util.ts"), "export function unused() {}\n").unwrap();
+        std::fs::write(root.join(".env"), "TOKEN=secret\n").unwrap();
+
+        let graph = Scanner::with_incremental(false).unwrap().scan_directory(root).unwrap();
+        let uris: Vec<String> = list_resources(root, &graph).into_iter().map(|r| r.uri).collect();
+        assert_eq!(
+            uris,
+            vec![GRAPH_URI, DEAD_CODE_URI, "index-chan://file/app.ts", "index-chan://file/util.ts"]
+        );
+
+        let helper = ResourceUri::Symbol("ts:app.ts#helper".to_string()).to_uri();
+        let contents = read_resource(root, &graph, &helper).unwrap();
+        assert_eq!(contents.text, "function helper() {\n  return 1;\n}");
+        assert_eq!(contents.mime_type.as_deref(), Some("text/x-typescript"));
+        assert!(read_resource(root, &graph, "index-chan://symbol/ts:app.ts%23missing").is_err());
+
+        // Files are served only if the scan saw them
+        assert_eq!(read_resource(root, &graph, "index-chan://file/./util.ts").unwrap().text, "export function unused() {}\n");
+        assert!(read_resource(root, &graph, "index-chan://file/.env").is_err());
+
+        let report: serde_json::Value =
+            serde_json::from_str(&read_resource(root, &graph, DEAD_CODE_URI).unwrap().text).unwrap();
+        assert_eq!(report["summary"]["total_functions"], 3);
+
+        let subscriptions: HashSet<String> =
+            [helper.clone(), "index-chan://file/util.ts".to_string(), GRAPH_URI.to_string()].into();
+        let changed = [root.canonicalize().unwrap().join("app.ts")];
+        assert_eq!(affected_uris(root, &subscriptions, &changed), vec![GRAPH_URI, helper.as_str()]);
+        assert!(affected_uris(root, &subscriptions, &[root.join("README.md")]).is_empty());
+    }
+}
@@ -0,0 +1,127 @@
+// Workspace roots (MCP roots/list)
+// クライアントが開いているフォルダごとにグラフを持ち、ツールは root 名か URI で対象を選ぶ
+
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use crate::mcp::resources::{percent_decode, percent_encode};
+
+/// `root` value that makes the search tools run in every root
+pub const ALL_ROOTS: &str = "*";
+
+/// One directory the client works in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceRoot {
+    /// Client-given name, or the directory name
+    pub name: String,
+    pub uri: String,
+    pub path: PathBuf,
+}
+
+impl WorkspaceRoot {
+    pub fn from_path(path: &Path) -> Self {
+        Self {
+            name: dir_name(path),
+            uri: path_to_file_uri(path),
+            path: path.to_path_buf(),
+        }
+    }
+
+    /// Whether `selector` names this root: its name, URI or path
+    pub fn matches(&self, selector: &str) -> bool {
+        self.name == selector || self.uri == selector || self.path == Path::new(selector)
+    }
+}
+
+/// Roots from a `roots/list` result; URIs other than `file://` are skipped
+pub fn from_list_result(result: &Value) -> Vec<WorkspaceRoot> {
+    let entries = result.get("roots").and_then(Value::as_array).into_iter().flatten();
+    entries
+        .filter_map(|entry| {
+            let uri = entry.get("uri").and_then(Value::as_str)?;
+            let path = file_uri_to_path(uri)?;
+            let name = entry
+                .get("name")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| dir_name(&path));
+            Some(WorkspaceRoot { name, uri: uri.to_string(), path })
+        })
+        .collect()
+}
+
+/// The root `selector` names, or an error listing the known ones
+pub fn find<'a>(roots: &'a [WorkspaceRoot], selector: &str) -> Result<&'a WorkspaceRoot, String> {
+    roots.iter().find(|r| r.matches(selector)).ok_or_else(|| {
+        if roots.is_empty() {
+            format!("Unknown root: {} (the client has not shared any roots)", selector)
+        } else {
+            format!("Unknown root: {} (roots: {})", selector, names(roots))
+        }
+    })
+}
+
+pub fn names(roots: &[WorkspaceRoot]) -> String {
+    roots.iter().map(|r| r.name.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+/// `file:///home/a/b%20c` → `/home/a/b c` (`file:///C:/x` → `C:/x` on Windows)
+pub fn file_uri_to_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // Only local files: skip an optional `localhost` authority
+    let rest = rest.strip_prefix("localhost").unwrap_or(rest);
+    if !rest.starts_with('/') {
+        return None;
+    }
+    let decoded = percent_decode(rest)?;
+    let bytes = decoded.as_bytes();
+    if cfg!(windows) && bytes.len() > 2 && bytes[1].is_ascii_alphabetic() && bytes[2] == b':' {
+        return Some(PathBuf::from(&decoded[1..]));
+    }
+    Some(PathBuf::from(decoded))
+}
+
+pub fn path_to_file_uri(path: &Path) -> String {
+    let absolute = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    let text = absolute.display().to_string().replace('\\', "/");
+    let text = text.strip_prefix("//?/").unwrap_or(&text);
+    if text.starts_with('/') {
+        format!("file://{}", percent_encode(text))
+    } else {
+        format!("file:///{}", percent_encode(text))
+    }
+}
+
+fn dir_name(path: &Path) -> String {
+    let absolute = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    absolute
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_roots_from_list_result() {
+        let result = json!({ "roots": [
+            { "uri": "file:///work/api", "name": "backend" },
+            { "uri": "file://localhost/work/web%20app" },
+            { "uri": "https://example.com/repo", "name": "remote" }
+        ]});
+        let roots = from_list_result(&result);
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0].name, "backend");
+        assert_eq!(roots[0].path, PathBuf::from("/work/api"));
+        assert_eq!(roots[1].name, "web app");
+        assert_eq!(roots[1].path, PathBuf::from("/work/web app"));
+
+        assert_eq!(find(&roots, "backend").unwrap().path, PathBuf::from("/work/api"));
+        assert_eq!(find(&roots, "file:///work/api").unwrap().name, "backend");
+        assert_eq!(find(&roots, "/work/web app").unwrap().name, "web app");
+        assert_eq!(find(&roots, "nope").unwrap_err(), "Unknown root: nope (roots: backend, web app)");
+    }
+}
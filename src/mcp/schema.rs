@@ -0,0 +1,144 @@
+// JSON Schema validation (the subset used by tools.rs)
+// ツール引数・出力をスキーマで検証し、どの値がなぜ違うのかを返す
+//
+// Supported keywords: type, enum, properties, required, additionalProperties, items, minimum
+
+use serde_json::Value;
+
+/// Everything wrong with `value` under `schema`; empty when it conforms.
+/// Each message starts with the path of the offending value, e.g. `changes[0].file_path`.
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    check(schema, value, "", &mut errors);
+    errors
+}
+
+fn check(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let at = if path.is_empty() { "arguments" } else { path };
+
+    if let Some(expected) = schema.get("type") {
+        let types: Vec<&str> = match expected {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !types.is_empty() && !types.iter().any(|t| has_type(value, t)) {
+            errors.push(format!("{}: expected {}, got {}", at, types.join(" or "), type_name(value)));
+            // Nothing below makes sense for a value of the wrong type
+            return;
+        }
+    }
+
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            let choices: Vec<String> = allowed.iter().map(Value::to_string).collect();
+            errors.push(format!("{}: must be one of {} (got {})", at, choices.join(", "), value));
+        }
+    }
+
+    if let (Some(minimum), Some(n)) = (schema.get("minimum").and_then(Value::as_f64), value.as_f64()) {
+        if n < minimum {
+            errors.push(format!("{}: must be at least {} (got {})", at, minimum, value));
+        }
+    }
+
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for name in schema.get("required").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(name) = name.as_str() {
+                if !object.contains_key(name) {
+                    errors.push(format!("{}: missing required property `{}`", at, name));
+                }
+            }
+        }
+        for (name, item) in object {
+            let item_path = if path.is_empty() { name.clone() } else { format!("{}.{}", path, name) };
+            match (properties.and_then(|p| p.get(name)), schema.get("additionalProperties")) {
+                (Some(property), _) => check(property, item, &item_path, errors),
+                (None, Some(Value::Bool(false))) => {
+                    errors.push(format!("{}: unknown property `{}`", at, name));
+                }
+                (None, Some(additional @ Value::Object(_))) => check(additional, item, &item_path, errors),
+                (None, _) => {}
+            }
+        }
+    }
+
+    if let (Value::Array(items), Some(item_schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            check(item_schema, item, &format!("{}[{}]", at, i), errors);
+        }
+    }
+}
+
+fn has_type(value: &Value, expected: &str) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mcp::tools::get_tool_definitions;
+    use serde_json::json;
+
+    fn input_schema(tool: &str) -> Value {
+        get_tool_definitions().into_iter().find(|t| t.name == tool).unwrap().input_schema
+    }
+
+    #[test]
+    fn test_errors_name_the_offending_value() {
+        let schema = input_schema("apply_changes");
+        assert!(validate(&schema, &json!({ "directory": ".", "changes": [] })).is_empty());
+
+        let errors = validate(
+            &schema,
+            &json!({
+                "changes": [{ "file_path": 3, "modified_content": "x" }, { "file_path": "a.ts" }],
+                "create_backup": "yes"
+            }),
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "changes[0].file_path: expected string, got integer",
+                "changes[1]: missing required property `modified_content`",
+                "create_backup: expected boolean, got string",
+            ]
+        );
+
+        let errors = validate(
+            &input_schema("gather_context"),
+            &json!({ "directory": ".", "mode": "fast", "depth": -1 }),
+        );
+        assert_eq!(
+            errors,
+            vec![
+                "depth: must be at least 0 (got -1)",
+                "mode: must be one of \"full\", \"skeleton\" (got \"fast\")",
+            ]
+        );
+
+        assert_eq!(validate(&schema, &json!([])), vec!["arguments: expected object, got array"]);
+    }
+}
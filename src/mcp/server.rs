@@ -1,129 +1,582 @@
 // MCP Server Implementation
 // stdio-based JSON-RPC server
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
 use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
 use std::io::{BufRead, BufReader, Write};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
 
+use crate::daemon::DaemonClient;
 use crate::detector::detect_dead_code;
 use crate::graph::CodeGraph;
 use crate::mcp::changes::{ChangeManager, FileChange};
-use crate::mcp::context::{ContextFormat, ContextGenerator, ContextMode, parse_llm_edits};
+use crate::mcp::context::{ContextFormat, ContextGenerator, ContextMode, ContextResult, parse_llm_edits};
 use crate::mcp::protocol::*;
+use crate::mcp::prompts::{get_prompt, get_prompt_definitions};
+use crate::mcp::paging::{paginate, PageRequest};
+use crate::mcp::rename;
+use crate::mcp::resources;
+use crate::mcp::roots::{self, WorkspaceRoot};
+use crate::mcp::schema;
+use crate::mcp::tokens::count_tokens;
 use crate::mcp::tools::get_tool_definitions;
+use crate::progress::Progress;
 use crate::scanner::Scanner;
+use crate::search::graph_search::GraphSearchResult;
 use std::path::Path;
 
-/// MCP Server with graph caching
+/// Delivers server-initiated messages: stdout for stdio, session streams for HTTP
+pub type Notifier = Arc<dyn Fn(&ServerMessage) + Send + Sync>;
+
+/// Requests being handled, so `notifications/cancelled` can reach them from another thread
+#[derive(Clone, Default)]
+pub struct InFlight(Arc<Mutex<HashMap<String, Progress>>>);
+
+impl InFlight {
+    fn start(&self, id: &Value, progress: Progress) {
+        self.0.lock().unwrap().insert(id.to_string(), progress);
+    }
+
+    fn finish(&self, id: &Value) {
+        self.0.lock().unwrap().remove(&id.to_string());
+    }
+
+    /// Apply and drop the `notifications/cancelled` messages of a payload
+    pub fn intercept(&self, payload: &mut Payload) {
+        payload.messages.retain(|message| match message {
+            Incoming::Request(r) if r.id.is_none() && r.method == "notifications/cancelled" => {
+                self.cancel(r.params.as_ref());
+                false
+            }
+            _ => true,
+        });
+    }
+
+    /// Cancel the request named by `notifications/cancelled` params; false if it is not running
+    pub fn cancel(&self, params: Option<&Value>) -> bool {
+        let Some(id) = params.and_then(|p| p.get("requestId")) else {
+            return false;
+        };
+        match self.0.lock().unwrap().get(&id.to_string()) {
+            Some(progress) => {
+                eprintln!("🛑 Cancelling request {}", id);
+                progress.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A graph kept between tool calls
+struct CachedGraph {
+    graph: Arc<CodeGraph>,
+    /// Daemon generation the graph came from (None: scanned locally)
+    daemon_generation: Option<u64>,
+}
+
+/// Cached graph of one directory, locked while it is loaded
+type GraphSlot = Arc<Mutex<Option<CachedGraph>>>;
+
+/// Graphs shared by every session of a server, one slot per directory.
+/// A session scanning one directory only holds that slot, so others keep being served.
+#[derive(Clone, Default)]
+struct GraphCache {
+    slots: Arc<Mutex<HashMap<PathBuf, GraphSlot>>>,
+    /// Directories whose cached graph a subscription watcher saw change
+    stale_dirs: Arc<Mutex<HashSet<PathBuf>>>,
+    /// How many sessions have each root open; a root's graph is dropped once none has
+    open_roots: Arc<Mutex<HashMap<PathBuf, usize>>>,
+}
+
+impl GraphCache {
+    /// The slot of `dir`, after forgetting graphs whose sources changed
+    fn slot(&self, dir: &Path) -> GraphSlot {
+        let mut slots = self.slots.lock().unwrap();
+        for stale in self.stale_dirs.lock().unwrap().drain() {
+            slots.remove(&stale);
+        }
+        Arc::clone(slots.entry(dir.to_path_buf()).or_default())
+    }
+
+    fn invalidate(&self, dir: &Path) {
+        self.slots.lock().unwrap().remove(dir);
+    }
+
+    #[cfg(feature = "db")]
+    fn mark_stale(&self, dir: &Path) {
+        self.stale_dirs.lock().unwrap().insert(dir.to_path_buf());
+    }
+
+    fn open_roots(&self, roots: &[WorkspaceRoot]) {
+        let mut open = self.open_roots.lock().unwrap();
+        for root in roots {
+            *open.entry(root.path.clone()).or_default() += 1;
+        }
+    }
+
+    /// Graphs of roots that no session has open anymore are not needed
+    fn close_roots(&self, roots: &[WorkspaceRoot]) {
+        let mut open = self.open_roots.lock().unwrap();
+        for root in roots {
+            if let Some(count) = open.get_mut(&root.path) {
+                *count -= 1;
+                if *count == 0 {
+                    open.remove(&root.path);
+                    self.invalidate(&root.path);
+                }
+            }
+        }
+    }
+}
+
+/// MCP Server with graph caching.
+/// Holds the state of one client session; `new_session` starts another one on the same graph cache.
 pub struct McpServer {
     project_dir: Option<PathBuf>,
     initialized: bool,
-    /// Cached graph to avoid re-scanning on each tool call
-    graph_cache: Option<CodeGraph>,
+    /// Protocol revision agreed on in `initialize`
+    protocol_version: Option<&'static str>,
+    /// Cached graphs, one per directory, to avoid re-scanning on each tool call
+    graphs: GraphCache,
+    /// Workspace roots: the client's `roots/list`, or the project directory
+    roots: Vec<WorkspaceRoot>,
+    /// The client declared the `roots` capability
+    client_roots: bool,
+    /// Id of the `roots/list` request still waiting for its response
+    pending_roots: Option<Value>,
+    next_request_id: u64,
+    /// Resource URIs the client subscribed to
+    subscriptions: Arc<Mutex<HashSet<String>>>,
+    /// Directory the subscription watcher runs for, and its handle (dropping it ends the watch)
+    #[cfg(feature = "db")]
+    watcher: Option<(PathBuf, crate::watcher::EventDebouncer)>,
+    notifier: Notifier,
+    in_flight: InFlight,
+    /// Progress of the tool call being handled (scans and embedding builds report to it)
+    progress: Progress,
+    /// Minimum level sent as `notifications/message` (None until `logging/setLevel`)
+    log_level: Option<LogLevel>,
+}
+
+/// Quality metrics (Concept Transformer Phase 9b) and budget notes put above standard-format context
+fn quality_header(result: &ContextResult, max_tokens: Option<usize>) -> String {
+    let mut content_with_quality = format!(
+        "// ===== QUALITY METRICS =====\n\
+         // Estimated tokens: {}\n\
+         // S/N ratio: {:.2}\n\
+         // Concept density: {:.2}\n\
+         // Dependencies: {}\n\
+         // Entry point ratio: {:.1}%\n\
+         // Quality: {}\n",
+        result.quality.estimated_tokens,
+        result.quality.sn_ratio,
+        result.quality.concept_density,
+        result.quality.dependency_count,
+        result.quality.entry_point_ratio * 100.0,
+        result.quality.quality_level
+    );
+    if result.quality.context_explosion_warning {
+        content_with_quality.push_str("// ⚠️ WARNING: Context explosion detected!\n");
+    }
+    if let Some(ref rec) = result.quality.recommendation {
+        content_with_quality.push_str(&format!("// Recommendation: {}\n", rec));
+    }
+    if let Some(budget) = max_tokens {
+        content_with_quality.push_str(&format!("// Token budget: {}\n", budget));
+        if !result.skeletonized.is_empty() {
+            content_with_quality.push_str(&format!("// Signatures only: {}\n", result.skeletonized.join(", ")));
+        }
+        if !result.dropped.is_empty() {
+            content_with_quality.push_str(&format!("// Left out: {}\n", result.dropped.join(", ")));
+        }
+    }
+    content_with_quality.push_str("// =============================\n\n");
+    content_with_quality
+}
+
+/// Write one JSON-RPC message as a line on stdout (shared with the subscription watcher)
+fn write_message<T: Serialize>(message: &T) -> Result<()> {
+    let line = serde_json::to_string(message)?;
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", line)?;
+    stdout.flush()?;
+    Ok(())
 }
 
 impl McpServer {
     pub fn new(project_dir: Option<PathBuf>) -> Self {
+        Self::with_graphs(project_dir, GraphCache::default())
+    }
+
+    fn with_graphs(project_dir: Option<PathBuf>, graphs: GraphCache) -> Self {
+        let roots: Vec<WorkspaceRoot> = project_dir.iter().map(|dir| WorkspaceRoot::from_path(dir)).collect();
+        graphs.open_roots(&roots);
         Self {
+            roots,
             project_dir,
             initialized: false,
-            graph_cache: None,
+            protocol_version: None,
+            graphs,
+            client_roots: false,
+            pending_roots: None,
+            next_request_id: 0,
+            subscriptions: Arc::new(Mutex::new(HashSet::new())),
+            #[cfg(feature = "db")]
+            watcher: None,
+            notifier: Arc::new(|message: &ServerMessage| {
+                if let Err(e) = write_message(message) {
+                    eprintln!("Failed to write {}: {}", message.method(), e);
+                }
+            }),
+            in_flight: InFlight::default(),
+            progress: Progress::default(),
+            log_level: None,
+        }
+    }
+
+    /// A fresh, uninitialized session for another client that shares this server's graph cache
+    /// (server-initiated messages go to stdout until `set_notifier`)
+    pub fn new_session(&self) -> Self {
+        Self::with_graphs(self.project_dir.clone(), self.graphs.clone())
+    }
+
+    /// A copy of this session for handling `runs_on_worker` payloads on another thread: same
+    /// graphs, roots, notifier and in-flight requests, but its own progress, so calls overlap
+    pub fn worker(&self) -> Self {
+        self.graphs.open_roots(&self.roots);
+        Self {
+            project_dir: self.project_dir.clone(),
+            initialized: self.initialized,
+            protocol_version: self.protocol_version,
+            graphs: self.graphs.clone(),
+            roots: self.roots.clone(),
+            client_roots: self.client_roots,
+            pending_roots: None,
+            next_request_id: 0,
+            subscriptions: Arc::clone(&self.subscriptions),
+            #[cfg(feature = "db")]
+            watcher: None,
+            notifier: Arc::clone(&self.notifier),
+            in_flight: self.in_flight.clone(),
+            progress: Progress::default(),
+            log_level: self.log_level,
+        }
+    }
+
+    /// Only requests that read the graph (tool calls, resources, prompts) and leave the
+    /// session state alone: they can go to a `worker` while pings and lifecycle messages
+    /// keep being answered
+    pub fn runs_on_worker(payload: &Payload) -> bool {
+        payload.messages.iter().all(|message| match message {
+            Incoming::Request(request) => {
+                request.id.is_some()
+                    && matches!(
+                        request.method.as_str(),
+                        "tools/call" | "resources/list" | "resources/read" | "prompts/get"
+                    )
+            }
+            _ => false,
+        })
+    }
+
+    /// Send server-initiated messages somewhere other than stdout
+    pub fn set_notifier(&mut self, notifier: Notifier) {
+        self.notifier = notifier;
+    }
+
+    /// Handle for cancelling running requests without waiting for the server
+    pub fn in_flight(&self) -> InFlight {
+        self.in_flight.clone()
+    }
+
+    /// Log to stderr, and to the client as `notifications/message` once it asked for this level
+    fn log(&self, level: LogLevel, message: &str) {
+        eprintln!("{}", message);
+        if self.log_level.is_some_and(|min| level >= min) {
+            (self.notifier)(
+                &JsonRpcNotification::new(
+                    "notifications/message",
+                    json!({ "level": level, "logger": "index-chan", "data": message }),
+                )
+                .into(),
+            );
         }
     }
 
     /// Get or load graph with caching
-    /// Returns a reference to the cached graph, scanning if necessary
-    fn get_or_load_graph(&mut self, dir: &Path) -> Result<&CodeGraph, String> {
-        let dir_buf = dir.to_path_buf();
-
-        // Check if cache is valid for this directory
-        let cache_valid = self.graph_cache.is_some()
-            && self.project_dir.as_ref() == Some(&dir_buf);
-
-        if !cache_valid {
-            eprintln!("📊 Loading graph for: {}", dir.display());
-            let mut scanner = Scanner::new().map_err(|e| e.to_string())?;
-            let graph = scanner.scan_directory(dir).map_err(|e| e.to_string())?;
-            self.graph_cache = Some(graph);
-            self.project_dir = Some(dir_buf);
-        } else {
-            eprintln!("📊 Using cached graph");
+    /// Returns the cached graph for `dir`, scanning if necessary; sessions asking for the
+    /// same directory wait for one scan instead of running their own
+    fn get_or_load_graph(&self, dir: &Path) -> Result<Arc<CodeGraph>, String> {
+        let slot = self.graphs.slot(dir);
+        let mut cached = slot.lock().unwrap();
+
+        // A running daemon holds the live graph; refetch only when its generation moved
+        if let Some(mut client) = DaemonClient::connect(dir) {
+            if let Ok(status) = client.status() {
+                if let Some(hit) = cached.as_ref().filter(|c| c.daemon_generation == Some(status.generation)) {
+                    self.log(LogLevel::Debug, &format!("📊 Using cached graph (daemon generation {})", status.generation));
+                    return Ok(Arc::clone(&hit.graph));
+                }
+                if let Ok(snapshot) = client.graph() {
+                    self.log(LogLevel::Info, &format!("📡 Loaded graph from daemon (generation {})", snapshot.generation));
+                    let graph = Arc::new(snapshot.graph);
+                    *cached = Some(CachedGraph {
+                        graph: Arc::clone(&graph),
+                        daemon_generation: Some(snapshot.generation),
+                    });
+                    return Ok(graph);
+                }
+            }
         }
 
-        Ok(self.graph_cache.as_ref().unwrap())
+        // Rescan unless we hold a local scan (a daemon's graph may have gone stale with it)
+        if let Some(hit) = cached.as_ref().filter(|c| c.daemon_generation.is_none()) {
+            self.log(LogLevel::Debug, "📊 Using cached graph");
+            return Ok(Arc::clone(&hit.graph));
+        }
+
+        self.log(LogLevel::Info, &format!("📊 Loading graph for: {}", dir.display()));
+        let mut scanner = Scanner::new().map_err(|e| e.to_string())?;
+        scanner.set_progress(self.progress.clone());
+        let graph = Arc::new(scanner.scan_directory(dir).map_err(|e| e.to_string())?);
+        *cached = Some(CachedGraph {
+            graph: Arc::clone(&graph),
+            daemon_generation: None,
+        });
+        Ok(graph)
     }
 
-    /// Invalidate the graph cache (call after file modifications)
-    #[allow(dead_code)]
-    fn invalidate_cache(&mut self) {
-        self.graph_cache = None;
+    /// Invalidate the graph cache of `dir` (call after file modifications)
+    fn invalidate_cache(&self, dir: &Path) {
+        self.graphs.invalidate(dir);
     }
 
     /// Run the server (stdio mode)
+    ///
+    /// A reader thread takes messages off stdin, so `notifications/cancelled` reaches a tool call
+    /// that is still running. Tool calls run on their own threads and answer when they finish;
+    /// everything else is handled here in order.
     pub fn run(&mut self) -> Result<()> {
-        let stdin = std::io::stdin();
-        let mut stdout = std::io::stdout();
-        let reader = BufReader::new(stdin.lock());
-
         eprintln!("index-chan MCP server started");
 
-        for line in reader.lines() {
-            let line = line?;
-            // Remove BOM and trim whitespace
-            let line = line.trim_start_matches('\u{feff}').trim();
-            if line.is_empty() {
-                continue;
-            }
-
-            match serde_json::from_str::<JsonRpcRequest>(line) {
-                Ok(request) => {
-                    let response = self.handle_request(request);
-                    let response_json = serde_json::to_string(&response)?;
-                    writeln!(stdout, "{}", response_json)?;
-                    stdout.flush()?;
+        let in_flight = self.in_flight();
+        let (sender, receiver) = std::sync::mpsc::channel::<Payload>();
+        let reader = std::thread::spawn(move || -> Result<()> {
+            let stdin = std::io::stdin();
+            for line in BufReader::new(stdin.lock()).lines() {
+                let line = line?;
+                // Remove BOM and trim whitespace
+                let line = line.trim_start_matches('\u{feff}').trim();
+                if line.is_empty() {
+                    continue;
                 }
-                Err(e) => {
-                    let response = JsonRpcResponse::error(
-                        None,
-                        McpError::PARSE_ERROR,
-                        &format!("Parse error: {}", e),
-                    );
-                    let response_json = serde_json::to_string(&response)?;
-                    writeln!(stdout, "{}", response_json)?;
-                    stdout.flush()?;
+
+                match Payload::parse(line.as_bytes()) {
+                    Ok(mut payload) => {
+                        in_flight.intercept(&mut payload);
+                        if !payload.messages.is_empty() && sender.send(payload).is_err() {
+                            break;
+                        }
+                    }
+                    Err(response) => write_message(&response)?,
                 }
             }
+            Ok(())
+        });
+
+        let mut workers: Vec<std::thread::JoinHandle<()>> = Vec::new();
+        for payload in receiver {
+            workers.retain(|worker| !worker.is_finished());
+            if Self::runs_on_worker(&payload) {
+                let mut worker = self.worker();
+                workers.push(std::thread::spawn(move || {
+                    if let Some(reply) = worker.handle_payload(payload) {
+                        if let Err(e) = write_message(&reply) {
+                            eprintln!("Failed to write response: {}", e);
+                        }
+                    }
+                }));
+            } else if let Some(reply) = self.handle_payload(payload) {
+                write_message(&reply)?;
+            }
         }
 
-        Ok(())
+        // stdin closed: answer the calls still running before exiting
+        for worker in workers {
+            let _ = worker.join();
+        }
+        reader.join().map_err(|_| anyhow!("stdin reader panicked"))?
     }
 
-    /// Handle a single request
-    fn handle_request(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
-        match request.method.as_str() {
+    /// Handle a message or a batch (shared by the stdio and HTTP transports).
+    /// None when there is nothing to send back (only notifications or responses).
+    pub fn handle_payload(&mut self, mut payload: Payload) -> Option<Value> {
+        if let Some(version) = self.protocol_version.filter(|v| payload.batch && !supports_batches(v)) {
+            let error = JsonRpcResponse::error(
+                None,
+                McpError::INVALID_REQUEST,
+                &format!("Invalid Request: batches are not supported in protocol version {}", version),
+            );
+            return serde_json::to_value(error).ok();
+        }
+        let responses = std::mem::take(&mut payload.messages)
+            .into_iter()
+            .filter_map(|message| match message {
+                Incoming::Request(request) => self.handle_request(request),
+                Incoming::Response(response) => {
+                    self.handle_response(response);
+                    None
+                }
+                Incoming::Invalid(error) => Some(error),
+            })
+            .filter(|response| !response.is_cancelled())
+            .collect();
+        payload.reply(responses)
+    }
+
+    /// Handle a single request; notifications (no id) are never answered
+    pub fn handle_request(&mut self, request: JsonRpcRequest) -> Option<JsonRpcResponse> {
+        if request.id.is_none() {
+            self.handle_notification(request);
+            return None;
+        }
+
+        // Lifecycle: only ping may come before initialize
+        if !self.initialized && !matches!(request.method.as_str(), "initialize" | "ping") {
+            return Some(JsonRpcResponse::error(
+                request.id,
+                McpError::INVALID_REQUEST,
+                "Server not initialized: send initialize first",
+            ));
+        }
+
+        let response = match request.method.as_str() {
             "initialize" => self.handle_initialize(request),
-            "initialized" => JsonRpcResponse::success(request.id, json!({})),
+            "ping" => JsonRpcResponse::success(request.id, json!({})),
+            "logging/setLevel" => self.handle_set_level(request),
             "tools/list" => self.handle_tools_list(request),
             "tools/call" => self.handle_tools_call(request),
+            "resources/list" => self.handle_resources_list(request),
+            "resources/templates/list" => JsonRpcResponse::success(
+                request.id,
+                json!({ "resourceTemplates": resources::resource_templates() }),
+            ),
+            "resources/read" => self.handle_resources_read(request),
+            "resources/subscribe" => self.handle_resources_subscribe(request, true),
+            "resources/unsubscribe" => self.handle_resources_subscribe(request, false),
+            "prompts/list" => JsonRpcResponse::success(request.id, json!({ "prompts": get_prompt_definitions() })),
+            "prompts/get" => self.handle_prompts_get(request),
             _ => JsonRpcResponse::error(
                 request.id,
                 McpError::METHOD_NOT_FOUND,
                 &format!("Method not found: {}", request.method),
             ),
+        };
+        Some(response)
+    }
+
+    fn handle_notification(&mut self, notification: JsonRpcRequest) {
+        match notification.method.as_str() {
+            // Ask for the workspace roots once the client is ready, and again when they change
+            "notifications/initialized" | "initialized" | "notifications/roots/list_changed" => {
+                self.request_roots();
+            }
+            "notifications/cancelled" => {
+                self.in_flight.cancel(notification.params.as_ref());
+            }
+            method => self.log(LogLevel::Debug, &format!("Ignoring notification: {}", method)),
+        }
+    }
+
+    /// Send `roots/list` if the client supports it; the answer comes back through `handle_response`
+    fn request_roots(&mut self) {
+        if !self.client_roots {
+            return;
         }
+        self.next_request_id += 1;
+        let id = json!(format!("index-chan-{}", self.next_request_id));
+        // A newer request supersedes one still unanswered
+        self.pending_roots = Some(id.clone());
+        (self.notifier)(&ServerMessage::Request(JsonRpcRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(id),
+            method: "roots/list".to_string(),
+            params: None,
+        }));
+    }
+
+    /// Handle the client's response to a server-initiated request
+    fn handle_response(&mut self, response: JsonRpcResponse) {
+        if response.id.is_none() || response.id != self.pending_roots {
+            let id = response.id.unwrap_or(Value::Null);
+            self.log(LogLevel::Debug, &format!("Ignoring response to unknown request {}", id));
+            return;
+        }
+        self.pending_roots = None;
+        match (response.result, response.error) {
+            (Some(result), _) => self.set_roots(roots::from_list_result(&result)),
+            (None, Some(error)) => self.log(LogLevel::Warning, &format!("roots/list failed: {}", error.message)),
+            (None, None) => {}
+        }
+    }
+
+    /// Replace the workspace roots (an empty list falls back to the project directory)
+    fn set_roots(&mut self, client_roots: Vec<WorkspaceRoot>) {
+        let roots = if client_roots.is_empty() {
+            self.project_dir.iter().map(|dir| WorkspaceRoot::from_path(dir)).collect()
+        } else {
+            client_roots
+        };
+        // Open the new roots first, so the graphs of roots that stay are kept
+        self.graphs.open_roots(&roots);
+        self.graphs.close_roots(&self.roots);
+        self.log(LogLevel::Info, &format!("📁 Workspace roots: {}", roots::names(&roots)));
+        self.roots = roots;
     }
 
     /// Handle initialize request
     fn handle_initialize(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let requested = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("protocolVersion"))
+            .and_then(Value::as_str);
+        let Some(requested) = requested else {
+            return JsonRpcResponse::error(request.id, McpError::INVALID_PARAMS, "Missing protocolVersion");
+        };
+        let protocol_version = negotiate_protocol_version(requested);
+        self.client_roots = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("capabilities"))
+            .and_then(|c| serde_json::from_value::<ClientCapabilities>(c.clone()).ok())
+            .is_some_and(|c| c.roots.is_some());
+        if protocol_version != requested {
+            self.log(
+                LogLevel::Warning,
+                &format!("Unsupported protocol version {}, offering {}", requested, protocol_version),
+            );
+        }
         self.initialized = true;
+        self.protocol_version = Some(protocol_version);
 
         let result = InitializeResult {
-            protocol_version: "2024-11-05".to_string(),
+            protocol_version: protocol_version.to_string(),
             capabilities: ServerCapabilities {
+                logging: Some(json!({})),
                 tools: Some(ToolsCapability {
                     list_changed: false,
                 }),
+                resources: Some(ResourcesCapability {
+                    // Change notifications need the file watcher
+                    subscribe: cfg!(feature = "db"),
+                    list_changed: false,
+                }),
+                prompts: Some(PromptsCapability { list_changed: false }),
             },
             server_info: ServerInfo {
                 name: "index-chan".to_string(),
@@ -134,6 +587,21 @@ impl McpServer {
         JsonRpcResponse::success(request.id, serde_json::to_value(result).unwrap())
     }
 
+    /// Handle logging/setLevel request
+    fn handle_set_level(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let params = request
+            .params
+            .ok_or_else(|| "Missing params".to_string())
+            .and_then(|p| serde_json::from_value::<SetLevelParams>(p).map_err(|e| format!("Invalid params: {}", e)));
+        match params {
+            Ok(params) => {
+                self.log_level = Some(params.level);
+                JsonRpcResponse::success(request.id, json!({}))
+            }
+            Err(e) => JsonRpcResponse::error(request.id, McpError::INVALID_PARAMS, &e),
+        }
+    }
+
     /// Handle tools/list request
     fn handle_tools_list(&self, request: JsonRpcRequest) -> JsonRpcResponse {
         let tools = get_tool_definitions();
@@ -162,73 +630,302 @@ impl McpServer {
             }
         };
 
-        let result = match params.name.as_str() {
-            "scan" => self.tool_scan(params.arguments),
-            "search" => self.tool_search(params.arguments),
-            "stats" => self.tool_stats(params.arguments),
-            "gather_context" => self.tool_gather_context(params.arguments),
-            "get_dependencies" => self.tool_get_dependencies(params.arguments),
-            "get_dependents" => self.tool_get_dependents(params.arguments),
-            "validate_changes" => self.tool_validate_changes(params.arguments),
-            "preview_changes" => self.tool_preview_changes(params.arguments),
-            "apply_changes" => self.tool_apply_changes(params.arguments),
-            "search_with_graph" => self.tool_search_with_graph(params.arguments),
-            "parse_llm_edits" => self.tool_parse_llm_edits(params.arguments),
-            "get_call_chain" => self.tool_get_call_chain(params.arguments),
-            "get_call_tree" => self.tool_get_call_tree(params.arguments),
-            _ => Err(format!("Unknown tool: {}", params.name)),
+        // Unknown tools and arguments that do not match the input schema are protocol errors
+        let Some(tool) = get_tool_definitions().into_iter().find(|t| t.name == params.name) else {
+            return JsonRpcResponse::error(
+                request.id,
+                McpError::INVALID_PARAMS,
+                &format!("Unknown tool: {}", params.name),
+            );
         };
+        let arguments = params.arguments.unwrap_or_else(|| json!({}));
+        let errors = schema::validate(&tool.input_schema, &arguments);
+        if !errors.is_empty() {
+            return JsonRpcResponse::error_with_data(
+                request.id,
+                McpError::INVALID_PARAMS,
+                &format!("Invalid arguments for {}: {}", tool.name, errors.join("; ")),
+                json!({ "errors": errors }),
+            );
+        }
+        let arguments = Some(arguments);
 
-        match result {
-            Ok(content) => {
-                let call_result = CallToolResult {
-                    content: vec![ToolContent::Text { text: content }],
-                    is_error: false,
-                };
-                JsonRpcResponse::success(request.id, serde_json::to_value(call_result).unwrap())
+        let progress = self.request_progress(params.meta.as_ref());
+        if let Some(id) = &request.id {
+            self.in_flight.start(id, progress.clone());
+        }
+        self.progress = progress.clone();
+
+        let result = match tool.name.as_str() {
+            "scan" => self.tool_scan(arguments),
+            "search" => self.tool_search(arguments),
+            "stats" => self.tool_stats(arguments),
+            "gather_context" => self.tool_gather_context(arguments),
+            "get_dependencies" => self.tool_get_dependencies(arguments),
+            "get_dependents" => self.tool_get_dependents(arguments),
+            "validate_changes" => self.tool_validate_changes(arguments),
+            "preview_changes" => self.tool_preview_changes(arguments),
+            "apply_changes" => self.tool_apply_changes(arguments),
+            "search_with_graph" => self.tool_search_with_graph(arguments),
+            "parse_llm_edits" => self.tool_parse_llm_edits(arguments),
+            "get_call_chain" => self.tool_get_call_chain(arguments),
+            "get_call_tree" => self.tool_get_call_tree(arguments),
+            "find_references" => self.tool_find_references(arguments),
+            "go_to_definition" => self.tool_go_to_definition(arguments),
+            "rename_symbol" => self.tool_rename_symbol(arguments),
+            _ => Err(format!("Unknown tool: {}", tool.name)),
+        };
+
+        self.progress = Progress::default();
+        if let Some(id) = &request.id {
+            self.in_flight.finish(id);
+        }
+        if progress.is_cancelled() {
+            return JsonRpcResponse::error(request.id, McpError::REQUEST_CANCELLED, "Request cancelled");
+        }
+
+        let call_result = match result {
+            // Plain text tools (gather_context)
+            Ok(Value::String(text)) => CallToolResult {
+                content: vec![ToolContent::Text { text }],
+                structured_content: None,
+                is_error: false,
+            },
+            // JSON results go out twice: as text for older clients and as structuredContent
+            Ok(value) => CallToolResult {
+                content: vec![ToolContent::Text {
+                    text: serde_json::to_string_pretty(&value).unwrap(),
+                }],
+                structured_content: Some(value),
+                is_error: false,
+            },
+            Err(e) => CallToolResult {
+                content: vec![ToolContent::Text { text: e }],
+                structured_content: None,
+                is_error: true,
+            },
+        };
+        JsonRpcResponse::success(request.id, serde_json::to_value(call_result).unwrap())
+    }
+
+    /// Progress for a tool call, sent as `notifications/progress` when the client gave a token
+    fn request_progress(&self, meta: Option<&RequestMeta>) -> Progress {
+        let Some(token) = meta.and_then(|m| m.progress_token.clone()) else {
+            return Progress::default();
+        };
+        let notifier = Arc::clone(&self.notifier);
+        // (offset, last value of the current phase, last value sent): a call can run
+        // several phases (scan, then embeddings) that each count from zero, while
+        // clients expect one increasing value
+        let state = Mutex::new((0u64, 0u64, None::<u64>));
+        Progress::new(Arc::new(move |done, total, message: &str| {
+            let mut state = state.lock().unwrap();
+            if done < state.1 {
+                state.0 += state.1;
             }
-            Err(e) => {
-                let call_result = CallToolResult {
-                    content: vec![ToolContent::Text { text: e }],
-                    is_error: true,
-                };
-                JsonRpcResponse::success(request.id, serde_json::to_value(call_result).unwrap())
+            state.1 = done;
+            let progress = state.0 + done;
+            if state.2.is_some_and(|sent| progress <= sent) {
+                return;
+            }
+            state.2 = Some(progress);
+
+            let mut params = json!({ "progressToken": token, "progress": progress, "message": message });
+            if let Some(total) = total {
+                params["total"] = json!(state.0 + total);
+            }
+            notifier(&JsonRpcNotification::new("notifications/progress", params).into());
+        }))
+    }
+
+    // ===== Resources =====
+
+    fn resource_dir(&self) -> Result<PathBuf, String> {
+        self.project_dir
+            .clone()
+            .or_else(|| self.roots.first().map(|root| root.path.clone()))
+            .ok_or_else(|| "No project directory: start the server with a directory".to_string())
+    }
+
+    /// Handle resources/list request
+    fn handle_resources_list(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let listed = self.resource_dir().and_then(|dir| {
+            let graph = self.get_or_load_graph(&dir)?;
+            Ok(resources::list_resources(&dir, &graph))
+        });
+        match listed {
+            Ok(list) => JsonRpcResponse::success(request.id, json!({ "resources": list })),
+            Err(e) => JsonRpcResponse::error(request.id, McpError::INTERNAL_ERROR, &e),
+        }
+    }
+
+    /// Handle resources/read request
+    fn handle_resources_read(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let params: ResourceUriParams = match request.params.map(serde_json::from_value) {
+            Some(Ok(params)) => params,
+            _ => return JsonRpcResponse::error(request.id, McpError::INVALID_PARAMS, "Missing or invalid uri"),
+        };
+        let dir = match self.resource_dir() {
+            Ok(dir) => dir,
+            Err(e) => return JsonRpcResponse::error(request.id, McpError::INTERNAL_ERROR, &e),
+        };
+        let graph = match self.get_or_load_graph(&dir) {
+            Ok(graph) => graph,
+            Err(e) => return JsonRpcResponse::error(request.id, McpError::INTERNAL_ERROR, &e),
+        };
+        match resources::read_resource(&dir, &graph, &params.uri) {
+            Ok(contents) => JsonRpcResponse::success(request.id, json!({ "contents": [contents] })),
+            Err(e) => JsonRpcResponse::error(request.id, McpError::RESOURCE_NOT_FOUND, &e),
+        }
+    }
+
+    /// Handle resources/subscribe and resources/unsubscribe requests
+    fn handle_resources_subscribe(&mut self, request: JsonRpcRequest, subscribe: bool) -> JsonRpcResponse {
+        let params: ResourceUriParams = match request.params.map(serde_json::from_value) {
+            Some(Ok(params)) => params,
+            _ => return JsonRpcResponse::error(request.id, McpError::INVALID_PARAMS, "Missing or invalid uri"),
+        };
+        if resources::ResourceUri::parse(&params.uri).is_none() {
+            return JsonRpcResponse::error(
+                request.id,
+                McpError::RESOURCE_NOT_FOUND,
+                &format!("Unknown resource: {}", params.uri),
+            );
+        }
+
+        if subscribe {
+            #[cfg(feature = "db")]
+            if let Err(e) = self.ensure_watcher() {
+                return JsonRpcResponse::error(request.id, McpError::INTERNAL_ERROR, &e);
+            }
+            self.subscriptions.lock().unwrap().insert(params.uri);
+        } else {
+            self.subscriptions.lock().unwrap().remove(&params.uri);
+        }
+        JsonRpcResponse::success(request.id, json!({}))
+    }
+
+    /// Start watching the project directory for subscribed resources; a watcher for a
+    /// previous directory is stopped
+    #[cfg(feature = "db")]
+    fn ensure_watcher(&mut self) -> Result<(), String> {
+        let dir = self.resource_dir()?;
+        if self.watcher.as_ref().is_some_and(|(watched, _)| *watched == dir) {
+            return Ok(());
+        }
+        // Its thread ends once the old debouncer (and with it the event channel) is gone
+        self.watcher = None;
+
+        let (debouncer, events) =
+            crate::watcher::event_stream(&dir, std::time::Duration::from_millis(300)).map_err(|e| e.to_string())?;
+        let subscriptions = Arc::clone(&self.subscriptions);
+        let graphs = self.graphs.clone();
+        let notifier = Arc::clone(&self.notifier);
+        let watch_dir = dir.clone();
+        std::thread::spawn(move || {
+            for result in events {
+                let paths = crate::watcher::event_paths(result);
+                if resources::sources_changed(&watch_dir, &paths) {
+                    graphs.mark_stale(&watch_dir);
+                }
+
+                let subscriptions = subscriptions.lock().unwrap();
+                for uri in resources::affected_uris(&watch_dir, &subscriptions, &paths) {
+                    notifier(&JsonRpcNotification::new("notifications/resources/updated", json!({ "uri": uri })).into());
+                }
             }
+        });
+
+        self.watcher = Some((dir, debouncer));
+        Ok(())
+    }
+
+    // ===== Prompts =====
+
+    /// Handle prompts/get request
+    fn handle_prompts_get(&mut self, request: JsonRpcRequest) -> JsonRpcResponse {
+        let params: GetPromptParams = match request.params.map(serde_json::from_value) {
+            Some(Ok(params)) => params,
+            _ => return JsonRpcResponse::error(request.id, McpError::INVALID_PARAMS, "Missing or invalid params"),
+        };
+        if !get_prompt_definitions().iter().any(|p| p.name == params.name) {
+            return JsonRpcResponse::error(
+                request.id,
+                McpError::INVALID_PARAMS,
+                &format!("Unknown prompt: {}", params.name),
+            );
+        }
+
+        let arguments = params.arguments.unwrap_or_default();
+        let prompt = self
+            .resource_dir()
+            .and_then(|dir| self.get_or_load_graph(&dir).map(|graph| get_prompt(graph, &params.name, &arguments)));
+        match prompt {
+            Ok(Ok(result)) => JsonRpcResponse::success(request.id, serde_json::to_value(result).unwrap()),
+            Ok(Err(e)) => JsonRpcResponse::error(request.id, McpError::INVALID_PARAMS, &e),
+            Err(e) => JsonRpcResponse::error(request.id, McpError::INTERNAL_ERROR, &e),
         }
     }
 
     // ===== Tool Implementations =====
 
     /// Scan tool - dead code detection
-    fn tool_scan(&self, args: Option<Value>) -> Result<String, String> {
+    fn tool_scan(&self, args: Option<Value>) -> Result<Value, String> {
         let directory = self.get_directory_arg(&args)?;
+        let page_request = PageRequest::from_args(args.as_ref().unwrap_or(&Value::Null))?;
 
-        let mut scanner = Scanner::new().map_err(|e| e.to_string())?;
-        let graph = scanner
-            .scan_directory(&directory)
-            .map_err(|e| e.to_string())?;
+        let package = args.as_ref().and_then(|a| a.get("package")).and_then(|v| v.as_str());
 
-        let dead_code = detect_dead_code(&graph);
+        // The cached graph, so later pages do not rescan the project
+        let graph = self.get_or_load_graph(&directory)?;
+
+        // Detect on the whole graph so uses from other packages count
+        let mut dead_code = detect_dead_code(&graph);
+        // Graph nodes have no order: sort so a cursor offset means the same on every page
+        dead_code.sort_by(|a, b| {
+            let (a, b) = (&a.node, &b.node);
+            (&a.file_path, a.line_range.0, &a.name).cmp(&(&b.file_path, b.line_range.0, &b.name))
+        });
+        let total_functions = match package {
+            Some(name) => {
+                if !graph.nodes.values().any(|n| n.package == name) {
+                    return Err(format!("Package not found: {}", name));
+                }
+                dead_code.retain(|d| d.node.package == name);
+                graph.nodes.values().filter(|n| n.package == name).count()
+            }
+            None => graph.nodes.len(),
+        };
+
+        let entries = dead_code.iter().map(|d| {
+            json!({
+                "name": d.node.name,
+                "symbol_id": d.node.symbol_id,
+                "package": d.node.package,
+                "file": d.node.file_path.display().to_string(),
+                "line": d.node.line_range.0,
+                "safety": format!("{:?}", d.safety_level)
+            })
+        }).collect();
+        let page = paginate(
+            entries,
+            &page_request,
+            &[("by_file", "file"), ("by_safety", "safety"), ("by_package", "package")],
+        );
 
         let result = json!({
-            "total_functions": graph.nodes.len(),
+            "total_functions": total_functions,
             "dead_code_count": dead_code.len(),
-            "dead_code": dead_code.iter().map(|d| {
-                json!({
-                    "name": d.node.name,
-                    "file": d.node.file_path.display().to_string(),
-                    "line": d.node.line_range.0,
-                    "safety": format!("{:?}", d.safety_level)
-                })
-            }).collect::<Vec<_>>()
+            "dead_code": page.items,
+            "page": page.info
         });
 
         // Save to DB (feature="db")
         #[cfg(feature = "db")]
         {
             let db_path = directory.join(".index-chan").join("graph.db");
-            // Clone graph for background thread
-            let graph_clone = graph.clone();
+            // Share the graph with the background thread
+            let graph_clone = Arc::clone(&graph);
 
             std::thread::spawn(move || {
                 let rt = tokio::runtime::Builder::new_current_thread()
@@ -253,11 +950,11 @@ impl McpServer {
             .map_err(|_| "Failed to join db thread".to_string())?;
         }
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+        Ok(result)
     }
 
     /// Search tool - code search (redirects to search_with_graph)
-    fn tool_search(&mut self, args: Option<Value>) -> Result<String, String> {
+    fn tool_search(&mut self, args: Option<Value>) -> Result<Value, String> {
         // search ツールは search_with_graph にリダイレクト (DB依存を削除)
         // デフォルト: semantic=false, graph_depth=1
         let mut new_args = args.unwrap_or(json!({}));
@@ -276,7 +973,7 @@ impl McpServer {
 
     /// Stats tool - project statistics
     /// Enhanced with semantic relation type clustering (Concept Transformer Phase 2)
-    fn tool_stats(&mut self, args: Option<Value>) -> Result<String, String> {
+    fn tool_stats(&mut self, args: Option<Value>) -> Result<Value, String> {
         use crate::graph::SemanticRelationType;
         use std::collections::HashMap;
 
@@ -329,14 +1026,23 @@ impl McpServer {
                 .collect::<std::collections::HashSet<_>>()
                 .len(),
             "edge_types": edge_type_counts,
-            "semantic_relations": semantic_summary
+            "semantic_relations": semantic_summary,
+            "packages": graph.packages().into_iter().map(|(name, nodes)| {
+                let dead = dead_code.iter().filter(|d| d.node.package == name).count();
+                json!({
+                    "name": name,
+                    "nodes": nodes,
+                    "dead_code_count": dead
+                })
+            }).collect::<Vec<_>>(),
+            "package_dependencies": graph.package_dependencies()
         });
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+        Ok(result)
     }
 
     /// gather_context tool - collect code with dependencies
-    fn tool_gather_context(&mut self, args: Option<Value>) -> Result<String, String> {
+    fn tool_gather_context(&mut self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
         let directory = self.get_directory_from_value(&args)?;
 
@@ -351,51 +1057,52 @@ impl McpServer {
             Some("llm_edit") => ContextFormat::LlmEdit,
             _ => ContextFormat::Standard,
         };
+        let use_semantic = args
+            .get("semantic")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+        let max_tokens = args.get("max_tokens").and_then(|v| v.as_u64()).map(|n| n as usize);
 
         // Use cached graph
         let graph = self.get_or_load_graph(&directory)?.clone();
-        let generator = ContextGenerator::from_graph(graph);
+        let mut generator = ContextGenerator::from_graph(graph.clone());
+
+        // A query picks its starting functions by semantic search when embeddings are available
+        if query.is_some() && entry_point.is_none() && use_semantic {
+            use crate::embedding_cache::EmbeddingCache;
+            match EmbeddingCache::get_or_create_with_progress(&graph, &directory, &self.progress) {
+                Ok(cache) => generator = generator.with_embeddings(cache.embeddings),
+                Err(e) => self.log(
+                    LogLevel::Warning,
+                    &format!("Failed to load embeddings, falling back to name match: {}", e),
+                ),
+            }
+        }
 
-        let result = generator
-            .gather_context(entry_point, query, depth, mode, format)
-            .map_err(|e| e.to_string())?;
+        // The quality header counts against max_tokens too: while the whole text does not fit,
+        // fit the content again to what the header leaves
+        let mut budget = max_tokens;
+        loop {
+            let result = generator
+                .gather_context(entry_point, query, depth, mode, format, budget)
+                .map_err(|e| e.to_string())?;
+
+            // For llm_edit format, return content directly without quality header
+            if format == ContextFormat::LlmEdit {
+                return Ok(Value::String(result.content));
+            }
 
-        // For llm_edit format, return content directly without quality header
-        if format == ContextFormat::LlmEdit {
-            return Ok(result.content);
-        }
-
-        // Build header with quality info (for standard format)
-        // Enhanced with Concept Transformer Phase 9b metrics
-        let mut content_with_quality = format!(
-            "// ===== QUALITY METRICS =====\n\
-             // Estimated tokens: {}\n\
-             // S/N ratio: {:.2}\n\
-             // Concept density: {:.2}\n\
-             // Dependencies: {}\n\
-             // Entry point ratio: {:.1}%\n\
-             // Quality: {}\n",
-            result.quality.estimated_tokens,
-            result.quality.sn_ratio,
-            result.quality.concept_density,
-            result.quality.dependency_count,
-            result.quality.entry_point_ratio * 100.0,
-            result.quality.quality_level
-        );
-        if result.quality.context_explosion_warning {
-            content_with_quality.push_str("// ⚠️ WARNING: Context explosion detected!\n");
-        }
-        if let Some(ref rec) = result.quality.recommendation {
-            content_with_quality.push_str(&format!("// Recommendation: {}\n", rec));
+            let text = format!("{}{}", quality_header(&result, max_tokens), result.content);
+            let overflow = max_tokens.map_or(0, |max| count_tokens(&text).saturating_sub(max));
+            match budget {
+                Some(current) if overflow > 0 && current > 0 => budget = Some(current.saturating_sub(overflow)),
+                _ => return Ok(Value::String(text)),
+            }
         }
-        content_with_quality.push_str("// =============================\n\n");
-        content_with_quality.push_str(&result.content);
-
-        Ok(content_with_quality)
     }
 
     /// get_dependencies tool
-    fn tool_get_dependencies(&mut self, args: Option<Value>) -> Result<String, String> {
+    fn tool_get_dependencies(&mut self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
         let directory = self.get_directory_from_value(&args)?;
         let function_name = args
@@ -409,17 +1116,24 @@ impl McpServer {
         let generator = ContextGenerator::from_graph(graph);
 
         let deps = generator.get_dependencies(function_name, depth);
+        let deps = serde_json::to_value(deps).map_err(|e| e.to_string())?;
+        let page = paginate(
+            deps.as_array().cloned().unwrap_or_default(),
+            &PageRequest::from_args(&args)?,
+            &[("by_file", "file_path")],
+        );
 
         let result = json!({
             "function": function_name,
-            "dependencies": deps
+            "dependencies": page.items,
+            "page": page.info
         });
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+        Ok(result)
     }
 
     /// get_dependents tool
-    fn tool_get_dependents(&mut self, args: Option<Value>) -> Result<String, String> {
+    fn tool_get_dependents(&mut self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
         let directory = self.get_directory_from_value(&args)?;
         let function_name = args
@@ -433,13 +1147,20 @@ impl McpServer {
         let generator = ContextGenerator::from_graph(graph);
 
         let deps = generator.get_dependents(function_name, depth);
+        let deps = serde_json::to_value(deps).map_err(|e| e.to_string())?;
+        let page = paginate(
+            deps.as_array().cloned().unwrap_or_default(),
+            &PageRequest::from_args(&args)?,
+            &[("by_file", "file_path")],
+        );
 
         let result = json!({
             "function": function_name,
-            "dependents": deps
+            "dependents": page.items,
+            "page": page.info
         });
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+        Ok(result)
     }
 
     // ===== Helper Methods =====
@@ -449,21 +1170,36 @@ impl McpServer {
         self.get_directory_from_value(args)
     }
 
+    /// Directory a tool works on: `directory`, else the `root` it names, else the only root
     fn get_directory_from_value(&self, args: &Value) -> Result<PathBuf, String> {
-        let dir_str = args
-            .get("directory")
-            .and_then(|v| v.as_str())
-            .ok_or("Missing directory parameter")?;
+        let directory = args.get("directory").and_then(|v| v.as_str());
+        let root = args.get("root").and_then(|v| v.as_str());
+        let path = match (directory, root) {
+            (Some(dir), _) => PathBuf::from(dir),
+            (None, Some(roots::ALL_ROOTS)) => {
+                return Err("root \"*\" is only supported by search and search_with_graph".to_string());
+            }
+            (None, Some(root)) => roots::find(&self.roots, root)?.path.clone(),
+            (None, None) => match self.roots.as_slice() {
+                [only] => only.path.clone(),
+                [] => return Err("Missing directory parameter".to_string()),
+                several => {
+                    return Err(format!(
+                        "Several workspace roots are open ({}): pass root or directory",
+                        roots::names(several)
+                    ));
+                }
+            },
+        };
 
-        let path = PathBuf::from(dir_str);
         if !path.exists() {
-            return Err(format!("Directory not found: {}", dir_str));
+            return Err(format!("Directory not found: {}", path.display()));
         }
         Ok(path)
     }
 
     /// validate_changes tool
-    fn tool_validate_changes(&self, args: Option<Value>) -> Result<String, String> {
+    fn tool_validate_changes(&self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
         let directory = self.get_directory_from_value(&args)?;
 
@@ -478,11 +1214,11 @@ impl McpServer {
             .validate_changes(&changes)
             .map_err(|e| e.to_string())?;
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+        serde_json::to_value(&result).map_err(|e| e.to_string())
     }
 
     /// preview_changes tool
-    fn tool_preview_changes(&self, args: Option<Value>) -> Result<String, String> {
+    fn tool_preview_changes(&self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
         let directory = self.get_directory_from_value(&args)?;
 
@@ -491,17 +1227,19 @@ impl McpServer {
             .and_then(|v| serde_json::from_value(v.clone()).ok())
             .ok_or("Invalid changes parameter")?;
 
+        let inline = args.get("inline").and_then(|v| v.as_bool()).unwrap_or(false);
+
         let manager = ChangeManager::from_directory(&directory).map_err(|e| e.to_string())?;
 
         let result = manager
-            .preview_changes(&changes)
+            .preview_changes(&changes, inline)
             .map_err(|e| e.to_string())?;
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+        serde_json::to_value(&result).map_err(|e| e.to_string())
     }
 
     /// apply_changes tool
-    fn tool_apply_changes(&self, args: Option<Value>) -> Result<String, String> {
+    fn tool_apply_changes(&mut self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
         let directory = self.get_directory_from_value(&args)?;
 
@@ -521,13 +1259,20 @@ impl McpServer {
             .apply_changes(&changes, create_backup)
             .map_err(|e| e.to_string())?;
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+        // Let the daemon pick up the edits right away; without one, rescan next time
+        let files = changes.iter().map(|c| PathBuf::from(&c.file_path)).collect();
+        let refreshed = DaemonClient::connect(&directory).is_some_and(|mut client| client.refresh(files).is_ok());
+        if !refreshed {
+            self.invalidate_cache(&directory);
+        }
+
+        serde_json::to_value(&result).map_err(|e| e.to_string())
     }
 
     /// search_with_graph tool (Phase 7 GraphRAG)
-    fn tool_search_with_graph(&mut self, args: Option<Value>) -> Result<String, String> {
+    /// `root: "*"` runs the search in every workspace root; results carry the root they came from
+    fn tool_search_with_graph(&mut self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
-        let directory = self.get_directory_from_value(&args)?;
         let query = args
             .get("query")
             .and_then(|v| v.as_str())
@@ -545,9 +1290,98 @@ impl McpServer {
             .get("filter_generic")
             .and_then(|v| v.as_bool())
             .unwrap_or(true);
+        let page_request = PageRequest::from_args(&args)?;
+
+        let all_roots = args.get("directory").is_none()
+            && args.get("root").and_then(|v| v.as_str()) == Some(roots::ALL_ROOTS);
+        let targets: Vec<(PathBuf, Option<String>)> = if all_roots {
+            if self.roots.is_empty() {
+                return Err("No workspace roots to search".to_string());
+            }
+            self.roots.iter().map(|r| (r.path.clone(), Some(r.name.clone()))).collect()
+        } else {
+            let directory = self.get_directory_from_value(&args)?;
+            let root = self.roots.iter().find(|r| r.path == directory).map(|r| r.name.clone());
+            vec![(directory, root)]
+        };
+
+        let mut results = Vec::new();
+        for (directory, root) in targets {
+            match self.search_directory(&directory, query, top_k, graph_depth, use_semantic, filter_generic) {
+                Ok(found) => results.extend(found.into_iter().map(|r| (root.clone(), r))),
+                // One unreadable root should not hide the matches in the others
+                Err(e) if all_roots => {
+                    self.log(LogLevel::Warning, &format!("Skipping root {}: {}", directory.display(), e));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        if all_roots {
+            results.sort_by(|(_, a), (_, b)| b.score.total_cmp(&a.score));
+        }
+
+        let entries = results.iter().map(|(root, r)| {
+            json!({
+                "root": root,
+                "name": r.metadata.function_name,
+                "file": r.metadata.file_path.display().to_string(),
+                "score": r.score,
+                "depth": r.depth,
+                "line_range": [r.metadata.start_line, r.metadata.end_line],
+                "match_type": format!("{:?}", r.match_type),
+                "explanation": {
+                    "trace": r.explanation.trace.iter().map(|t| {
+                        json!({
+                            "node": t.node,
+                            "node_type": t.node_type,
+                            "reason": t.reason,
+                            "edge": t.edge,
+                            "direction": t.direction
+                        })
+                    }).collect::<Vec<_>>(),
+                    "score_details": {
+                        "base_score": r.explanation.score_details.base_score,
+                        "decay_factor": r.explanation.score_details.decay_factor,
+                        "depth": r.explanation.score_details.depth
+                    }
+                }
+            })
+        }).collect();
+        let page = paginate(entries, &page_request, &[("by_root", "root"), ("by_file", "file")]);
+
+        let result = json!({
+            "query": query,
+            "graph_depth": graph_depth,
+            "semantic": use_semantic,
+            "results": page.items,
+            "page": page.info
+        });
+
+        Ok(result)
+    }
+
+    /// Graph search in one directory: through its daemon if one runs, else on the cached graph
+    fn search_directory(
+        &mut self,
+        directory: &Path,
+        query: &str,
+        top_k: usize,
+        graph_depth: usize,
+        use_semantic: bool,
+        filter_generic: bool,
+    ) -> Result<Vec<GraphSearchResult>, String> {
+        // The daemon keeps the search index (and embeddings) warm
+        let from_daemon = DaemonClient::connect(directory).and_then(|mut client| {
+            client
+                .search(query, top_k, graph_depth, filter_generic, use_semantic)
+                .ok()
+        });
+        if let Some(results) = from_daemon {
+            return Ok(results);
+        }
 
         // Use cached graph
-        let graph = self.get_or_load_graph(&directory)?.clone();
+        let graph = self.get_or_load_graph(directory)?.clone();
 
         // Use GraphSearcher
         use crate::search::GraphSearcher;
@@ -558,18 +1392,18 @@ impl McpServer {
             #[cfg(feature = "semantic-search")]
             {
                 use crate::embedding_cache::EmbeddingCache;
-                match EmbeddingCache::get_or_create(&graph, &directory) {
+                match EmbeddingCache::get_or_create_with_progress(&graph, directory, &self.progress) {
                     Ok(cache) => {
-                        eprintln!(
-                            "Using semantic search with {} embeddings",
-                            cache.embeddings.len()
+                        self.log(
+                            LogLevel::Info,
+                            &format!("Using semantic search with {} embeddings", cache.embeddings.len()),
                         );
                         searcher.search_semantic(query, &cache.embeddings, top_k, graph_depth)
                     }
                     Err(e) => {
-                        eprintln!(
-                            "Failed to load embeddings, falling back to name match: {}",
-                            e
+                        self.log(
+                            LogLevel::Warning,
+                            &format!("Failed to load embeddings, falling back to name match: {}", e),
                         );
                         searcher.search_with_graph_filtered(query, top_k, graph_depth, filter_generic)
                     }
@@ -577,51 +1411,18 @@ impl McpServer {
             }
             #[cfg(not(feature = "semantic-search"))]
             {
-                eprintln!("semantic-search feature not enabled, using name match");
+                self.log(LogLevel::Warning, "semantic-search feature not enabled, using name match");
                 searcher.search_with_graph_filtered(query, top_k, graph_depth, filter_generic)
             }
         } else {
             // Use name-based matching with filtering
             searcher.search_with_graph_filtered(query, top_k, graph_depth, filter_generic)
         };
-
-        let result = json!({
-            "query": query,
-            "graph_depth": graph_depth,
-            "semantic": use_semantic,
-            "results": results.iter().map(|r| {
-                json!({
-                    "name": r.metadata.function_name,
-                    "file": r.metadata.file_path.display().to_string(),
-                    "score": r.score,
-                    "depth": r.depth,
-                    "line_range": [r.metadata.start_line, r.metadata.end_line],
-                    "match_type": format!("{:?}", r.match_type),
-                    "explanation": {
-                        "trace": r.explanation.trace.iter().map(|t| {
-                            json!({
-                                "node": t.node,
-                                "node_type": t.node_type,
-                                "reason": t.reason,
-                                "edge": t.edge,
-                                "direction": t.direction
-                            })
-                        }).collect::<Vec<_>>(),
-                        "score_details": {
-                            "base_score": r.explanation.score_details.base_score,
-                            "decay_factor": r.explanation.score_details.decay_factor,
-                            "depth": r.explanation.score_details.depth
-                        }
-                    }
-                })
-            }).collect::<Vec<_>>()
-        });
-
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+        Ok(results)
     }
 
     /// parse_llm_edits tool - Parse LLM output into changes array
-    fn tool_parse_llm_edits(&self, args: Option<Value>) -> Result<String, String> {
+    fn tool_parse_llm_edits(&self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
         let content = args
             .get("content")
@@ -641,11 +1442,11 @@ impl McpServer {
             }).collect::<Vec<_>>()
         });
 
-        Ok(serde_json::to_string_pretty(&result).unwrap())
+        Ok(result)
     }
 
     /// get_call_chain tool - Find path between two functions
-    fn tool_get_call_chain(&mut self, args: Option<Value>) -> Result<String, String> {
+    fn tool_get_call_chain(&mut self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
         let directory = self.get_directory_from_value(&args)?;
         let from = args
@@ -685,11 +1486,11 @@ impl McpServer {
             "visual": visual
         });
 
-        Ok(serde_json::to_string_pretty(&output).unwrap())
+        Ok(output)
     }
 
     /// get_call_tree tool - Visualize call hierarchy
-    fn tool_get_call_tree(&mut self, args: Option<Value>) -> Result<String, String> {
+    fn tool_get_call_tree(&mut self, args: Option<Value>) -> Result<Value, String> {
         let args = args.ok_or("Missing arguments")?;
         let directory = self.get_directory_from_value(&args)?;
         let function_name = args
@@ -703,13 +1504,27 @@ impl McpServer {
         let graph = self.get_or_load_graph(&directory)?.clone();
         let generator = ContextGenerator::from_graph(graph);
         let tree = generator.get_call_tree(function_name, depth, direction);
+        let tree = serde_json::to_value(tree).map_err(|e| e.to_string())?;
+        let page = paginate(
+            tree.as_array().cloned().unwrap_or_default(),
+            &PageRequest::from_args(&args)?,
+            &[("by_file", "file_path"), ("by_depth", "depth")],
+        );
 
-        // Format output with visual tree representation
-        let visual = tree.iter()
+        // Format output with visual tree representation (of this page only)
+        let visual = page.items.iter()
             .map(|node| {
-                let indent = "  ".repeat(node.depth);
-                let prefix = if node.depth == 0 { "●" } else { "├─" };
-                format!("{}{} {} ({}:{})", indent, prefix, node.name, node.file_path, node.line)
+                let node_depth = node["depth"].as_u64().unwrap_or(0) as usize;
+                let indent = "  ".repeat(node_depth);
+                let prefix = if node_depth == 0 { "●" } else { "├─" };
+                format!(
+                    "{}{} {} ({}:{})",
+                    indent,
+                    prefix,
+                    node["name"].as_str().unwrap_or_default(),
+                    node["file_path"].as_str().unwrap_or_default(),
+                    node["line"]
+                )
             })
             .collect::<Vec<_>>()
             .join("\n");
@@ -718,11 +1533,127 @@ impl McpServer {
             "function": function_name,
             "direction": direction,
             "depth": depth,
-            "nodes": tree,
-            "visual": visual
+            "nodes": page.items,
+            "visual": visual,
+            "page": page.info
         });
 
-        Ok(serde_json::to_string_pretty(&output).unwrap())
+        Ok(output)
+    }
+
+    /// find_references tool - every use of a symbol with its position and source line
+    fn tool_find_references(&mut self, args: Option<Value>) -> Result<Value, String> {
+        let args = args.ok_or("Missing arguments")?;
+        let directory = self.get_directory_from_value(&args)?;
+        let symbol = args
+            .get("symbol")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing symbol parameter")?;
+        let include_definition = args
+            .get("include_definition")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
+
+        let graph = self.get_or_load_graph(&directory)?.clone();
+        let generator = ContextGenerator::from_graph(graph);
+        if generator.definitions_of(symbol).is_empty() {
+            return Err(format!("Symbol not found: {}", symbol));
+        }
+        let references = generator.find_references(symbol, include_definition);
+        let references = serde_json::to_value(references).map_err(|e| e.to_string())?;
+        let page = paginate(
+            references.as_array().cloned().unwrap_or_default(),
+            &PageRequest::from_args(&args)?,
+            &[("by_file", "file_path"), ("by_kind", "kind")],
+        );
+
+        Ok(json!({
+            "symbol": symbol,
+            "references": page.items,
+            "page": page.info
+        }))
+    }
+
+    /// rename_symbol tool - preview (or apply) a rename built from the resolved references
+    fn tool_rename_symbol(&mut self, args: Option<Value>) -> Result<Value, String> {
+        let args = args.ok_or("Missing arguments")?;
+        let directory = self.get_directory_from_value(&args)?;
+        let symbol = args
+            .get("symbol")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing symbol parameter")?;
+        let new_name = args
+            .get("new_name")
+            .and_then(|v| v.as_str())
+            .ok_or("Missing new_name parameter")?;
+        let apply = args.get("apply").and_then(|v| v.as_bool()).unwrap_or(false);
+        let create_backup = args
+            .get("create_backup")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+        let force = args.get("force").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        // Positions must match the files on disk, so always start from a fresh scan
+        self.invalidate_cache(&directory);
+        let graph = self.get_or_load_graph(&directory)?.clone();
+        let plan = rename::plan_rename(graph, &directory, symbol, new_name).map_err(|e| e.to_string())?;
+
+        let manager = ChangeManager::from_directory(&directory).map_err(|e| e.to_string())?;
+        let preview = manager.preview_changes(&plan.changes, false).map_err(|e| e.to_string())?;
+        // new_name already defined: the rename would clash, so it is only written when forced
+        if apply && !plan.warnings.is_empty() && !force {
+            return Err(format!(
+                "Not applied: {} (set force to rename anyway)",
+                plan.warnings.join("; ")
+            ));
+        }
+        let applied = if apply {
+            let result = manager
+                .apply_changes(&plan.changes, create_backup)
+                .map_err(|e| e.to_string())?;
+            let files = plan.changes.iter().map(|c| PathBuf::from(&c.file_path)).collect();
+            let refreshed = DaemonClient::connect(&directory).is_some_and(|mut client| client.refresh(files).is_ok());
+            if !refreshed {
+                self.invalidate_cache(&directory);
+            }
+            Some(result)
+        } else {
+            None
+        };
+
+        Ok(json!({
+            "symbol_id": plan.symbol_id,
+            "old_name": plan.old_name,
+            "new_name": plan.new_name,
+            "edits": plan.edits,
+            "unresolved": plan.unresolved,
+            "warnings": plan.warnings,
+            "preview": preview,
+            "applied": applied
+        }))
+    }
+
+    /// go_to_definition tool - by symbol name, or by the position of a use
+    fn tool_go_to_definition(&mut self, args: Option<Value>) -> Result<Value, String> {
+        let args = args.ok_or("Missing arguments")?;
+        let directory = self.get_directory_from_value(&args)?;
+        let symbol = args.get("symbol").and_then(|v| v.as_str());
+        let file_path = args.get("file_path").and_then(|v| v.as_str());
+        let line = args.get("line").and_then(|v| v.as_u64());
+        let column = args.get("column").and_then(|v| v.as_u64());
+
+        let graph = self.get_or_load_graph(&directory)?.clone();
+        let generator = ContextGenerator::from_graph(graph);
+        let definitions = match (symbol, file_path, line, column) {
+            (Some(symbol), _, _, _) => generator.go_to_definition(symbol),
+            (None, Some(file_path), Some(line), Some(column)) => {
+                let path = directory.join(file_path);
+                generator.definition_at(&path, line as usize, column as usize)
+            }
+            _ => return Err("Pass symbol, or file_path with line and column".to_string()),
+        };
+
+        Ok(json!({ "definitions": definitions }))
     }
 }
 
@@ -731,3 +1662,267 @@ impl Default for McpServer {
         Self::new(None)
     }
 }
+
+impl Drop for McpServer {
+    /// A session that ends closes its roots in the shared cache
+    fn drop(&mut self) {
+        self.graphs.close_roots(&self.roots);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{copy_fixture, temp_project, write};
+
+    #[test]
+    fn test_tool_call_progress_and_cancellation() {
+        let temp = temp_project();
+        let root = temp.path();
+        for i in 0..30 {
+            std::fs::write(root.join(format!("m{}.ts", i)), format!("export function f{}() {{}}\n", i)).unwrap();
+        }
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&sent);
+        let mut server = McpServer::new(Some(root.to_path_buf()));
+        server.set_notifier(Arc::new(move |n: &ServerMessage| sink.lock().unwrap().push(n.clone())));
+        server.initialized = true;
+
+        let request: JsonRpcRequest = serde_json::from_value(json!({
+            "jsonrpc": "2.0", "id": 7, "method": "tools/call",
+            "params": { "name": "stats", "arguments": { "directory": root }, "_meta": { "progressToken": "p1" } }
+        }))
+        .unwrap();
+        let response = server.handle_request(request).unwrap();
+        assert!(response.result.is_some());
+
+        let sent = sent.lock().unwrap();
+        let values: Vec<u64> = sent
+            .iter()
+            .filter(|n| n.method() == "notifications/progress")
+            .map(|n| n.params().unwrap())
+            .inspect(|p| assert_eq!(p["progressToken"], "p1"))
+            .map(|p| p["progress"].as_u64().unwrap())
+            .collect();
+        assert!(!values.is_empty());
+        assert!(values.windows(2).all(|w| w[0] < w[1]));
+
+        // Cancellation reaches a running request through its id
+        let in_flight = server.in_flight();
+        let progress = Progress::default();
+        in_flight.start(&json!(7), progress.clone());
+        assert!(!in_flight.cancel(Some(&json!({ "requestId": 8 }))));
+        assert!(in_flight.cancel(Some(&json!({ "requestId": 7, "reason": "user" }))));
+        assert!(progress.is_cancelled());
+    }
+
+    #[test]
+    fn test_tool_calls_run_on_workers() {
+        let temp = temp_project();
+        let root = temp.path();
+        std::fs::write(root.join("a.ts"), "export function a() {}\n").unwrap();
+        let mut server = McpServer::new(Some(root.to_path_buf()));
+        server.set_notifier(Arc::new(|_: &ServerMessage| {}));
+        server.initialized = true;
+
+        let parse = |value: Value| Payload::parse(value.to_string().as_bytes()).unwrap();
+        let call = |id: u64| {
+            json!({
+                "jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": { "name": "stats", "arguments": { "directory": root } }
+            })
+        };
+        let ping = json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" });
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(McpServer::runs_on_worker(&parse(call(1))));
+        assert!(!McpServer::runs_on_worker(&parse(ping.clone())));
+        assert!(!McpServer::runs_on_worker(&parse(json!([call(1), initialized]))));
+
+        // Calls overlap on their workers while the session keeps answering
+        let workers: Vec<_> = (3..5)
+            .map(|id| {
+                let mut worker = server.worker();
+                let payload = parse(call(id));
+                std::thread::spawn(move || worker.handle_payload(payload).unwrap())
+            })
+            .collect();
+        assert_eq!(server.handle_payload(parse(ping)).unwrap()["result"], json!({}));
+        for (id, worker) in (3..5).zip(workers) {
+            let reply = worker.join().unwrap();
+            assert_eq!(reply["id"], id);
+            assert!(reply["result"]["structuredContent"].is_object());
+        }
+    }
+
+    #[test]
+    fn test_roots_are_kept_per_session() {
+        let temp = temp_project();
+        write(temp.path(), "shared/a.ts", "export function a() {}\n");
+        write(temp.path(), "other/b.ts", "export function b() {}\n");
+        let shared = temp.path().join("shared");
+        let other = temp.path().join("other");
+
+        let mut first = McpServer::new(Some(shared.clone()));
+        first.set_notifier(Arc::new(|_: &ServerMessage| {}));
+        let mut second = first.new_session();
+        second.set_notifier(Arc::new(|_: &ServerMessage| {}));
+        first.get_or_load_graph(&shared).unwrap();
+
+        // A roots/list answer only counts for the session that asked
+        first.client_roots = true;
+        first.request_roots();
+        let id = first.pending_roots.clone();
+        let answer = json!({ "roots": [{ "uri": WorkspaceRoot::from_path(&other).uri }] });
+        second.handle_response(JsonRpcResponse::success(id.clone(), answer.clone()));
+        assert_eq!(second.roots[0].path, shared);
+        first.handle_response(JsonRpcResponse::success(id, answer));
+        assert_eq!(first.roots[0].path, other);
+        assert_eq!(second.roots[0].path, shared);
+
+        // The shared graph is dropped only once no session has its root open
+        let cached = |dir: &Path| {
+            let slots = first.graphs.slots.lock().unwrap();
+            slots.get(dir).is_some_and(|slot| slot.lock().unwrap().is_some())
+        };
+        assert!(cached(&shared));
+        drop(second);
+        assert!(!cached(&shared));
+    }
+
+    #[test]
+    fn test_rename_onto_defined_name_needs_force() {
+        let temp = temp_project();
+        let root = temp.path();
+        write(root, "a.ts", "export function a() {}\nexport function b() {}\n");
+        let mut server = McpServer::new(Some(root.to_path_buf()));
+        server.set_notifier(Arc::new(|_: &ServerMessage| {}));
+        server.initialized = true;
+        let mut rename = |force: bool| {
+            let request: JsonRpcRequest = serde_json::from_value(json!({
+                "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": "rename_symbol", "arguments": {
+                    "directory": root, "symbol": "a", "new_name": "b",
+                    "apply": true, "create_backup": false, "force": force
+                } }
+            }))
+            .unwrap();
+            serde_json::to_value(server.handle_request(request).unwrap()).unwrap()["result"].clone()
+        };
+
+        let refused = rename(false);
+        assert_eq!(refused["isError"], true);
+        assert!(refused["content"][0]["text"].as_str().unwrap().contains("already defined"));
+        let source = || std::fs::read_to_string(root.join("a.ts")).unwrap();
+        assert!(source().starts_with("export function a()"));
+
+        let forced = rename(true);
+        assert_eq!(forced["isError"], false);
+        assert_eq!(forced["structuredContent"]["applied"]["success"], true);
+        assert!(source().starts_with("export function b()"));
+    }
+
+    #[test]
+    fn test_scan_pages_cover_every_finding_once() {
+        let temp = temp_project();
+        let root = temp.path();
+        for i in 0..6 {
+            let source: String = (0..4).map(|j| format!("function unused{}_{}() {{}}\n", i, j)).collect();
+            write(root, &format!("m{}.ts", i), &source);
+        }
+        let mut server = McpServer::new(None);
+        server.initialized = true;
+
+        let mut seen: Vec<String> = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let mut arguments = json!({ "directory": root, "max_results": 5 });
+            if let Some(cursor) = &cursor {
+                arguments["cursor"] = json!(cursor);
+            }
+            let request: JsonRpcRequest = serde_json::from_value(json!({
+                "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": "scan", "arguments": arguments }
+            }))
+            .unwrap();
+            let response = serde_json::to_value(server.handle_request(request).unwrap()).unwrap();
+            let result = &response["result"]["structuredContent"];
+            assert_eq!(result["dead_code_count"], 24);
+            for item in result["dead_code"].as_array().unwrap() {
+                seen.push(item["symbol_id"].as_str().unwrap().to_string());
+            }
+            match result["page"]["next_cursor"].as_str() {
+                Some(next) => cursor = Some(next.to_string()),
+                None => break,
+            }
+        }
+
+        let unique: HashSet<&String> = seen.iter().collect();
+        assert_eq!(seen.len(), 24);
+        assert_eq!(unique.len(), 24);
+    }
+
+    #[test]
+    fn test_arguments_validated_and_results_structured() {
+        let fixture = copy_fixture("test_rust_project");
+        let dir = fixture.path();
+        let mut server = McpServer::new(None);
+        server.initialized = true;
+        let mut call = |name: &str, arguments: Value| {
+            let request: JsonRpcRequest = serde_json::from_value(json!({
+                "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+                "params": { "name": name, "arguments": arguments }
+            }))
+            .unwrap();
+            serde_json::to_value(server.handle_request(request).unwrap()).unwrap()
+        };
+
+        let invalid = call("get_dependencies", json!({ "directory": dir, "depth": "2" }));
+        assert_eq!(invalid["error"]["code"], McpError::INVALID_PARAMS);
+        assert_eq!(
+            invalid["error"]["data"]["errors"],
+            json!(["arguments: missing required property `function_name`", "depth: expected integer, got string"])
+        );
+        assert_eq!(call("no_such_tool", json!({}))["error"]["code"], McpError::INVALID_PARAMS);
+
+        let changes = json!([{ "file_path": "sample.rs", "modified_content": "pub fn used_function() {}\n" }]);
+        let calls = [
+            ("scan", json!({ "directory": dir })),
+            ("stats", json!({ "directory": dir })),
+            ("search", json!({ "directory": dir, "query": "used_function" })),
+            ("get_dependencies", json!({ "directory": dir, "function_name": "main" })),
+            ("get_dependents", json!({ "directory": dir, "function_name": "used_function" })),
+            ("get_call_chain", json!({ "directory": dir, "from": "main", "to": "used_function" })),
+            ("get_call_tree", json!({ "directory": dir, "function_name": "main" })),
+            ("find_references", json!({ "directory": dir, "symbol": "used_function", "include_definition": true })),
+            ("go_to_definition", json!({ "directory": dir, "symbol": "used_function" })),
+            ("rename_symbol", json!({ "directory": dir, "symbol": "used_function", "new_name": "kept_function" })),
+            ("validate_changes", json!({ "directory": dir, "changes": changes })),
+            ("preview_changes", json!({ "directory": dir, "changes": changes, "inline": true })),
+            ("parse_llm_edits", json!({ "content": "<<<FILE: a.rs:1-2>>>\nfn a() {}\n<<<END FILE>>>" })),
+        ];
+        let tools = get_tool_definitions();
+        for (name, arguments) in calls {
+            let response = call(name, arguments);
+            let result = &response["result"];
+            assert_eq!(result["isError"], false, "{}: {}", name, result);
+            let structured = &result["structuredContent"];
+            let output_schema = tools.iter().find(|t| t.name == name).unwrap().output_schema.as_ref().unwrap();
+            assert_eq!(schema::validate(output_schema, structured), Vec::<String>::new(), "{}", name);
+            let text: Value = serde_json::from_str(result["content"][0]["text"].as_str().unwrap()).unwrap();
+            assert_eq!(&text, structured);
+        }
+
+        // Plain-text tools declare no output schema and send no structuredContent
+        let context = call("gather_context", json!({ "directory": dir, "entry_point": "main" }));
+        assert!(context["result"].get("structuredContent").is_none());
+
+        // The quality header is part of the budget
+        let full = context["result"]["content"][0]["text"].as_str().unwrap();
+        let budget = count_tokens(full) - 20;
+        let fitted = call("gather_context", json!({ "directory": dir, "entry_point": "main", "max_tokens": budget }));
+        let fitted = fitted["result"]["content"][0]["text"].as_str().unwrap();
+        assert!(fitted.contains("// Token budget:"));
+        assert!(count_tokens(fitted) <= budget, "{} > {}", count_tokens(fitted), budget);
+    }
+}
@@ -0,0 +1,26 @@
+// Token counting for context budgets
+// cl100k_base（GPT-4 系）の BPE で実際にエンコードして数える
+
+use tiktoken_rs::cl100k_base_singleton;
+
+/// Number of tokens the cl100k_base BPE produces for `text`.
+/// Special-token markers such as `<|endoftext|>` inside source files count as plain text.
+pub fn count_tokens(text: &str) -> usize {
+    cl100k_base_singleton().encode_ordinary(text).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_counts_cl100k_tokens() {
+        // The example from the tiktoken README
+        assert_eq!(cl100k_base_singleton().encode_ordinary("tiktoken is great!"), vec![83, 1609, 5963, 374, 2294, 0]);
+        assert_eq!(count_tokens("tiktoken is great!"), 6);
+        // "fn" " main" "()" " {}"
+        assert_eq!(count_tokens("fn main() {}"), 4);
+        assert_eq!(count_tokens(""), 0);
+        assert!(count_tokens("<|endoftext|>") > 1);
+    }
+}
@@ -2,11 +2,11 @@
 // index-chanが提供するツール群
 
 use crate::mcp::protocol::ToolDefinition;
-use serde_json::json;
+use serde_json::{json, Value};
 
 /// Get all available tool definitions
 pub fn get_tool_definitions() -> Vec<ToolDefinition> {
-    vec![
+    let tools = vec![
         // Scan tool
         ToolDefinition {
             name: "scan".to_string(),
@@ -17,10 +17,26 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     "directory": {
                         "type": "string",
                         "description": "Target directory to scan"
+                    },
+                    "package": {
+                        "type": "string",
+                        "description": "Only report dead code in this workspace package (package.json / Cargo.toml name)"
                     }
                 },
                 "required": ["directory"]
             }),
+            output_schema: Some(object(json!({
+                "total_functions": { "type": "integer" },
+                "dead_code_count": { "type": "integer" },
+                "dead_code": array_of(object(json!({
+                    "name": { "type": "string" },
+                    "symbol_id": { "type": "string" },
+                    "package": { "type": "string" },
+                    "file": { "type": "string" },
+                    "line": { "type": "integer" },
+                    "safety": { "type": "string", "enum": ["DefinitelySafe", "ProbablySafe", "NeedsReview"] }
+                })))
+            }))),
         },
         // Search tool
         ToolDefinition {
@@ -40,11 +56,13 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     "top_k": {
                         "type": "integer",
                         "description": "Number of results to return",
+                        "minimum": 1,
                         "default": 10
                     }
                 },
                 "required": ["query"]
             }),
+            output_schema: Some(search_output_schema()),
         },
         // Stats tool
         ToolDefinition {
@@ -60,6 +78,29 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                 },
                 "required": ["directory"]
             }),
+            output_schema: Some(object(json!({
+                "directory": { "type": "string" },
+                "total_nodes": { "type": "integer" },
+                "total_edges": { "type": "integer" },
+                "dead_code_count": { "type": "integer" },
+                "files": { "type": "integer" },
+                "edge_types": { "type": "object", "additionalProperties": { "type": "integer" } },
+                "semantic_relations": array_of(object(json!({
+                    "type": { "type": "string" },
+                    "count": { "type": "integer" },
+                    "weight": { "type": "number" }
+                }))),
+                "packages": array_of(object(json!({
+                    "name": { "type": "string" },
+                    "nodes": { "type": "integer" },
+                    "dead_code_count": { "type": "integer" }
+                }))),
+                "package_dependencies": array_of(object(json!({
+                    "from": { "type": "string" },
+                    "to": { "type": "string" },
+                    "edges": { "type": "integer" }
+                })))
+            }))),
         },
         // gather_context tool (Phase 6 core feature)
         ToolDefinition {
@@ -75,15 +116,21 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     },
                     "entry_point": {
                         "type": "string",
-                        "description": "Function name to start from"
+                        "description": "Function name or symbol id (e.g. rs:src/scanner.rs#Scanner.scan_file) to start from"
                     },
                     "query": {
                         "type": "string",
-                        "description": "Natural language query to find relevant code"
+                        "description": "Natural language query (instead of entry_point): the best matching functions are expanded with their dependencies"
+                    },
+                    "semantic": {
+                        "type": "boolean",
+                        "description": "Match the query with embeddings instead of function names (requires semantic-search feature)",
+                        "default": false
                     },
                     "depth": {
                         "type": "integer",
                         "description": "Dependency traversal depth",
+                        "minimum": 0,
                         "default": 2
                     },
                     "mode": {
@@ -97,10 +144,17 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                         "enum": ["standard", "llm_edit"],
                         "description": "Output format: standard (comments) or llm_edit (<<<FILE>>> markers for batch editing)",
                         "default": "standard"
+                    },
+                    "max_tokens": {
+                        "type": "integer",
+                        "description": "Token budget for the context: the least relevant dependencies are reduced to signatures, then left out",
+                        "minimum": 1
                     }
                 },
                 "required": ["directory"]
             }),
+            // Plain text: source code with a quality header
+            output_schema: None,
         },
         // get_dependencies tool
         ToolDefinition {
@@ -115,16 +169,21 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     },
                     "function_name": {
                         "type": "string",
-                        "description": "Function name to analyze"
+                        "description": "Function name or symbol id to analyze"
                     },
                     "depth": {
                         "type": "integer",
                         "description": "Traversal depth",
+                        "minimum": 0,
                         "default": 1
                     }
                 },
                 "required": ["directory", "function_name"]
             }),
+            output_schema: Some(object(json!({
+                "function": { "type": "string" },
+                "dependencies": array_of(location_schema(json!({})))
+            }))),
         },
         // get_dependents tool
         ToolDefinition {
@@ -139,16 +198,21 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     },
                     "function_name": {
                         "type": "string",
-                        "description": "Function name to analyze"
+                        "description": "Function name or symbol id to analyze"
                     },
                     "depth": {
                         "type": "integer",
                         "description": "Traversal depth",
+                        "minimum": 0,
                         "default": 1
                     }
                 },
                 "required": ["directory", "function_name"]
             }),
+            output_schema: Some(object(json!({
+                "function": { "type": "string" },
+                "dependents": array_of(location_schema(json!({})))
+            }))),
         },
         // validate_changes tool (Phase 6 Week 3)
         ToolDefinition {
@@ -181,11 +245,13 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                                 },
                                 "start_line": {
                                     "type": "integer",
-                                    "description": "Start line for partial update (optional)"
+                                    "description": "Start line for partial update (optional)",
+                                    "minimum": 1
                                 },
                                 "end_line": {
                                     "type": "integer",
-                                    "description": "End line for partial update (optional)"
+                                    "description": "End line for partial update (optional)",
+                                    "minimum": 1
                                 }
                             },
                             "required": ["file_path", "modified_content"]
@@ -194,6 +260,31 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                 },
                 "required": ["directory", "changes"]
             }),
+            output_schema: Some(object(json!({
+                "status": { "type": "string", "enum": ["Ok", "Warning", "Error"] },
+                "warnings": array_of(json!({ "type": "string" })),
+                "errors": array_of(json!({ "type": "string" })),
+                "affected_files": array_of(json!({ "type": "string" })),
+                "affected_functions": array_of(json!({ "type": "string" })),
+                "new_functions": array_of(json!({ "type": "string" })),
+                "import_issues": array_of(object(json!({
+                    "file": { "type": "string" },
+                    "line": { "type": "integer" },
+                    "import_path": { "type": "string" },
+                    "issue": { "type": "string" }
+                }))),
+                "syntax_errors": array_of(object(json!({
+                    "file": { "type": "string" },
+                    "line": { "type": "integer" },
+                    "column": { "type": "integer" },
+                    "message": { "type": "string" }
+                }))),
+                "removed_symbols": array_of(object(json!({
+                    "file": { "type": "string" },
+                    "name": { "type": "string" },
+                    "dependents": array_of(json!({ "type": "string" }))
+                })))
+            }))),
         },
         // preview_changes tool
         ToolDefinition {
@@ -227,15 +318,25 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                             },
                             "required": ["file_path", "modified_content"]
                         }
+                    },
+                    "inline": {
+                        "type": "boolean",
+                        "description": "Also return word-level changes per line (for rendering inline diffs)",
+                        "default": false
                     }
                 },
                 "required": ["directory", "changes"]
             }),
+            output_schema: Some(object(json!({
+                "diffs": array_of(file_diff_schema()),
+                "total_additions": { "type": "integer" },
+                "total_deletions": { "type": "integer" }
+            }))),
         },
         // apply_changes tool
         ToolDefinition {
             name: "apply_changes".to_string(),
-            description: "Apply validated changes to files (creates backup by default)".to_string(),
+            description: "Apply validated changes to files, all or nothing (creates backup by default). A change whose base_hash no longer matches the file is refused as a conflict".to_string(),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -259,11 +360,17 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                                 },
                                 "start_line": {
                                     "type": "integer",
-                                    "description": "Start line for partial update (optional)"
+                                    "description": "Start line for partial update (optional)",
+                                    "minimum": 1
                                 },
                                 "end_line": {
                                     "type": "integer",
-                                    "description": "End line for partial update (optional)"
+                                    "description": "End line for partial update (optional)",
+                                    "minimum": 1
+                                },
+                                "base_hash": {
+                                    "type": "string",
+                                    "description": "SHA-256 of the file the edit is based on, as returned by preview_changes (optional)"
                                 }
                             },
                             "required": ["file_path", "modified_content"]
@@ -277,6 +384,19 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                 },
                 "required": ["directory", "changes"]
             }),
+            output_schema: Some(object(json!({
+                "success": { "type": "boolean" },
+                "applied_files": array_of(json!({ "type": "string" })),
+                "failed_files": array_of(object(json!({
+                    "file": { "type": "string" },
+                    "error": { "type": "string" }
+                }))),
+                "backup_dir": { "type": ["string", "null"] },
+                // A write failed and the files already written were restored
+                "rolled_back": { "type": "boolean" },
+                // Files the rollback could not restore
+                "rollback_failed": array_of(json!({ "type": "string" }))
+            }))),
         },
         // search_with_graph tool (Phase 7 GraphRAG)
         ToolDefinition {
@@ -298,11 +418,13 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     "top_k": {
                         "type": "integer",
                         "description": "Number of initial matches to find",
+                        "minimum": 1,
                         "default": 3
                     },
                     "graph_depth": {
                         "type": "integer",
                         "description": "How many dependency hops to traverse",
+                        "minimum": 0,
                         "default": 2
                     },
                     "semantic": {
@@ -318,6 +440,7 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                 },
                 "required": ["directory", "query"]
             }),
+            output_schema: Some(search_output_schema()),
         },
         // parse_llm_edits tool - Parse LLM output back into file changes
         ToolDefinition {
@@ -333,6 +456,14 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                 },
                 "required": ["content"]
             }),
+            output_schema: Some(object(json!({
+                "changes": array_of(object(json!({
+                    "file_path": { "type": "string" },
+                    "start_line": { "type": "integer" },
+                    "end_line": { "type": "integer" },
+                    "modified_content": { "type": "string" }
+                })))
+            }))),
         },
         // get_call_chain tool - Find path between two functions
         ToolDefinition {
@@ -347,20 +478,28 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     },
                     "from": {
                         "type": "string",
-                        "description": "Source function name"
+                        "description": "Source function name or symbol id"
                     },
                     "to": {
                         "type": "string",
-                        "description": "Target function name"
+                        "description": "Target function name or symbol id"
                     },
                     "max_depth": {
                         "type": "integer",
                         "description": "Maximum search depth",
+                        "minimum": 1,
                         "default": 10
                     }
                 },
                 "required": ["directory", "from", "to"]
             }),
+            output_schema: Some(object(json!({
+                "from": { "type": "string" },
+                "to": { "type": "string" },
+                "found": { "type": "boolean" },
+                "chain": array_of(location_schema(json!({}))),
+                "visual": { "type": "string" }
+            }))),
         },
         // get_call_tree tool - Visualize call hierarchy
         ToolDefinition {
@@ -375,11 +514,12 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                     },
                     "function_name": {
                         "type": "string",
-                        "description": "Function to analyze"
+                        "description": "Function name or symbol id to analyze"
                     },
                     "depth": {
                         "type": "integer",
                         "description": "How many levels to traverse",
+                        "minimum": 0,
                         "default": 3
                     },
                     "direction": {
@@ -391,6 +531,329 @@ pub fn get_tool_definitions() -> Vec<ToolDefinition> {
                 },
                 "required": ["directory", "function_name"]
             }),
+            output_schema: Some(object(json!({
+                "function": { "type": "string" },
+                "direction": { "type": "string", "enum": ["callee", "caller"] },
+                "depth": { "type": "integer" },
+                "nodes": array_of(location_schema(json!({ "depth": { "type": "integer" } }))),
+                "visual": { "type": "string" }
+            }))),
+        },
+        // find_references tool - every use of a symbol with its exact position
+        ToolDefinition {
+            name: "find_references".to_string(),
+            description: "Find every call, import and reference of a symbol with file, line, column and the source line".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "directory": {
+                        "type": "string",
+                        "description": "Project directory"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "Function name or symbol id (a plain name covers every definition with that name)"
+                    },
+                    "include_definition": {
+                        "type": "boolean",
+                        "description": "Also list the definitions themselves",
+                        "default": false
+                    }
+                },
+                "required": ["directory", "symbol"]
+            }),
+            output_schema: Some(object(json!({
+                "symbol": { "type": "string" },
+                "references": array_of(symbol_location_schema())
+            }))),
+        },
+        // go_to_definition tool - where a symbol or the identifier at a position is defined
+        ToolDefinition {
+            name: "go_to_definition".to_string(),
+            description: "Find where a symbol is defined, by name or by the position of a use (file_path, line, column)".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "directory": {
+                        "type": "string",
+                        "description": "Project directory"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "Function name or symbol id"
+                    },
+                    "file_path": {
+                        "type": "string",
+                        "description": "File containing the use (relative to the project directory), instead of symbol"
+                    },
+                    "line": {
+                        "type": "integer",
+                        "description": "1-based line of the use",
+                        "minimum": 1
+                    },
+                    "column": {
+                        "type": "integer",
+                        "description": "1-based column (in characters) anywhere on the identifier",
+                        "minimum": 1
+                    }
+                },
+                "required": ["directory"]
+            }),
+            output_schema: Some(object(json!({
+                "definitions": array_of(symbol_location_schema())
+            }))),
         },
-    ]
+        // rename_symbol tool - rewrite a definition and its resolved uses
+        ToolDefinition {
+            name: "rename_symbol".to_string(),
+            description: "Rename a symbol at its definition, call sites and import specifiers (from resolved references, not text search). Returns a preview; set apply to write the files with a backup".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "directory": {
+                        "type": "string",
+                        "description": "Project directory"
+                    },
+                    "symbol": {
+                        "type": "string",
+                        "description": "Function name or symbol id (must name exactly one definition)"
+                    },
+                    "new_name": {
+                        "type": "string",
+                        "description": "New identifier"
+                    },
+                    "apply": {
+                        "type": "boolean",
+                        "description": "Write the changes (otherwise only preview them)",
+                        "default": false
+                    },
+                    "create_backup": {
+                        "type": "boolean",
+                        "description": "Create backup before applying changes",
+                        "default": true
+                    },
+                    "force": {
+                        "type": "boolean",
+                        "description": "Apply even if new_name is already defined (see warnings)",
+                        "default": false
+                    }
+                },
+                "required": ["directory", "symbol", "new_name"]
+            }),
+            output_schema: Some(object(json!({
+                "symbol_id": { "type": "string" },
+                "old_name": { "type": "string" },
+                "new_name": { "type": "string" },
+                "edits": array_of(rename_edit_schema()),
+                // Whole-word mentions no reference points at (comments, strings, dynamic calls)
+                "unresolved": array_of(rename_edit_schema()),
+                "warnings": array_of(json!({ "type": "string" })),
+                "preview": object(json!({
+                    "diffs": array_of(file_diff_schema()),
+                    "total_additions": { "type": "integer" },
+                    "total_deletions": { "type": "integer" }
+                })),
+                "applied": {
+                    "type": ["object", "null"],
+                    "properties": {
+                        "success": { "type": "boolean" },
+                        "applied_files": array_of(json!({ "type": "string" })),
+                        "backup_dir": { "type": ["string", "null"] },
+                        "rolled_back": { "type": "boolean" },
+                        "rollback_failed": array_of(json!({ "type": "string" }))
+                    }
+                }
+            }))),
+        },
+    ];
+    tools.into_iter().map(with_root_argument).map(with_paging).collect()
+}
+
+/// Tools whose main list is cut into pages (the `page` object in their output)
+const PAGED_TOOLS: &[&str] = &[
+    "scan",
+    "search",
+    "search_with_graph",
+    "get_dependencies",
+    "get_dependents",
+    "get_call_tree",
+    "find_references",
+];
+
+/// Add `cursor`, `max_results` and `max_tokens` to list-returning tools, and `page` to their output
+fn with_paging(mut tool: ToolDefinition) -> ToolDefinition {
+    if !PAGED_TOOLS.contains(&tool.name.as_str()) {
+        return tool;
+    }
+    if let Some(properties) = tool.input_schema.get_mut("properties").and_then(Value::as_object_mut) {
+        properties.insert(
+            "cursor".to_string(),
+            json!({
+                "type": "string",
+                "description": "page.next_cursor of the previous call, to get the next page (keep the other arguments unchanged)"
+            }),
+        );
+        properties.insert(
+            "max_results".to_string(),
+            json!({ "type": "integer", "description": "Maximum number of list entries to return", "minimum": 1 }),
+        );
+        properties.insert(
+            "max_tokens".to_string(),
+            json!({ "type": "integer", "description": "Approximate token budget for the returned entries", "minimum": 1 }),
+        );
+    }
+
+    if let Some(output) = tool.output_schema.as_mut() {
+        let page = object(json!({
+            "offset": { "type": "integer" },
+            "returned": { "type": "integer" },
+            "total": { "type": "integer" },
+            "next_cursor": { "type": ["string", "null"] },
+            // Counts over the whole list, sent when this page leaves entries out
+            "summary": {
+                "type": ["object", "null"],
+                "additionalProperties": { "type": "object", "additionalProperties": { "type": "integer" } }
+            }
+        }));
+        if let Some(properties) = output.get_mut("properties").and_then(Value::as_object_mut) {
+            properties.insert("page".to_string(), page);
+        }
+        if let Some(required) = output.get_mut("required").and_then(Value::as_array_mut) {
+            required.push(json!("page"));
+        }
+    }
+    tool
+}
+
+/// Tools that take a `directory` can name a workspace root instead, or omit both
+/// when the client shared a single root
+fn with_root_argument(mut tool: ToolDefinition) -> ToolDefinition {
+    let Some(schema) = tool.input_schema.as_object_mut() else {
+        return tool;
+    };
+    let takes_directory = schema
+        .get("properties")
+        .and_then(|p| p.get("directory"))
+        .is_some();
+    if !takes_directory {
+        return tool;
+    }
+
+    let description = if matches!(tool.name.as_str(), "search" | "search_with_graph") {
+        "Workspace root (name or file:// URI from roots/list) to use instead of directory; \"*\" searches every root"
+    } else {
+        "Workspace root (name or file:// URI from roots/list) to use instead of directory"
+    };
+    if let Some(properties) = schema.get_mut("properties").and_then(Value::as_object_mut) {
+        properties.insert("root".to_string(), json!({ "type": "string", "description": description }));
+    }
+    if let Some(required) = schema.get_mut("required").and_then(Value::as_array_mut) {
+        required.retain(|name| name != "directory");
+    }
+    tool
+}
+
+/// Object schema in which every listed property is required
+fn object(properties: Value) -> Value {
+    let required: Vec<String> = properties
+        .as_object()
+        .map(|p| p.keys().cloned().collect())
+        .unwrap_or_default();
+    json!({ "type": "object", "properties": properties, "required": required })
+}
+
+fn array_of(items: Value) -> Value {
+    json!({ "type": "array", "items": items })
+}
+
+/// A function and where it is defined, plus `extra` properties
+fn location_schema(extra: Value) -> Value {
+    let mut properties = json!({
+        "name": { "type": "string" },
+        "symbol_id": { "type": "string" },
+        "file_path": { "type": "string" },
+        "line": { "type": "integer" },
+        "node_type": { "type": "string" }
+    });
+    if let (Some(properties), Some(extra)) = (properties.as_object_mut(), extra.as_object()) {
+        properties.extend(extra.clone());
+    }
+    object(properties)
+}
+
+/// A definition or use found by `find_references` and `go_to_definition`
+fn symbol_location_schema() -> Value {
+    object(json!({
+        "kind": { "type": "string", "enum": ["definition", "call", "reference", "instantiation", "import"] },
+        "name": { "type": "string" },
+        "symbol_id": { "type": "string" },
+        "file_path": { "type": "string" },
+        "line": { "type": "integer" },
+        "column": { "type": "integer" },
+        "source_line": { "type": "string" },
+        "container": { "type": ["string", "null"] }
+    }))
+}
+
+/// One file of `preview_changes`; `inline` is only sent when asked for
+fn file_diff_schema() -> Value {
+    let mut schema = object(json!({
+        "file_path": { "type": "string" },
+        "diff": { "type": "string" },
+        "additions": { "type": "integer" },
+        "deletions": { "type": "integer" },
+        "base_hash": { "type": ["string", "null"] }
+    }));
+    schema["properties"]["inline"] = array_of(object(json!({
+        "old_line": { "type": ["integer", "null"] },
+        "new_line": { "type": ["integer", "null"] },
+        "segments": array_of(object(json!({
+            "kind": { "type": "string", "enum": ["equal", "delete", "insert"] },
+            "text": { "type": "string" }
+        })))
+    })));
+    schema
+}
+
+/// A position `rename_symbol` rewrites or reports
+fn rename_edit_schema() -> Value {
+    object(json!({
+        "kind": { "type": "string" },
+        "file_path": { "type": "string" },
+        "line": { "type": "integer" },
+        "column": { "type": "integer" },
+        "source_line": { "type": "string" }
+    }))
+}
+
+/// Results of `search` and `search_with_graph`
+fn search_output_schema() -> Value {
+    object(json!({
+        "query": { "type": "string" },
+        "graph_depth": { "type": "integer" },
+        "semantic": { "type": "boolean" },
+        "results": array_of(object(json!({
+            "root": { "type": ["string", "null"] },
+            "name": { "type": "string" },
+            "file": { "type": "string" },
+            "score": { "type": "number" },
+            "depth": { "type": "integer" },
+            "line_range": array_of(json!({ "type": "integer" })),
+            "match_type": { "type": "string" },
+            "explanation": object(json!({
+                "trace": array_of(object(json!({
+                    "node": { "type": "string" },
+                    "node_type": { "type": "string" },
+                    "reason": { "type": "string" },
+                    "edge": { "type": ["string", "null"] },
+                    "direction": { "type": ["string", "null"] }
+                }))),
+                "score_details": object(json!({
+                    "base_score": { "type": "number" },
+                    "decay_factor": { "type": "number" },
+                    "depth": { "type": "integer" }
+                }))
+            }))
+        })))
+    }))
 }
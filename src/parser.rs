@@ -1,9 +1,12 @@
 use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 use tree_sitter::{Node, Parser};
 
+use crate::graph::NodeType;
+
 // Language enum for multi-language support
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum Language {
     TypeScript,
     Rust,
@@ -12,11 +15,20 @@ pub enum Language {
 impl Language {
     pub fn from_extension(ext: &str) -> Option<Self> {
         match ext {
-            "ts" | "tsx" => Some(Language::TypeScript),
+            // Vue/Svelte components: the <script> blocks are parsed as TypeScript
+            "ts" | "tsx" | "vue" | "svelte" => Some(Language::TypeScript),
             "rs" => Some(Language::Rust),
             _ => None,
         }
     }
+
+    /// Short tag used as the language prefix of symbol ids
+    pub fn tag(self) -> &'static str {
+        match self {
+            Language::TypeScript => "ts",
+            Language::Rust => "rs",
+        }
+    }
 }
 
 // Unified parser for multiple languages
@@ -41,16 +53,35 @@ impl CodeParser {
         Ok(Self { parser, language })
     }
 
+    /// Parser for the file at `path`, or None for other languages.
+    /// Same as `new`, except that `.tsx` files get the TSX grammar so JSX parses.
+    pub fn for_path(path: &Path) -> Result<Option<Self>> {
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        let Some(language) = Language::from_extension(ext) else {
+            return Ok(None);
+        };
+        let mut parser = Self::new(language)?;
+        if ext == "tsx" {
+            parser
+                .parser
+                .set_language(&tree_sitter_typescript::LANGUAGE_TSX.into())
+                .context("Failed to set language")?;
+        }
+        Ok(Some(parser))
+    }
+
     pub fn parse_file(&mut self, path: &Path) -> Result<tree_sitter::Tree> {
         let source_code = std::fs::read_to_string(path)
             .context(format!("Failed to read file: {}", path.display()))?;
 
-        let tree = self
-            .parser
-            .parse(&source_code, None)
-            .context("Failed to parse file")?;
+        self.parse_source(&source_code)
+    }
 
-        Ok(tree)
+    /// Parse source code that has already been read (or preprocessed)
+    pub fn parse_source(&mut self, source: &str) -> Result<tree_sitter::Tree> {
+        self.parser
+            .parse(source, None)
+            .context("Failed to parse file")
     }
 
     pub fn extract_functions(&self, tree: &tree_sitter::Tree, source: &str) -> Vec<FunctionInfo> {
@@ -93,15 +124,62 @@ impl CodeParser {
         let end_line = node.end_position().row + 1;
         let is_exported = self.is_exported(node);
         let signature = self.extract_signature(node, source);
+        let scope = self.extract_scope(node, source);
+        let node_type = if self.is_method(node) {
+            NodeType::Method
+        } else {
+            NodeType::Function
+        };
 
         Some(FunctionInfo {
             name,
+            node_type,
             line_range: (start_line, end_line),
             is_exported,
             signature,
+            scope,
         })
     }
 
+    /// Class methods (TS) and functions declared directly in an `impl`/`trait` body (Rust)
+    fn is_method(&self, node: Node) -> bool {
+        match self.language {
+            Language::TypeScript => node.kind() == "method_definition",
+            Language::Rust => node
+                .parent()
+                .filter(|parent| parent.kind() == "declaration_list")
+                .and_then(|list| list.parent())
+                .is_some_and(|owner| matches!(owner.kind(), "impl_item" | "trait_item")),
+        }
+    }
+
+    /// Collect the names of enclosing modules / impl blocks / classes
+    fn extract_scope(&self, node: Node, source: &str) -> Vec<String> {
+        let mut scope = Vec::new();
+        let mut current = node.parent();
+
+        while let Some(parent) = current {
+            let name_node = match (self.language, parent.kind()) {
+                (Language::Rust, "mod_item" | "trait_item") => parent.child_by_field_name("name"),
+                (Language::Rust, "impl_item") => parent.child_by_field_name("type"),
+                (Language::TypeScript, "class_declaration" | "abstract_class_declaration" | "class") => {
+                    parent.child_by_field_name("name")
+                }
+                _ => None,
+            };
+            if let Some(text) = name_node.and_then(|n| n.utf8_text(source.as_bytes()).ok()) {
+                // impl<T> Foo<T> -> Foo
+                let name = text.split('<').next().unwrap_or(text).trim();
+                let name = name.rsplit("::").next().unwrap_or(name);
+                scope.push(name.to_string());
+            }
+            current = parent.parent();
+        }
+
+        scope.reverse();
+        scope
+    }
+
     /// Extract function signature (everything before the body block)
     fn extract_signature(&self, node: Node, source: &str) -> String {
         let node_text = node.utf8_text(source.as_bytes()).unwrap_or("");
@@ -255,10 +333,13 @@ impl CodeParser {
                             return Some(CallInfo {
                                 caller_line,
                                 callee_name: callee_name.to_string(),
+                                path: None,
+                                position: name_position(child, source),
                             });
                         }
                     } else if child.kind() == "member_expression" {
                         // For member expressions like obj.method(), get just the method name
+                        let property = child.child_by_field_name("property").unwrap_or(child);
                         if let Ok(full_name) = child.utf8_text(source.as_bytes()) {
                             let name = full_name.split('.').last().unwrap_or(full_name);
                             // Skip common built-in methods
@@ -277,6 +358,8 @@ impl CodeParser {
                                 return Some(CallInfo {
                                     caller_line,
                                     callee_name: name.to_string(),
+                                    path: None,
+                                    position: name_position(property, source),
                                 });
                             }
                         }
@@ -293,15 +376,20 @@ impl CodeParser {
                             return Some(CallInfo {
                                 caller_line,
                                 callee_name: callee_name.to_string(),
+                                path: None,
+                                position: name_position(child, source),
                             });
                         }
                     } else if child.kind() == "field_expression" {
                         // For field expressions like obj.method()
                         if let Ok(full_name) = child.utf8_text(source.as_bytes()) {
                             let name = full_name.split('.').last().unwrap_or(full_name);
+                            let field = child.child_by_field_name("field").unwrap_or(child);
                             return Some(CallInfo {
                                 caller_line,
                                 callee_name: name.to_string(),
+                                path: None,
+                                position: name_position(field, source),
                             });
                         }
                     }
@@ -326,16 +414,20 @@ impl CodeParser {
                         calls.push(CallInfo {
                             caller_line,
                             callee_name: callee_name.to_string(),
+                            path: None,
+                            position: name_position(child, source),
                         });
                     }
                 }
                 // フィールド式: self.graph.method() - チェーン全体を解析
                 "field_expression" => {
                     let chain = self.extract_method_chain(child, source);
-                    for method_name in chain {
+                    for (method_name, position) in chain {
                         calls.push(CallInfo {
                             caller_line,
                             callee_name: method_name,
+                            path: None,
+                            position,
                         });
                     }
                 }
@@ -344,9 +436,12 @@ impl CodeParser {
                     if let Ok(full_path) = child.utf8_text(source.as_bytes()) {
                         // フルパスから最後の識別子を取得
                         if let Some(name) = full_path.rsplit("::").next() {
+                            let name_node = child.child_by_field_name("name").unwrap_or(child);
                             calls.push(CallInfo {
                                 caller_line,
                                 callee_name: name.to_string(),
+                                path: Some(full_path.to_string()),
+                                position: name_position(name_node, source),
                             });
                         }
                         // 型名も記録 (Vec, Result など)
@@ -355,6 +450,8 @@ impl CodeParser {
                                 calls.push(CallInfo {
                                     caller_line,
                                     callee_name: type_name.to_string(),
+                                    path: None,
+                                    position: name_position(child, source),
                                 });
                             }
                         }
@@ -368,18 +465,23 @@ impl CodeParser {
                         if inner.kind() == "identifier" || inner.kind() == "scoped_identifier" {
                             if let Ok(name) = inner.utf8_text(source.as_bytes()) {
                                 let final_name = name.rsplit("::").next().unwrap_or(name);
+                                let name_node = inner.child_by_field_name("name").unwrap_or(inner);
                                 calls.push(CallInfo {
                                     caller_line,
                                     callee_name: final_name.to_string(),
+                                    path: None,
+                                    position: name_position(name_node, source),
                                 });
                             }
                             break;
                         } else if inner.kind() == "field_expression" {
                             let chain = self.extract_method_chain(inner, source);
-                            for method_name in chain {
+                            for (method_name, position) in chain {
                                 calls.push(CallInfo {
                                     caller_line,
                                     callee_name: method_name,
+                                    path: None,
+                                    position,
                                 });
                             }
                             break;
@@ -404,9 +506,12 @@ impl CodeParser {
                 if let Ok(name) = child.utf8_text(source.as_bytes()) {
                     // パス付きマクロ (std::println!) の場合は最後の部分を使用
                     let macro_name = name.rsplit("::").next().unwrap_or(name);
+                    let name_node = child.child_by_field_name("name").unwrap_or(child);
                     return Some(CallInfo {
                         caller_line,
                         callee_name: macro_name.to_string(),
+                        path: None,
+                        position: name_position(name_node, source),
                     });
                 }
             }
@@ -415,7 +520,8 @@ impl CodeParser {
     }
 
     /// メソッドチェーンを再帰的に解析: self.graph.traverse_from() → ["traverse_from", "graph"]
-    fn extract_method_chain(&self, node: Node, source: &str) -> Vec<String> {
+    /// (各名前の位置付き)
+    fn extract_method_chain(&self, node: Node, source: &str) -> Vec<(String, (usize, usize))> {
         let mut methods = Vec::new();
 
         if node.kind() == "field_expression" {
@@ -424,7 +530,7 @@ impl CodeParser {
             for child in node.children(&mut cursor) {
                 if child.kind() == "field_identifier" {
                     if let Ok(name) = child.utf8_text(source.as_bytes()) {
-                        methods.push(name.to_string());
+                        methods.push((name.to_string(), name_position(child, source)));
                     }
                 } else if child.kind() == "field_expression" || child.kind() == "call_expression" {
                     // 再帰的にチェーンを辿る
@@ -445,24 +551,86 @@ impl CodeParser {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionInfo {
     pub name: String,
+    pub node_type: NodeType,
     pub line_range: (usize, usize),
     pub is_exported: bool,
     pub signature: String,
+    /// Enclosing containers inside the file, outermost first
+    /// (Rust: inline `mod` blocks and the `impl`/`trait` type, TypeScript: class)
+    pub scope: Vec<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImportInfo {
     pub module_name: String,
     pub aliases: Vec<String>,
+    /// Position of each alias, in the same order
+    pub positions: Vec<(usize, usize)>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CallInfo {
     pub caller_line: usize,
     pub callee_name: String,
+    /// Full path for scoped calls (`crate::foo::bar`, `Type::new`)
+    pub path: Option<String>,
+    /// 1-based line and column (in characters) of the callee name at the call site
+    pub position: (usize, usize),
+}
+
+/// A place where tree-sitter had to recover from invalid syntax
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyntaxError {
+    /// 1-based
+    pub line: usize,
+    /// 1-based, in characters
+    pub column: usize,
+    pub message: String,
+}
+
+/// Every `ERROR` and `MISSING` node in `tree` (an ERROR node is reported once, not its insides)
+pub fn syntax_errors(tree: &tree_sitter::Tree, source: &str) -> Vec<SyntaxError> {
+    fn visit(node: Node, source: &str, errors: &mut Vec<SyntaxError>) {
+        if node.is_missing() {
+            let (line, column) = name_position(node, source);
+            errors.push(SyntaxError { line, column, message: format!("missing `{}`", node.kind()) });
+        } else if node.is_error() {
+            let (line, column) = name_position(node, source);
+            let text = node.utf8_text(source.as_bytes()).unwrap_or("");
+            let first_line = text.lines().next().unwrap_or("").trim();
+            let snippet: String = first_line.chars().take(40).collect();
+            let message = if snippet.is_empty() {
+                "unexpected end of input".to_string()
+            } else if snippet.len() < first_line.len() {
+                format!("unexpected `{}...`", snippet)
+            } else {
+                format!("unexpected `{}`", snippet)
+            };
+            errors.push(SyntaxError { line, column, message });
+        } else if node.has_error() {
+            let mut cursor = node.walk();
+            for child in node.children(&mut cursor) {
+                visit(child, source, errors);
+            }
+        }
+    }
+
+    let mut errors = Vec::new();
+    visit(tree.root_node(), source, &mut errors);
+    errors
+}
+
+/// 1-based line and column (in characters, not bytes) where `node` starts
+pub fn name_position(node: Node, source: &str) -> (usize, usize) {
+    let start = node.start_position();
+    let line_start = node.start_byte() - start.column;
+    let column = source
+        .get(line_start..node.start_byte())
+        .map_or(start.column, |prefix| prefix.chars().count());
+    (start.row + 1, column + 1)
 }
 
 impl CodeParser {
@@ -501,6 +669,7 @@ impl CodeParser {
                 // import * as Foo from "./bar";
                 let mut module_name = String::new();
                 let mut aliases = Vec::new();
+                let mut positions = Vec::new();
 
                 let mut cursor = node.walk();
                 for child in node.children(&mut cursor) {
@@ -517,6 +686,7 @@ impl CodeParser {
                                 // Default import
                                 if let Ok(text) = clause_child.utf8_text(source.as_bytes()) {
                                     aliases.push(text.to_string());
+                                    positions.push(name_position(clause_child, source));
                                 }
                             } else if clause_child.kind() == "named_imports" {
                                 // { Foo, Bar as Baz }
@@ -530,6 +700,7 @@ impl CodeParser {
                                                     spec_child.utf8_text(source.as_bytes())
                                                 {
                                                     aliases.push(text.to_string());
+                                                    positions.push(name_position(spec_child, source));
                                                     // For simple cases, we take both name and alias as separate entries to be safe
                                                     // Ideal would be to track alias -> original connection
                                                 }
@@ -544,6 +715,7 @@ impl CodeParser {
                                     if ns_child.kind() == "identifier" {
                                         if let Ok(text) = ns_child.utf8_text(source.as_bytes()) {
                                             aliases.push(text.to_string());
+                                            positions.push(name_position(ns_child, source));
                                         }
                                     }
                                 }
@@ -556,6 +728,7 @@ impl CodeParser {
                     return Some(ImportInfo {
                         module_name,
                         aliases,
+                        positions,
                     });
                 }
                 None
@@ -579,18 +752,21 @@ impl CodeParser {
                 // Not perfect but better than nothing
                 let mut aliases = Vec::new();
 
+                let mut positions = Vec::new();
+
                 // Recursive function to find identifiers in use tree
-                fn find_idents(node: Node, source: &str, idents: &mut Vec<String>) {
+                fn find_idents(node: Node, source: &str, idents: &mut Vec<String>, positions: &mut Vec<(usize, usize)>) {
                     if node.kind() == "identifier" {
                         idents.push(get_text(node, source));
+                        positions.push(name_position(node, source));
                     }
                     let mut cursor = node.walk();
                     for child in node.children(&mut cursor) {
-                        find_idents(child, source, idents);
+                        find_idents(child, source, idents, positions);
                     }
                 }
 
-                find_idents(node, source, &mut aliases);
+                find_idents(node, source, &mut aliases, &mut positions);
 
                 // Assuming the last ones are the imported items
                 // This is a naive heuristic
@@ -600,6 +776,7 @@ impl CodeParser {
                     return Some(ImportInfo {
                         module_name,
                         aliases,
+                        positions,
                     });
                 }
                 None
@@ -0,0 +1,67 @@
+// 長時間処理の進捗通知とキャンセル
+// スキャナーや埋め込み生成に渡し、MCP の progress / cancelled 通知につなぐ
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Receives `(done, total, message)` as work advances
+pub type ProgressFn = Arc<dyn Fn(u64, Option<u64>, &str) + Send + Sync>;
+
+/// Progress reporting and cooperative cancellation for one long operation.
+/// Clones share the cancel flag, so the caller can keep one to cancel with.
+#[derive(Clone, Default)]
+pub struct Progress {
+    report: Option<ProgressFn>,
+    cancelled: Arc<AtomicBool>,
+}
+
+/// Error returned by operations stopped through [`Progress::cancel`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Operation cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+impl Progress {
+    /// Progress delivered to `report`
+    pub fn new(report: ProgressFn) -> Self {
+        Self {
+            report: Some(report),
+            cancelled: Arc::default(),
+        }
+    }
+
+    pub fn report(&self, done: u64, total: Option<u64>, message: &str) {
+        if let Some(report) = &self.report {
+            report(done, total, message);
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Err([`Cancelled`]) once cancelled; call between units of work
+    pub fn check(&self) -> Result<(), Cancelled> {
+        if self.is_cancelled() {
+            Err(Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Whether an error chain comes from a cancelled operation
+pub fn is_cancelled(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<Cancelled>().is_some()
+}
@@ -21,6 +21,8 @@ pub struct Summary {
 pub struct DeadCodeEntry {
     pub file: String,
     pub name: String,
+    #[serde(default)]
+    pub symbol_id: String,
     pub line_start: usize,
     pub line_end: usize,
     pub safety_level: String,
@@ -148,6 +150,7 @@ pub fn generate_json_report(
         .map(|dc| DeadCodeEntry {
             file: dc.node.file_path.to_string_lossy().to_string(),
             name: dc.node.name.clone(),
+            symbol_id: dc.node.symbol_id.clone(),
             line_start: dc.node.line_range.0,
             line_end: dc.node.line_range.1,
             safety_level: format!("{:?}", dc.safety_level).to_lowercase(),
@@ -0,0 +1,659 @@
+// TypeScript module resolution
+// tsconfig.json (extends / paths / baseUrl / references) と package.json (exports / main) を解釈し、
+// import 指定子を実際のファイルに対応付ける
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+/// Extensions tried when an import omits one (in TypeScript's preference order)
+const SOURCE_EXTENSIONS: &[&str] = &[
+    "ts", "tsx", "d.ts", "mts", "cts", "js", "jsx", "mjs", "cjs", "vue", "svelte",
+];
+
+/// package.json `exports` conditions, most useful for source lookup first
+const EXPORT_CONDITIONS: &[&str] = &["source", "import", "module", "default", "require", "node", "types"];
+
+const NODE_BUILTINS: &[&str] = &[
+    "assert", "async_hooks", "buffer", "child_process", "cluster", "crypto", "dgram", "dns",
+    "events", "fs", "http", "http2", "https", "inspector", "module", "net", "os", "path",
+    "perf_hooks", "process", "querystring", "readline", "stream", "string_decoder", "timers",
+    "tls", "tty", "url", "util", "v8", "vm", "worker_threads", "zlib",
+];
+
+/// Result of resolving an import specifier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// Source file inside the project
+    File(PathBuf),
+    /// Third-party package (node_modules) or Node.js builtin
+    External(String),
+    /// Nothing matched
+    Unresolved,
+}
+
+/// Effective module settings of one tsconfig.json (after `extends`)
+#[derive(Debug, Clone, Default)]
+struct TsConfig {
+    base_url: Option<PathBuf>,
+    /// `paths` mappings in declaration order
+    paths: Vec<(String, Vec<String>)>,
+    /// Directory `paths` targets are relative to when there is no baseUrl
+    paths_dir: Option<PathBuf>,
+    /// Referenced project config files
+    references: Vec<PathBuf>,
+}
+
+/// Resolves TypeScript/JavaScript import specifiers for a project
+pub struct ModuleResolver {
+    root: PathBuf,
+    /// Loaded configs by file path (None = unreadable)
+    configs: HashMap<PathBuf, Option<TsConfig>>,
+    /// Directory -> nearest tsconfig.json
+    nearest_config: HashMap<PathBuf, Option<PathBuf>>,
+    /// Workspace packages by name -> package root
+    packages: HashMap<String, PathBuf>,
+}
+
+impl ModuleResolver {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            configs: HashMap::new(),
+            nearest_config: HashMap::new(),
+            packages: HashMap::new(),
+        }
+    }
+
+    /// Resolve imports of these workspace packages to their sources, even when
+    /// they are not linked into node_modules
+    pub fn with_packages(mut self, packages: HashMap<String, PathBuf>) -> Self {
+        self.packages = packages;
+        self
+    }
+
+    /// Resolve `specifier` as imported from `from_file`
+    pub fn resolve(&mut self, from_file: &Path, specifier: &str) -> Resolution {
+        let from_dir = from_file.parent().unwrap_or(Path::new("")).to_path_buf();
+
+        // 1. Relative / absolute paths
+        if specifier.starts_with("./") || specifier.starts_with("../") || specifier == "." || specifier == ".." {
+            return match resolve_file(&from_dir.join(specifier)) {
+                Some(path) => Resolution::File(path),
+                None => Resolution::Unresolved,
+            };
+        }
+        if Path::new(specifier).is_absolute() {
+            return match resolve_file(Path::new(specifier)) {
+                Some(path) => Resolution::File(path),
+                None => Resolution::Unresolved,
+            };
+        }
+
+        // 2. Node.js builtins
+        if let Some(builtin) = specifier.strip_prefix("node:") {
+            return Resolution::External(builtin.to_string());
+        }
+        let first_segment = specifier.split('/').next().unwrap_or(specifier);
+        if NODE_BUILTINS.contains(&first_segment) {
+            return Resolution::External(first_segment.to_string());
+        }
+
+        // 3. tsconfig `paths` and `baseUrl` (nearest config first, then project references)
+        for config in self.configs_for(&from_dir) {
+            if let Some(path) = resolve_with_paths(&config, specifier) {
+                return Resolution::File(path);
+            }
+            if let Some(base_url) = &config.base_url {
+                if let Some(path) = resolve_file(&base_url.join(specifier)) {
+                    return Resolution::File(path);
+                }
+            }
+        }
+
+        // 4. Workspace packages by name
+        if let Some(path) = self.resolve_workspace_package(specifier) {
+            return Resolution::File(path);
+        }
+
+        // 5. Packages in node_modules (workspace symlinks resolve back into the project)
+        self.resolve_package(&from_dir, specifier)
+    }
+
+    /// `@scope/pkg/sub` of a workspace package: manifest entry, then the subpath, then `src/`
+    fn resolve_workspace_package(&self, specifier: &str) -> Option<PathBuf> {
+        let (name, subpath) = split_package_specifier(specifier);
+        let package_dir = self.packages.get(&name)?;
+        read_jsonc(&package_dir.join("package.json"))
+            .and_then(|manifest| resolve_package_entry(package_dir, &manifest, &subpath))
+            .or_else(|| resolve_file(&package_dir.join(&subpath)))
+            .or_else(|| resolve_file(&package_dir.join("src").join(&subpath)))
+            .map(|path| normalize_path(&path))
+    }
+
+    /// Nearest tsconfig.json for a directory plus the configs it references
+    fn configs_for(&mut self, dir: &Path) -> Vec<TsConfig> {
+        let mut result = Vec::new();
+        let Some(config_path) = self.find_nearest_config(dir) else {
+            return result;
+        };
+        let Some(config) = self.load_config(&config_path, 0) else {
+            return result;
+        };
+
+        let references = config.references.clone();
+        result.push(config);
+        for reference in references {
+            if let Some(referenced) = self.load_config(&reference, 0) {
+                result.push(referenced);
+            }
+        }
+        result
+    }
+
+    fn find_nearest_config(&mut self, dir: &Path) -> Option<PathBuf> {
+        if let Some(cached) = self.nearest_config.get(dir) {
+            return cached.clone();
+        }
+
+        let found = dir
+            .ancestors()
+            .take_while(|d| d.starts_with(&self.root) || !dir.starts_with(&self.root))
+            .map(|d| d.join("tsconfig.json"))
+            .find(|p| p.is_file());
+
+        self.nearest_config.insert(dir.to_path_buf(), found.clone());
+        found
+    }
+
+    /// Load a tsconfig file, following `extends` (depth-limited to avoid cycles)
+    fn load_config(&mut self, path: &Path, depth: usize) -> Option<TsConfig> {
+        if let Some(cached) = self.configs.get(path) {
+            return cached.clone();
+        }
+        if depth > 8 {
+            return None;
+        }
+
+        let config = read_jsonc(path).map(|json| {
+            let dir = path.parent().unwrap_or(Path::new("")).to_path_buf();
+            let mut config = TsConfig::default();
+
+            // Parents first, so the child's options override them
+            let extends: Vec<String> = match json.get("extends") {
+                Some(Value::String(s)) => vec![s.clone()],
+                Some(Value::Array(items)) => items.iter().filter_map(|v| v.as_str().map(String::from)).collect(),
+                _ => Vec::new(),
+            };
+            for parent in extends {
+                if let Some(parent_path) = resolve_extends(&dir, &parent) {
+                    if let Some(parent_config) = self.load_config(&parent_path, depth + 1) {
+                        config.base_url = parent_config.base_url.or(config.base_url);
+                        if !parent_config.paths.is_empty() {
+                            config.paths = parent_config.paths;
+                            config.paths_dir = parent_config.paths_dir;
+                        }
+                    }
+                }
+            }
+
+            if let Some(options) = json.get("compilerOptions") {
+                if let Some(base_url) = options.get("baseUrl").and_then(|v| v.as_str()) {
+                    config.base_url = Some(normalize_path(&dir.join(base_url)));
+                }
+                if let Some(paths) = options.get("paths").and_then(|v| v.as_object()) {
+                    config.paths = paths
+                        .iter()
+                        .map(|(pattern, targets)| {
+                            let targets = targets
+                                .as_array()
+                                .map(|a| a.iter().filter_map(|t| t.as_str().map(String::from)).collect())
+                                .unwrap_or_default();
+                            (pattern.clone(), targets)
+                        })
+                        .collect();
+                    config.paths_dir = Some(dir.clone());
+                }
+            }
+
+            if let Some(references) = json.get("references").and_then(|v| v.as_array()) {
+                for reference in references {
+                    if let Some(ref_path) = reference.get("path").and_then(|v| v.as_str()) {
+                        let target = dir.join(ref_path);
+                        let target = if target.is_dir() { target.join("tsconfig.json") } else { target };
+                        if target.is_file() {
+                            config.references.push(normalize_path(&target));
+                        }
+                    }
+                }
+            }
+
+            config
+        });
+
+        self.configs.insert(path.to_path_buf(), config.clone());
+        config
+    }
+
+    fn resolve_package(&self, from_dir: &Path, specifier: &str) -> Resolution {
+        let (name, subpath) = split_package_specifier(specifier);
+
+        let package_dir = from_dir
+            .ancestors()
+            .map(|d| d.join("node_modules").join(&name))
+            .find(|p| p.is_dir());
+        let Some(package_dir) = package_dir else {
+            return Resolution::Unresolved;
+        };
+
+        let manifest = read_jsonc(&package_dir.join("package.json"));
+        let entry = manifest
+            .as_ref()
+            .and_then(|m| resolve_package_entry(&package_dir, m, &subpath))
+            .or_else(|| resolve_file(&package_dir.join(&subpath)));
+
+        // pnpm / yarn workspaces link local packages into node_modules
+        if let Some(file) = entry {
+            if let Some(in_project) = self.map_into_project(&file) {
+                return Resolution::File(in_project);
+            }
+        }
+        Resolution::External(name)
+    }
+
+    /// Map a file reached through a symlink back to its path under the project root
+    fn map_into_project(&self, file: &Path) -> Option<PathBuf> {
+        let root = self.root.canonicalize().ok()?;
+        let real = file.canonicalize().ok()?;
+        let relative = real.strip_prefix(&root).ok()?;
+        if relative.components().any(|c| c.as_os_str() == "node_modules") {
+            return None;
+        }
+        Some(self.root.join(relative))
+    }
+}
+
+/// Try the `paths` mappings of a config (longest matching prefix first)
+fn resolve_with_paths(config: &TsConfig, specifier: &str) -> Option<PathBuf> {
+    let base = config.base_url.as_ref().or(config.paths_dir.as_ref())?;
+
+    let mut matches: Vec<(usize, &Vec<String>, &str)> = config
+        .paths
+        .iter()
+        .filter_map(|(pattern, targets)| match pattern.split_once('*') {
+            Some((prefix, suffix)) => {
+                if specifier.len() >= prefix.len() + suffix.len()
+                    && specifier.starts_with(prefix)
+                    && specifier.ends_with(suffix)
+                {
+                    Some((prefix.len(), targets, &specifier[prefix.len()..specifier.len() - suffix.len()]))
+                } else {
+                    None
+                }
+            }
+            None if pattern == specifier => Some((usize::MAX, targets, "")),
+            None => None,
+        })
+        .collect();
+    matches.sort_by_key(|m| std::cmp::Reverse(m.0));
+
+    matches.into_iter().find_map(|(_, targets, wildcard)| {
+        targets
+            .iter()
+            .find_map(|target| resolve_file(&base.join(target.replace('*', wildcard))))
+    })
+}
+
+/// Resolve a package entry point via `exports`, then `types`/`module`/`main`
+fn resolve_package_entry(package_dir: &Path, manifest: &Value, subpath: &str) -> Option<PathBuf> {
+    if let Some(exports) = manifest.get("exports") {
+        return exports_targets(exports, subpath)
+            .into_iter()
+            .find_map(|target| resolve_file(&package_dir.join(target)));
+    }
+
+    if subpath != "." {
+        return None;
+    }
+    ["source", "types", "typings", "module", "main"]
+        .iter()
+        .filter_map(|field| manifest.get(*field).and_then(|v| v.as_str()))
+        .find_map(|entry| resolve_file(&package_dir.join(entry)))
+}
+
+/// Candidate targets of `exports` for a subpath (`.`, `./utils`, ...)
+fn exports_targets(exports: &Value, subpath: &str) -> Vec<String> {
+    match exports {
+        Value::String(target) if subpath == "." => vec![target.clone()],
+        Value::Array(items) => items.iter().flat_map(|v| exports_targets(v, subpath)).collect(),
+        Value::Object(map) => {
+            let is_subpath_map = map.keys().any(|k| k.starts_with('.'));
+            if !is_subpath_map {
+                // Conditions object for "."
+                return if subpath == "." { condition_targets(exports) } else { Vec::new() };
+            }
+            if let Some(target) = map.get(subpath) {
+                return condition_targets(target);
+            }
+            for (key, target) in map {
+                if let Some((prefix, suffix)) = key.split_once('*') {
+                    if subpath.len() >= prefix.len() + suffix.len()
+                        && subpath.starts_with(prefix)
+                        && subpath.ends_with(suffix)
+                    {
+                        let wildcard = &subpath[prefix.len()..subpath.len() - suffix.len()];
+                        return condition_targets(target)
+                            .into_iter()
+                            .map(|t| t.replace('*', wildcard))
+                            .collect();
+                    }
+                }
+            }
+            Vec::new()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Flatten a (possibly nested) conditions value into targets in preference order
+fn condition_targets(value: &Value) -> Vec<String> {
+    match value {
+        Value::String(target) => vec![target.clone()],
+        Value::Array(items) => items.iter().flat_map(condition_targets).collect(),
+        Value::Object(map) => {
+            let mut targets: Vec<String> = EXPORT_CONDITIONS
+                .iter()
+                .filter_map(|c| map.get(*c))
+                .flat_map(condition_targets)
+                .collect();
+            for (key, nested) in map {
+                if !EXPORT_CONDITIONS.contains(&key.as_str()) {
+                    targets.extend(condition_targets(nested));
+                }
+            }
+            targets
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Resolve a path to a file: exact match, added extension, `.js` -> `.ts`, or directory index
+pub fn resolve_file(candidate: &Path) -> Option<PathBuf> {
+    let candidate = normalize_path(candidate);
+
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+
+    // ESM style: `import "./foo.js"` refers to foo.ts
+    if let Some(ext) = candidate.extension().and_then(|e| e.to_str()) {
+        let replacements: &[&str] = match ext {
+            "js" => &["ts", "tsx"],
+            "jsx" => &["tsx"],
+            "mjs" => &["mts"],
+            "cjs" => &["cts"],
+            _ => &[],
+        };
+        for replacement in replacements {
+            let path = candidate.with_extension(replacement);
+            if path.is_file() {
+                return Some(path);
+            }
+        }
+    }
+
+    for ext in SOURCE_EXTENSIONS {
+        let path = with_added_extension(&candidate, ext);
+        if path.is_file() {
+            return Some(path);
+        }
+    }
+
+    if candidate.is_dir() {
+        if let Some(manifest) = read_jsonc(&candidate.join("package.json")) {
+            let entry = ["source", "types", "typings", "module", "main"]
+                .iter()
+                .filter_map(|field| manifest.get(*field).and_then(|v| v.as_str()))
+                .map(|entry| normalize_path(&candidate.join(entry)))
+                .find_map(|entry| {
+                    if entry.is_file() {
+                        Some(entry)
+                    } else {
+                        SOURCE_EXTENSIONS
+                            .iter()
+                            .map(|ext| with_added_extension(&entry, ext))
+                            .find(|p| p.is_file())
+                    }
+                });
+            if entry.is_some() {
+                return entry;
+            }
+        }
+        for ext in SOURCE_EXTENSIONS {
+            let index = candidate.join(format!("index.{}", ext));
+            if index.is_file() {
+                return Some(index);
+            }
+        }
+    }
+
+    None
+}
+
+fn with_added_extension(path: &Path, ext: &str) -> PathBuf {
+    let mut s = path.as_os_str().to_owned();
+    s.push(".");
+    s.push(ext);
+    PathBuf::from(s)
+}
+
+/// Resolve the target of a tsconfig `extends` entry
+fn resolve_extends(config_dir: &Path, extends: &str) -> Option<PathBuf> {
+    let candidates = |base: PathBuf| {
+        [
+            base.clone(),
+            with_added_extension(&base, "json"),
+            base.join("tsconfig.json"),
+        ]
+    };
+
+    if extends.starts_with('.') || Path::new(extends).is_absolute() {
+        return candidates(config_dir.join(extends))
+            .into_iter()
+            .map(|p| normalize_path(&p))
+            .find(|p| p.is_file());
+    }
+
+    // Shared configs from packages, e.g. "@tsconfig/node18/tsconfig.json"
+    config_dir
+        .ancestors()
+        .flat_map(|dir| candidates(dir.join("node_modules").join(extends)))
+        .find(|p| p.is_file())
+}
+
+/// "@scope/pkg/sub/path" -> ("@scope/pkg", "./sub/path"), "pkg" -> ("pkg", ".")
+fn split_package_specifier(specifier: &str) -> (String, String) {
+    let mut parts = specifier.split('/');
+    let name = match parts.next() {
+        Some(scope) if scope.starts_with('@') => match parts.next() {
+            Some(pkg) => format!("{}/{}", scope, pkg),
+            None => scope.to_string(),
+        },
+        Some(pkg) => pkg.to_string(),
+        None => String::new(),
+    };
+    let rest: Vec<&str> = parts.collect();
+    let subpath = if rest.is_empty() {
+        ".".to_string()
+    } else {
+        format!("./{}", rest.join("/"))
+    };
+    (name, subpath)
+}
+
+/// Lexically normalise `.` and `..` components (without touching the filesystem)
+pub fn normalize_path(path: &Path) -> PathBuf {
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                let can_pop = matches!(result.components().next_back(), Some(Component::Normal(_)));
+                if can_pop {
+                    result.pop();
+                } else {
+                    result.push("..");
+                }
+            }
+            other => result.push(other.as_os_str()),
+        }
+    }
+    result
+}
+
+/// Read a JSON file that may contain comments and trailing commas (tsconfig style)
+pub fn read_jsonc(path: &Path) -> Option<Value> {
+    let content = std::fs::read_to_string(path).ok()?;
+    serde_json::from_str(&strip_jsonc(&content)).ok()
+}
+
+/// Remove `//` and `/* */` comments and trailing commas from JSONC text
+fn strip_jsonc(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut out = String::with_capacity(input.len());
+    let mut i = 0;
+    let mut in_string = false;
+
+    while i < chars.len() {
+        let ch = chars[i];
+        if in_string {
+            out.push(ch);
+            if ch == '\\' && i + 1 < chars.len() {
+                out.push(chars[i + 1]);
+                i += 1;
+            } else if ch == '"' {
+                in_string = false;
+            }
+            i += 1;
+            continue;
+        }
+
+        match ch {
+            '"' => {
+                in_string = true;
+                out.push(ch);
+            }
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                i += 2;
+                while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                    i += 1;
+                }
+                i += 2;
+                continue;
+            }
+            ',' => {
+                // Drop the comma if the next significant character closes the container
+                let next = chars[i + 1..].iter().find(|c| !c.is_whitespace());
+                if !matches!(next, Some('}') | Some(']')) {
+                    out.push(ch);
+                }
+            }
+            _ => out.push(ch),
+        }
+        i += 1;
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_project, write};
+
+    #[test]
+    fn test_tsconfig_paths_extends_and_index_files() {
+        let temp = temp_project();
+        let root = temp.path();
+        write(root, "tsconfig.base.json", r#"{
+            // shared options
+            "compilerOptions": {
+                "baseUrl": ".",
+                "paths": { "@app/*": ["src/app/*"], "~/*": ["src/*"], },
+            },
+        }"#);
+        write(root, "tsconfig.json", r#"{ "extends": "./tsconfig.base.json" }"#);
+        write(root, "src/app/utils.ts", "export function util() {}");
+        write(root, "src/components/Button/index.tsx", "export function Button() {}");
+        write(root, "src/main.ts", "");
+        write(root, "src/lib/helper.ts", "");
+
+        let mut resolver = ModuleResolver::new(root);
+        let from = root.join("src/main.ts");
+
+        assert_eq!(resolver.resolve(&from, "@app/utils"), Resolution::File(root.join("src/app/utils.ts")));
+        assert_eq!(
+            resolver.resolve(&from, "~/components/Button"),
+            Resolution::File(root.join("src/components/Button/index.tsx"))
+        );
+        assert_eq!(resolver.resolve(&from, "src/lib/helper"), Resolution::File(root.join("src/lib/helper.ts")));
+        assert_eq!(resolver.resolve(&from, "./lib/helper.js"), Resolution::File(root.join("src/lib/helper.ts")));
+        assert_eq!(resolver.resolve(&from, "node:fs"), Resolution::External("fs".to_string()));
+        assert_eq!(resolver.resolve(&from, "@app/missing"), Resolution::Unresolved);
+    }
+
+    #[test]
+    fn test_package_exports_and_project_references() {
+        let temp = temp_project();
+        let root = temp.path();
+        write(root, "tsconfig.json", r#"{ "files": [], "references": [{ "path": "./tsconfig.app.json" }] }"#);
+        write(root, "tsconfig.app.json", r#"{ "compilerOptions": { "paths": { "@/*": ["./src/*"] } } }"#);
+        write(root, "src/store.ts", "");
+        write(root, "node_modules/lib-a/package.json", r#"{
+            "name": "lib-a",
+            "exports": { ".": { "types": "./dist/index.d.ts", "import": "./dist/index.mjs" }, "./feature/*": "./dist/feature/*.js" }
+        }"#);
+        write(root, "node_modules/lib-a/dist/index.mjs", "");
+        write(root, "node_modules/lib-a/dist/feature/x.js", "");
+        write(root, "node_modules/lib-b/package.json", r#"{ "name": "lib-b", "main": "lib/main" }"#);
+        write(root, "node_modules/lib-b/lib/main.js", "");
+
+        let mut resolver = ModuleResolver::new(root);
+        let from = root.join("src/main.ts");
+
+        assert_eq!(resolver.resolve(&from, "@/store"), Resolution::File(root.join("src/store.ts")));
+        assert_eq!(resolver.resolve(&from, "lib-a"), Resolution::External("lib-a".to_string()));
+        assert_eq!(resolver.resolve(&from, "lib-a/feature/x"), Resolution::External("lib-a".to_string()));
+        assert_eq!(resolver.resolve(&from, "lib-b"), Resolution::External("lib-b".to_string()));
+        assert_eq!(resolver.resolve(&from, "not-installed"), Resolution::Unresolved);
+    }
+
+    #[test]
+    fn test_workspace_packages_resolve_without_node_modules() {
+        let temp = temp_project();
+        let root = temp.path();
+        write(root, "packages/ui/package.json", r#"{ "name": "@acme/ui", "main": "dist/index.js" }"#);
+        write(root, "packages/ui/src/index.ts", "");
+        write(root, "packages/ui/src/button.ts", "");
+        write(root, "packages/core/package.json", r#"{ "name": "core", "exports": { ".": "./lib/main.ts" } }"#);
+        write(root, "packages/core/lib/main.ts", "");
+
+        let packages = HashMap::from([
+            ("@acme/ui".to_string(), root.join("packages/ui")),
+            ("core".to_string(), root.join("packages/core")),
+        ]);
+        let mut resolver = ModuleResolver::new(root).with_packages(packages);
+        let from = root.join("apps/web/src/main.ts");
+
+        assert_eq!(resolver.resolve(&from, "@acme/ui"), Resolution::File(root.join("packages/ui/src/index.ts")));
+        assert_eq!(resolver.resolve(&from, "@acme/ui/button"), Resolution::File(root.join("packages/ui/src/button.ts")));
+        assert_eq!(resolver.resolve(&from, "core"), Resolution::File(root.join("packages/core/lib/main.ts")));
+        assert_eq!(resolver.resolve(&from, "@acme/other"), Resolution::Unresolved);
+    }
+}
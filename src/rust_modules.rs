@@ -0,0 +1,456 @@
+// Rust module tree
+// Cargo.toml (workspace members / [lib] / [[bin]]) と mod 宣言からファイル -> モジュールパスを構築する
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tree_sitter::{Node, Tree};
+
+use crate::parser::{CodeParser, Language};
+use crate::resolver::normalize_path;
+
+/// Out-of-line `mod foo;` declaration found in a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModDeclaration {
+    /// Enclosing inline modules, then the declared module name
+    pub modules: Vec<String>,
+    /// Value of a `#[path = "..."]` attribute
+    pub path_attr: Option<String>,
+}
+
+/// Map of Rust source files to their module paths (`crate_name::module::sub`)
+#[derive(Debug, Clone, Default)]
+pub struct RustModuleTree {
+    root: PathBuf,
+    modules: HashMap<PathBuf, String>,
+    /// Crate names of the workspace targets, so `other_crate::item` paths stay absolute
+    crates: HashSet<String>,
+}
+
+impl RustModuleTree {
+    /// Build the tree for the Cargo package/workspace containing `root`
+    pub fn build(root: &Path) -> Self {
+        Self::build_with_declarations(root, &HashMap::new())
+    }
+
+    /// Build the tree using already-extracted `mod` declarations where available;
+    /// only files missing from `declarations` are read and parsed
+    pub fn build_with_declarations(root: &Path, declarations: &HashMap<PathBuf, Vec<ModDeclaration>>) -> Self {
+        let mut tree = Self {
+            root: root.to_path_buf(),
+            modules: HashMap::new(),
+            crates: HashSet::new(),
+        };
+
+        let Some(manifest) = root
+            .ancestors()
+            .map(|dir| dir.join("Cargo.toml"))
+            .find(|p| p.is_file())
+        else {
+            return tree;
+        };
+
+        let mut visited = HashSet::new();
+        for manifest_path in workspace_manifests(&manifest) {
+            for (crate_name, root_file) in crate_targets(&manifest_path) {
+                tree.crates.insert(crate_name.clone());
+                tree.walk_file(&root_file, &crate_name, true, declarations, &mut visited);
+            }
+        }
+
+        tree
+    }
+
+    /// Module path of a file. Files outside the module tree get a path derived
+    /// from their location (`crate::dir::file`).
+    pub fn module_path(&self, file: &Path) -> String {
+        let file = normalize_path(file);
+        if let Some(path) = self.modules.get(&file) {
+            return path.clone();
+        }
+
+        let relative = file.strip_prefix(normalize_path(&self.root)).unwrap_or(&file);
+        let mut segments = vec!["crate".to_string()];
+        let components: Vec<String> = relative
+            .with_extension("")
+            .components()
+            .filter_map(|c| c.as_os_str().to_str().map(String::from))
+            .collect();
+        for (i, segment) in components.iter().enumerate() {
+            let is_last = i + 1 == components.len();
+            if is_last && matches!(segment.as_str(), "mod" | "lib" | "main") {
+                continue;
+            }
+            if segment == "src" {
+                continue;
+            }
+            segments.push(segment.replace('-', "_"));
+        }
+        segments.join("::")
+    }
+
+    /// Whether `module` (an absolute path) is a module of the tree, i.e. a file's module
+    pub fn has_module(&self, module: &str) -> bool {
+        self.modules.values().any(|path| path == module)
+    }
+
+    /// File that holds `module`
+    pub fn module_file(&self, module: &str) -> Option<&Path> {
+        self.modules.iter().find(|(_, path)| *path == module).map(|(file, _)| file.as_path())
+    }
+
+    /// Turn a path written in `module` (`crate::a::b`, `super::c`, `self::d`, `Type::new`)
+    /// into an absolute path
+    pub fn absolute_path(&self, module: &str, path: &str) -> String {
+        let mut base: Vec<&str> = module.split("::").collect();
+        let mut segments = path.split("::").peekable();
+
+        match segments.peek().copied() {
+            Some("crate") => {
+                segments.next();
+                base.truncate(1);
+            }
+            Some("self") => {
+                segments.next();
+            }
+            Some("super") => {
+                while segments.peek() == Some(&"super") {
+                    segments.next();
+                    if base.len() > 1 {
+                        base.pop();
+                    }
+                }
+            }
+            // Path into another crate of the workspace
+            Some(first) if self.crates.contains(first) && !self.is_local_module(module, first) => {
+                base.clear();
+            }
+            _ => {}
+        }
+
+        base.into_iter()
+            .map(String::from)
+            .chain(segments.map(String::from))
+            .collect::<Vec<_>>()
+            .join("::")
+    }
+
+    /// Whether `name` is a child module of `module` (which then shadows a crate of the same name)
+    fn is_local_module(&self, module: &str, name: &str) -> bool {
+        let child = format!("{}::{}", module, name);
+        self.modules.values().any(|path| *path == child)
+    }
+
+    /// Record `file` as `module_path` and follow its `mod` declarations
+    fn walk_file(
+        &mut self,
+        file: &Path,
+        module_path: &str,
+        is_root: bool,
+        declarations: &HashMap<PathBuf, Vec<ModDeclaration>>,
+        visited: &mut HashSet<PathBuf>,
+    ) {
+        let file = normalize_path(file);
+        if !visited.insert(file.clone()) {
+            return;
+        }
+        let declared = match declarations.get(&file) {
+            Some(declared) => declared.clone(),
+            None => {
+                let Ok(source) = std::fs::read_to_string(&file) else {
+                    return;
+                };
+                let Ok(mut parser) = CodeParser::new(Language::Rust) else {
+                    return;
+                };
+                let Ok(tree) = parser.parse_source(&source) else {
+                    return;
+                };
+                extract_mod_declarations(&tree, &source)
+            }
+        };
+        self.modules.insert(file.clone(), module_path.to_string());
+
+        // Crate roots and mod.rs own their directory; foo.rs puts children in foo/
+        let file_dir = file.parent().unwrap_or(Path::new("")).to_path_buf();
+        let is_mod_rs = file.file_name().and_then(|n| n.to_str()) == Some("mod.rs");
+        let child_dir = if is_root || is_mod_rs {
+            file_dir.clone()
+        } else {
+            file_dir.join(file.file_stem().unwrap_or_default())
+        };
+
+        for declaration in declared {
+            let Some((name, inline)) = declaration.modules.split_last() else {
+                continue;
+            };
+            let child_module = format!("{}::{}", module_path, declaration.modules.join("::"));
+            // Inside inline `mod a { mod b; }` both lookups are relative to a/
+            let inline_dir = inline.iter().fold(child_dir.clone(), |dir, m| dir.join(m));
+            let path_attr_dir = if inline.is_empty() { &file_dir } else { &inline_dir };
+            let child_file = declaration
+                .path_attr
+                .as_ref()
+                .map(|p| path_attr_dir.join(p))
+                .or_else(|| {
+                    [inline_dir.join(format!("{}.rs", name)), inline_dir.join(name).join("mod.rs")]
+                        .into_iter()
+                        .find(|p| p.is_file())
+                });
+            if let Some(child_file) = child_file {
+                self.walk_file(&child_file, &child_module, false, declarations, visited);
+            }
+        }
+    }
+}
+
+/// Out-of-line `mod` declarations of a parsed file, including those inside inline modules
+pub fn extract_mod_declarations(tree: &Tree, source: &str) -> Vec<ModDeclaration> {
+    let mut declared = Vec::new();
+    collect_mod_declarations(tree.root_node(), source, &mut Vec::new(), &mut declared);
+    declared
+}
+
+/// Find out-of-line `mod foo;` declarations, descending into inline `mod foo { ... }` blocks
+fn collect_mod_declarations(node: Node, source: &str, inline: &mut Vec<String>, declared: &mut Vec<ModDeclaration>) {
+    let mut cursor = node.walk();
+    for item in node.children(&mut cursor) {
+        if item.kind() != "mod_item" {
+            continue;
+        }
+        let Some(name) = item
+            .child_by_field_name("name")
+            .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+        else {
+            continue;
+        };
+        let name = name.trim_start_matches("r#").to_string();
+
+        match item.child_by_field_name("body") {
+            Some(body) => {
+                inline.push(name);
+                collect_mod_declarations(body, source, inline, declared);
+                inline.pop();
+            }
+            None => {
+                let mut modules = inline.clone();
+                modules.push(name);
+                declared.push(ModDeclaration {
+                    modules,
+                    path_attr: path_attribute(item, source),
+                });
+            }
+        }
+    }
+}
+
+/// `#[path = "..."]` on a mod item
+fn path_attribute(item: Node, source: &str) -> Option<String> {
+    let mut sibling = item.prev_sibling();
+    while let Some(node) = sibling {
+        if node.kind() != "attribute_item" {
+            break;
+        }
+        let text = node.utf8_text(source.as_bytes()).ok()?;
+        let inner = text.trim_start_matches("#[").trim_end_matches(']').trim();
+        if let Some(value) = inner.strip_prefix("path") {
+            let value = value.trim_start().strip_prefix('=')?.trim();
+            return Some(value.trim_matches('"').to_string());
+        }
+        sibling = node.prev_sibling();
+    }
+    None
+}
+
+/// The manifest itself plus its workspace members
+pub(crate) fn workspace_manifests(manifest: &Path) -> Vec<PathBuf> {
+    let mut manifests = vec![manifest.to_path_buf()];
+    let Some(value) = read_manifest(manifest) else {
+        return manifests;
+    };
+    let Some(workspace) = value.get("workspace") else {
+        return manifests;
+    };
+
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+    let list = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+            .unwrap_or_default()
+    };
+    let excluded: Vec<PathBuf> = list("exclude").iter().map(|e| normalize_path(&dir.join(e))).collect();
+
+    for member in list("members") {
+        for member_dir in expand_glob(dir, &member) {
+            let member_dir = normalize_path(&member_dir);
+            let member_manifest = member_dir.join("Cargo.toml");
+            if !excluded.contains(&member_dir) && member_manifest.is_file() && !manifests.contains(&member_manifest) {
+                manifests.push(member_manifest);
+            }
+        }
+    }
+    manifests
+}
+
+/// Expand a workspace member pattern (`crates/*`, `tools/cli`) into directories.
+/// `**` is treated like `*` (one directory level).
+pub(crate) fn expand_glob(base: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut current = vec![base.to_path_buf()];
+    for segment in pattern.split('/').filter(|s| !s.is_empty() && *s != ".") {
+        let mut next = Vec::new();
+        for dir in &current {
+            if segment.contains('*') {
+                let segment = if segment == "**" { "*" } else { segment };
+                let (prefix, suffix) = segment.split_once('*').unwrap_or((segment, ""));
+                let Ok(entries) = std::fs::read_dir(dir) else {
+                    continue;
+                };
+                let mut matched: Vec<PathBuf> = entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.is_dir())
+                    .filter(|p| {
+                        p.file_name()
+                            .and_then(|n| n.to_str())
+                            .map(|n| n.starts_with(prefix) && n.ends_with(suffix))
+                            .unwrap_or(false)
+                    })
+                    .collect();
+                matched.sort();
+                next.extend(matched);
+            } else {
+                next.push(dir.join(segment));
+            }
+        }
+        current = next;
+    }
+    current
+}
+
+/// (crate name, root file) for the lib and bin targets of a package
+fn crate_targets(manifest: &Path) -> Vec<(String, PathBuf)> {
+    let Some(value) = read_manifest(manifest) else {
+        return Vec::new();
+    };
+    let Some(package_name) = value
+        .get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+    else {
+        return Vec::new();
+    };
+    let dir = manifest.parent().unwrap_or(Path::new(""));
+    let crate_name = |name: &str| name.replace('-', "_");
+    let mut targets = Vec::new();
+
+    // [lib]
+    let lib = value.get("lib");
+    let lib_path = lib
+        .and_then(|l| l.get("path"))
+        .and_then(|p| p.as_str())
+        .map(|p| dir.join(p))
+        .unwrap_or_else(|| dir.join("src/lib.rs"));
+    if lib_path.is_file() {
+        let name = lib
+            .and_then(|l| l.get("name"))
+            .and_then(|n| n.as_str())
+            .unwrap_or(package_name);
+        targets.push((crate_name(name), lib_path));
+    }
+
+    // [[bin]]
+    let mut bin_paths = HashSet::new();
+    if let Some(bins) = value.get("bin").and_then(|b| b.as_array()) {
+        for bin in bins {
+            let name = bin.get("name").and_then(|n| n.as_str()).unwrap_or(package_name);
+            let path = bin
+                .get("path")
+                .and_then(|p| p.as_str())
+                .map(|p| dir.join(p))
+                .unwrap_or_else(|| dir.join("src/bin").join(format!("{}.rs", name)));
+            if path.is_file() {
+                bin_paths.insert(normalize_path(&path));
+                targets.push((crate_name(name), path));
+            }
+        }
+    }
+
+    // Auto-discovered binaries: src/main.rs, src/bin/*.rs, src/bin/*/main.rs
+    let main_rs = dir.join("src/main.rs");
+    if main_rs.is_file() && !bin_paths.contains(&normalize_path(&main_rs)) {
+        targets.push((crate_name(package_name), main_rs));
+    }
+    if let Ok(entries) = std::fs::read_dir(dir.join("src/bin")) {
+        let mut entries: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+        entries.sort();
+        for path in entries {
+            let (name, file) = if path.is_dir() {
+                (path.file_name().and_then(|n| n.to_str()).map(String::from), path.join("main.rs"))
+            } else if path.extension().and_then(|e| e.to_str()) == Some("rs") {
+                (path.file_stem().and_then(|n| n.to_str()).map(String::from), path.clone())
+            } else {
+                continue;
+            };
+            if let Some(name) = name {
+                if file.is_file() && !bin_paths.contains(&normalize_path(&file)) {
+                    targets.push((crate_name(&name), file));
+                }
+            }
+        }
+    }
+
+    targets
+}
+
+pub(crate) fn read_manifest(path: &Path) -> Option<toml::Value> {
+    let content = std::fs::read_to_string(path).ok()?;
+    content.parse::<toml::Value>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_project, write};
+
+    #[test]
+    fn test_workspace_module_tree() {
+        let temp = temp_project();
+        let root = temp.path();
+
+        write(root, "Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n");
+        write(root, "crates/core-lib/Cargo.toml", "[package]\nname = \"core-lib\"\n");
+        write(root, "crates/core-lib/src/lib.rs", "pub mod parser;\nmod util {\n    pub mod io;\n}\n#[path = \"gen/out.rs\"]\nmod generated;\n");
+        write(root, "crates/core-lib/src/parser.rs", "mod tokens;\npub fn parse() {}\n");
+        write(root, "crates/core-lib/src/parser/tokens.rs", "pub fn parse() {}\n");
+        write(root, "crates/core-lib/src/util/io.rs", "");
+        write(root, "crates/core-lib/src/gen/out.rs", "");
+        write(root, "crates/cli/Cargo.toml", "[package]\nname = \"cli\"\n\n[[bin]]\nname = \"tool\"\npath = \"src/main.rs\"\n");
+        write(root, "crates/cli/src/main.rs", "mod commands;\nfn main() {}\n");
+        write(root, "crates/cli/src/commands/mod.rs", "");
+
+        let tree = RustModuleTree::build(root);
+        let lib = root.join("crates/core-lib/src");
+
+        assert_eq!(tree.module_path(&lib.join("lib.rs")), "core_lib");
+        assert_eq!(tree.module_path(&lib.join("parser.rs")), "core_lib::parser");
+        assert_eq!(tree.module_path(&lib.join("parser/tokens.rs")), "core_lib::parser::tokens");
+        assert_eq!(tree.module_path(&lib.join("util/io.rs")), "core_lib::util::io");
+        assert_eq!(tree.module_path(&lib.join("gen/out.rs")), "core_lib::generated");
+        assert_eq!(tree.module_path(&root.join("crates/cli/src/commands/mod.rs")), "tool::commands");
+
+        assert_eq!(tree.absolute_path("core_lib::parser::tokens", "super::parse"), "core_lib::parser::parse");
+        assert_eq!(tree.absolute_path("core_lib::parser", "crate::util::io::read"), "core_lib::util::io::read");
+        assert_eq!(tree.absolute_path("core_lib::parser", "self::tokens::parse"), "core_lib::parser::tokens::parse");
+        assert_eq!(tree.absolute_path("tool::commands", "core_lib::parser::parse"), "core_lib::parser::parse");
+        assert_eq!(tree.absolute_path("tool", "commands::run"), "tool::commands::run");
+    }
+
+    #[test]
+    fn test_fallback_module_path_without_manifest() {
+        let tree = RustModuleTree::default();
+        assert_eq!(tree.module_path(Path::new("sample.rs")), "crate::sample");
+        assert_eq!(tree.module_path(Path::new("src/net/mod.rs")), "crate::net");
+    }
+}
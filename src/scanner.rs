@@ -1,53 +1,128 @@
 use anyhow::{Context, Result};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 use ignore::WalkBuilder;
+use rayon::prelude::*;
 use sha2::{Sha256, Digest};
 use serde::{Serialize, Deserialize};
 
-use crate::graph::{CodeGraph, CodeNode, DependencyEdge, EdgeType, NodeType};
-use crate::parser::{CodeParser, Language};
+use crate::graph::{make_symbol_id, CodeGraph, CodeNode, DependencyEdge, EdgeType, NodeId, SourcePosition};
+use crate::parser::{CallInfo, CodeParser, FunctionInfo, ImportInfo, Language};
+use crate::progress::Progress;
+use crate::resolver::{normalize_path, ModuleResolver, Resolution};
+use crate::rust_modules::{extract_mod_declarations, ModDeclaration, RustModuleTree};
+use crate::sfc::{parse_sfc, SfcKind, TemplateReference};
+use crate::workspace::Workspace;
 
-/// File hash cache for incremental scanning
+/// Everything extracted from one file by a single parse
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileExtraction {
+    pub path: PathBuf,
+    pub language: Language,
+    /// SHA-256 of the file content
+    pub content_hash: String,
+    pub functions: Vec<FunctionInfo>,
+    pub calls: Vec<CallInfo>,
+    pub imports: Vec<ImportInfo>,
+    /// Identifiers used from a Vue/Svelte template
+    pub template_refs: Vec<TemplateReference>,
+    /// Out-of-line `mod` declarations (Rust only)
+    pub mod_declarations: Vec<ModDeclaration>,
+}
+
+impl FileExtraction {
+    /// Whether any name this file resolves against the graph is in `names`
+    fn references_any(&self, names: &HashSet<String>) -> bool {
+        self.calls.iter().any(|call| names.contains(&call.callee_name))
+            || self.template_refs.iter().any(|r| names.contains(&r.name))
+            || self.imports.iter().flat_map(|i| &i.aliases).any(|alias| names.contains(alias))
+    }
+}
+
+/// Parsers owned by one worker thread, created on first use per language
+#[derive(Default)]
+struct ParserSet {
+    typescript: Option<CodeParser>,
+    rust: Option<CodeParser>,
+}
+
+impl ParserSet {
+    fn get(&mut self, language: Language) -> Result<&mut CodeParser> {
+        let slot = match language {
+            Language::TypeScript => &mut self.typescript,
+            Language::Rust => &mut self.rust,
+        };
+        if slot.is_none() {
+            *slot = Some(CodeParser::new(language)?);
+        }
+        Ok(slot.as_mut().unwrap())
+    }
+}
+
+/// Per-file extraction cache for incremental scanning
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ScanCache {
-    /// Map of file path -> content hash
-    pub file_hashes: HashMap<String, String>,
-    /// Cached graph (serializable form)
-    pub cached_nodes: Vec<CachedNode>,
     /// Version for cache invalidation
     pub version: u32,
+    /// index-chan version that wrote the cache; extraction rules change between releases
+    pub tool_version: String,
+    /// Map of file path -> cached extraction and resolved edges
+    pub files: HashMap<String, CachedFile>,
+    /// Workspace package name -> root, which import resolution depends on
+    pub packages: BTreeMap<String, PathBuf>,
+    /// Hash of the tsconfig/jsconfig and package.json files imports are resolved with
+    pub resolver_config: String,
 }
 
-const CACHE_VERSION: u32 = 1;
+const CACHE_VERSION: u32 = 7;
+const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Cached scan result of one file
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CachedNode {
+pub struct CachedFile {
+    pub extraction: FileExtraction,
+    /// Rust module path the definitions were qualified with (empty for TypeScript)
+    pub module_path: String,
+    /// Definitions the file contributed, as seen by name lookups
+    pub definitions: Vec<CachedDefinition>,
+    /// Edges resolved from this file's imports, calls and template references
+    pub edges: Vec<CachedEdge>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CachedDefinition {
+    pub symbol_id: String,
     pub name: String,
-    pub node_type: String,
-    pub file_path: String,
-    pub line_range: (usize, usize),
-    pub is_exported: bool,
-    pub signature: String,
+    pub qualified_name: String,
+}
+
+/// Edge endpoints by symbol id; `from: None` is a top-level reference
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedEdge {
+    pub from: Option<String>,
+    pub to: String,
+    pub edge_type: EdgeType,
+    pub site: Option<SourcePosition>,
 }
 
 impl ScanCache {
     /// Load cache from file
     pub fn load(cache_path: &Path) -> Option<Self> {
-        let content = std::fs::read_to_string(cache_path).ok()?;
-        let cache: ScanCache = serde_json::from_str(&content).ok()?;
-        if cache.version != CACHE_VERSION {
+        let content = std::fs::read(cache_path).ok()?;
+        let cache: ScanCache = serde_json::from_slice(&content).ok()?;
+        if cache.version != CACHE_VERSION || cache.tool_version != TOOL_VERSION {
             return None; // Invalidate old cache
         }
         Some(cache)
     }
 
-    /// Save cache to file
+    /// Save cache to file (compact JSON: the cache holds full extractions)
     pub fn save(&self, cache_path: &Path) -> Result<()> {
         if let Some(parent) = cache_path.parent() {
             std::fs::create_dir_all(parent)?;
         }
-        let content = serde_json::to_string_pretty(self)?;
+        let content = serde_json::to_vec(self)?;
         std::fs::write(cache_path, content)?;
         Ok(())
     }
@@ -55,16 +130,21 @@ impl ScanCache {
     /// Calculate file hash
     pub fn hash_file(path: &Path) -> Result<String> {
         let content = std::fs::read(path)?;
+        Ok(Self::hash_content(&content))
+    }
+
+    /// Calculate the hash of already-read content
+    pub fn hash_content(content: &[u8]) -> String {
         let mut hasher = Sha256::new();
-        hasher.update(&content);
-        Ok(format!("{:x}", hasher.finalize()))
+        hasher.update(content);
+        format!("{:x}", hasher.finalize())
     }
 
     /// Check if file has changed
     pub fn is_file_changed(&self, path: &Path) -> bool {
         let path_str = path.display().to_string();
-        match (self.file_hashes.get(&path_str), Self::hash_file(path)) {
-            (Some(cached), Ok(current)) => cached != &current,
+        match (self.files.get(&path_str), Self::hash_file(path)) {
+            (Some(cached), Ok(current)) => cached.extraction.content_hash != current,
             _ => true, // If we can't determine, assume changed
         }
     }
@@ -73,16 +153,27 @@ impl ScanCache {
 pub struct Scanner {
     /// Enable incremental scanning
     pub incremental: bool,
+    /// Cache produced by the last scan, kept so `rescan` can skip unchanged files
+    last_scan: Option<(PathBuf, ScanCache)>,
+    /// Where parsing progress goes; cancelling it stops the scan with `Cancelled`
+    progress: Progress,
+    /// The last scan found files added, removed or modified since the cached one
+    last_changed: bool,
 }
 
 impl Scanner {
     pub fn new() -> Result<Self> {
-        Ok(Self { incremental: true })
+        Self::with_incremental(true)
     }
 
     /// Create scanner with specific incremental setting
     pub fn with_incremental(incremental: bool) -> Result<Self> {
-        Ok(Self { incremental })
+        Ok(Self { incremental, last_scan: None, progress: Progress::default(), last_changed: true })
+    }
+
+    /// Report progress to (and accept cancellation from) `progress`
+    pub fn set_progress(&mut self, progress: Progress) {
+        self.progress = progress;
     }
 
     /// Get cache path for a directory
@@ -91,182 +182,463 @@ impl Scanner {
     }
 
     pub fn scan_directory(&mut self, dir: &Path) -> Result<CodeGraph> {
+        self.scan(dir, None)
+    }
+
+    /// Rescan after the given files were modified, created or removed.
+    /// Other files are taken from the previous scan without being read again;
+    /// paths must be in the form the scan produces (`dir` joined, normalised).
+    pub fn rescan(&mut self, dir: &Path, changed: &HashSet<PathBuf>) -> Result<CodeGraph> {
+        self.scan(dir, Some(changed))
+    }
+
+    /// Whether the last scan found anything different from the cache it started from
+    pub fn last_scan_changed(&self) -> bool {
+        self.last_changed
+    }
+
+    fn scan(&mut self, dir: &Path, changed_files: Option<&HashSet<PathBuf>>) -> Result<CodeGraph> {
         let cache_path = Self::cache_path(dir);
-        let cache = if self.incremental {
-            ScanCache::load(&cache_path)
-        } else {
-            None
+        let cache = match self.last_scan.take() {
+            Some((root, cache)) if self.incremental && root == dir => Some(cache),
+            _ if self.incremental => ScanCache::load(&cache_path),
+            _ => None,
         };
 
         let mut graph = CodeGraph::new();
         let mut new_cache = ScanCache {
             version: CACHE_VERSION,
+            tool_version: TOOL_VERSION.to_string(),
             ..Default::default()
         };
-        let mut file_count = 0;
-        let mut cached_count = 0;
-        let mut changed_count = 0;
 
-        // Collect all supported files (TypeScript and Rust) using ignore crate
-        let code_files: Vec<(PathBuf, Language)> = WalkBuilder::new(dir)
+        // Collect all supported files (TypeScript, Vue/Svelte and Rust) using ignore crate,
+        // and the config files import resolution reads
+        let mut code_files: Vec<(PathBuf, Language)> = Vec::new();
+        let mut config_files: Vec<PathBuf> = Vec::new();
+        let walker = WalkBuilder::new(dir)
             .add_custom_ignore_filename(".indexchanignore")
             .git_ignore(true)      // .gitignoreも尊重
             .git_global(true)      // グローバル.gitignoreも
             .git_exclude(true)     // .git/info/excludeも
-            .build()
-            .filter_map(|e| e.ok())
-            .filter_map(|e| {
-                let path = e.path();
-                if !path.is_file() {
-                    return None;
-                }
-                let ext = path.extension()?.to_str()?;
-                let lang = Language::from_extension(ext)?;
-                Some((path.to_path_buf(), lang))
-            })
-            .collect();
+            .build();
+        for entry in walker.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if !path.is_file() {
+                continue;
+            }
+            // Normalised so paths match those produced by import resolution
+            if let Some(lang) = path.extension().and_then(|e| e.to_str()).and_then(Language::from_extension) {
+                code_files.push((normalize_path(path), lang));
+            } else if is_resolver_config(path) {
+                config_files.push(normalize_path(path));
+            }
+        }
+        code_files.sort_by(|a, b| a.0.cmp(&b.0));
+        config_files.sort();
+        new_cache.resolver_config = hash_resolver_config(&config_files);
 
-        let ts_count = code_files.iter().filter(|(_, lang)| *lang == Language::TypeScript).count();
+        let sfc_count = code_files.iter().filter(|(path, _)| SfcKind::from_path(path).is_some()).count();
+        let ts_count = code_files.iter().filter(|(_, lang)| *lang == Language::TypeScript).count() - sfc_count;
         let rs_count = code_files.iter().filter(|(_, lang)| *lang == Language::Rust).count();
 
-        println!("📂 Found {} files (TypeScript: {}, Rust: {})", code_files.len(), ts_count, rs_count);
-
-        // First pass: collect all function/class definitions
-        for (file_path, language) in &code_files {
-            let file_changed = cache.as_ref()
-                .map(|c| c.is_file_changed(file_path))
-                .unwrap_or(true);
-
-            if file_changed {
-                // File changed, rescan
-                if let Err(e) = self.scan_file(file_path, *language, &mut graph) {
-                    eprintln!("⚠️  Failed to scan {}: {}", file_path.display(), e);
-                } else {
-                    file_count += 1;
-                    changed_count += 1;
+        eprintln!(
+            "📂 Found {} files (TypeScript: {}, Vue/Svelte: {}, Rust: {})",
+            code_files.len(), ts_count, sfc_count, rs_count
+        );
+
+        // Parse changed files exactly once, in parallel (one parser set per worker);
+        // unchanged files reuse the cached extraction without being parsed.
+        // `collect` keeps the input order, so the merge below is deterministic.
+        let cached_file = |path: &Path| cache.as_ref().and_then(|c| c.files.get(&path.display().to_string()));
+        let total = code_files.len() as u64;
+        // About 20 reports per scan, whatever its size
+        let report_every = (total / 20).max(1);
+        // Counted under a lock so reports arrive in increasing order
+        let done = Mutex::new(0u64);
+        self.progress.report(0, Some(total), "Parsing files");
+        let extractions: Vec<Result<(FileExtraction, bool)>> = code_files
+            .par_iter()
+            .map_init(ParserSet::default, |parsers, (path, language)| {
+                self.progress.check()?;
+                let trusted = changed_files.is_some_and(|files| !files.contains(path));
+                let result = Self::load_or_extract(parsers, path, *language, cached_file(path), trusted);
+                let mut finished = done.lock().unwrap();
+                *finished += 1;
+                if finished.is_multiple_of(report_every) || *finished == total {
+                    self.progress.report(*finished, Some(total), "Parsing files");
                 }
-            } else {
-                // File unchanged, use cached nodes
-                if let Some(ref c) = cache {
-                    let path_str = file_path.display().to_string();
-                    for cached_node in c.cached_nodes.iter().filter(|n| n.file_path == path_str) {
-                        let node = CodeNode {
-                            id: 0,
-                            name: cached_node.name.clone(),
-                            node_type: NodeType::Function, // Simplified
-                            file_path: PathBuf::from(&cached_node.file_path),
-                            line_range: cached_node.line_range,
-                            is_exported: cached_node.is_exported,
-                            is_used: false,
-                            signature: cached_node.signature.clone(),
-                        };
-                        graph.add_node(node);
+                result
+            })
+            .collect();
+        self.progress.check()?;
+
+        let mut extracted = Vec::with_capacity(extractions.len());
+        let mut changed_count = 0;
+        for ((file_path, _), result) in code_files.iter().zip(extractions) {
+            match result {
+                Ok((extraction, changed)) => {
+                    if changed {
+                        changed_count += 1;
                     }
-                    cached_count += 1;
-                    file_count += 1;
+                    extracted.push((extraction, changed));
                 }
+                Err(e) => eprintln!("⚠️  Failed to scan {}: {}", file_path.display(), e),
             }
+        }
+        let file_count = extracted.len();
+
+        // Rust module paths come from Cargo.toml targets and `mod` declarations
+        let module_tree = if rs_count > 0 {
+            let declarations: HashMap<PathBuf, Vec<ModDeclaration>> = extracted
+                .iter()
+                .filter(|(e, _)| e.language == Language::Rust)
+                .map(|(e, _)| (e.path.clone(), e.mod_declarations.clone()))
+                .collect();
+            RustModuleTree::build_with_declarations(dir, &declarations)
+        } else {
+            RustModuleTree::default()
+        };
 
-            // Update hash cache
-            if let Ok(hash) = ScanCache::hash_file(file_path) {
-                new_cache.file_hashes.insert(file_path.display().to_string(), hash);
-            }
+        let workspace = Workspace::detect(dir);
+        new_cache.packages = workspace
+            .npm_packages()
+            .map(|p| (p.name.clone(), p.root.clone()))
+            .collect();
+
+        // First pass: definitions, in file order so node ids are stable
+        let mut definitions = Vec::with_capacity(file_count);
+        for (extraction, _) in &extracted {
+            let ids = self.add_definitions(dir, extraction, &module_tree, &workspace, &mut graph);
+            let defs: Vec<CachedDefinition> = ids
+                .iter()
+                .filter_map(|id| graph.nodes.get(id))
+                .map(|node| CachedDefinition {
+                    symbol_id: node.symbol_id.clone(),
+                    name: node.name.clone(),
+                    qualified_name: node.qualified_name.clone(),
+                })
+                .collect();
+            definitions.push(defs);
         }
 
         if self.incremental && cache.is_some() {
-            println!("✅ Scanned {} files ({} changed, {} cached)", file_count, changed_count, cached_count);
+            eprintln!("✅ Scanned {} files ({} changed, {} unchanged)", file_count, changed_count, file_count - changed_count);
         } else {
-            println!("✅ Scanned {} files (full scan)", file_count);
+            eprintln!("✅ Scanned {} files (full scan)", file_count);
+        }
+        eprintln!("📊 Found {} nodes", graph.nodes.len());
+
+        // Names whose definitions appeared, disappeared or were requalified since the
+        // cached scan: edges of unchanged files referring to them must be re-resolved
+        let mut changed_names: HashSet<String> = HashSet::new();
+        let mut file_set_changed = false;
+        if let Some(cache) = &cache {
+            // Renamed or moved packages, or edited `paths`/`baseUrl`/`exports`,
+            // change how imports resolve
+            file_set_changed =
+                cache.packages != new_cache.packages || cache.resolver_config != new_cache.resolver_config;
+            let current: HashSet<String> = extracted.iter().map(|(e, _)| e.path.display().to_string()).collect();
+            for (path, cached) in &cache.files {
+                if !current.contains(path) {
+                    file_set_changed = true;
+                    changed_names.extend(cached.definitions.iter().map(|d| d.name.clone()));
+                }
+            }
+            for ((extraction, _), defs) in extracted.iter().zip(&definitions) {
+                let Some(cached) = cache.files.get(&extraction.path.display().to_string()) else {
+                    file_set_changed = true;
+                    changed_names.extend(defs.iter().map(|d| d.name.clone()));
+                    continue;
+                };
+                let old: HashSet<&CachedDefinition> = cached.definitions.iter().collect();
+                let new: HashSet<&CachedDefinition> = defs.iter().collect();
+                changed_names.extend(old.symmetric_difference(&new).map(|d| d.name.clone()));
+            }
         }
-        println!("📊 Found {} nodes", graph.nodes.len());
-
-        // Second pass: build dependency edges
-        for (file_path, language) in &code_files {
-            if let Err(e) = self.build_dependencies(file_path, *language, &mut graph) {
-                eprintln!(
-                    "⚠️  Failed to build dependencies for {}: {}",
-                    file_path.display(),
-                    e
-                );
+        self.last_changed = cache.is_none() || changed_count > 0 || file_set_changed;
+
+        // Second pass: dependency edges, reusing cached edges where nothing they
+        // depend on has changed
+        self.progress.check()?;
+        self.progress.report(total, Some(total), "Resolving dependencies");
+        let packages = new_cache.packages.iter().map(|(name, root)| (name.clone(), root.clone())).collect();
+        let mut resolver = ModuleResolver::new(dir).with_packages(packages);
+        let mut resolved_count = 0;
+        for ((extraction, changed), defs) in extracted.into_iter().zip(definitions) {
+            let path_str = extraction.path.display().to_string();
+            let module_path = match extraction.language {
+                Language::Rust => module_tree.module_path(&extraction.path),
+                Language::TypeScript => String::new(),
+            };
+            let reusable = cached_file(&extraction.path).filter(|cached| {
+                !changed
+                    && cached.module_path == module_path
+                    // Added or removed files can change how imports resolve
+                    && (!file_set_changed || extraction.imports.is_empty())
+                    && !extraction.references_any(&changed_names)
+            });
+            let reused = reusable.and_then(|cached| Self::restore_edges(&graph, &cached.edges));
+
+            let edges = match reused {
+                Some(edges) => {
+                    for edge in edges {
+                        graph.add_edge(edge);
+                    }
+                    reusable.map(|cached| cached.edges.clone()).unwrap_or_default()
+                }
+                None => {
+                    resolved_count += 1;
+                    let start = graph.edges.len();
+                    self.add_dependencies(&extraction, &mut resolver, &module_tree, &mut graph);
+                    graph.edges[start..]
+                        .iter()
+                        .map(|edge| CachedEdge {
+                            from: graph.nodes.get(&edge.from).map(|n| n.symbol_id.clone()),
+                            to: graph.nodes.get(&edge.to).map(|n| n.symbol_id.clone()).unwrap_or_default(),
+                            edge_type: edge.edge_type,
+                            site: edge.site.clone(),
+                        })
+                        .collect()
+                }
+            };
+
+            if self.incremental {
+                new_cache.files.insert(path_str, CachedFile {
+                    extraction,
+                    module_path,
+                    definitions: defs,
+                    edges,
+                });
             }
         }
 
-        println!("🔗 Found {} edges", graph.edges.len());
+        if cache.is_some() {
+            eprintln!("🔗 Found {} edges ({} files re-resolved)", graph.edges.len(), resolved_count);
+        } else {
+            eprintln!("🔗 Found {} edges", graph.edges.len());
+        }
 
         // Save cache for next scan
         if self.incremental {
-            // Convert nodes to cached format
-            for node in graph.nodes.values() {
-                new_cache.cached_nodes.push(CachedNode {
-                    name: node.name.clone(),
-                    node_type: format!("{:?}", node.node_type),
-                    file_path: node.file_path.display().to_string(),
-                    line_range: node.line_range,
-                    is_exported: node.is_exported,
-                    signature: node.signature.clone(),
-                });
-            }
             if let Err(e) = new_cache.save(&cache_path) {
                 eprintln!("⚠️  Failed to save scan cache: {}", e);
             }
+            self.last_scan = Some((dir.to_path_buf(), new_cache));
         }
 
         Ok(graph)
     }
 
-    fn scan_file(&mut self, path: &Path, language: Language, graph: &mut CodeGraph) -> Result<()> {
+    /// Reuse the cached extraction when the content hash matches, otherwise parse.
+    /// `trusted` files are known unchanged and are not even read.
+    /// Returns the extraction and whether the file changed.
+    fn load_or_extract(
+        parsers: &mut ParserSet,
+        path: &Path,
+        language: Language,
+        cached: Option<&CachedFile>,
+        trusted: bool,
+    ) -> Result<(FileExtraction, bool)> {
+        if let Some(cached) = cached.filter(|c| trusted && c.extraction.language == language) {
+            return Ok((cached.extraction.clone(), false));
+        }
+
         let source = std::fs::read_to_string(path)
             .context(format!("Failed to read file: {}", path.display()))?;
+        let content_hash = ScanCache::hash_content(source.as_bytes());
+
+        if let Some(cached) = cached {
+            if cached.extraction.content_hash == content_hash && cached.extraction.language == language {
+                return Ok((cached.extraction.clone(), false));
+            }
+        }
+
+        Ok((Self::extract_file(parsers, path, language, source, content_hash)?, true))
+    }
 
-        let mut parser = CodeParser::new(language)?;
+    /// Parse one file, extracting definitions, calls and imports together.
+    /// For Vue/Svelte components only the `<script>` blocks are parsed (line numbers
+    /// preserved) and template references are collected separately.
+    fn extract_file(
+        parsers: &mut ParserSet,
+        path: &Path,
+        language: Language,
+        source: String,
+        content_hash: String,
+    ) -> Result<FileExtraction> {
+        let (script, template_refs) = match SfcKind::from_path(path) {
+            Some(kind) => {
+                let doc = parse_sfc(&source, kind);
+                (doc.script_source, doc.template_refs)
+            }
+            None => (source, Vec::new()),
+        };
+
+        let parser = parsers.get(language)?;
         let tree = parser
-            .parse_file(path)
+            .parse_source(&script)
             .context("Failed to parse file")?;
 
-        let functions = parser.extract_functions(&tree, &source);
+        Ok(FileExtraction {
+            path: path.to_path_buf(),
+            language,
+            content_hash,
+            functions: parser.extract_functions(&tree, &script),
+            calls: parser.extract_calls(&tree, &script),
+            imports: match language {
+                Language::TypeScript => parser.extract_imports(&tree, &script),
+                Language::Rust => Vec::new(),
+            },
+            template_refs,
+            mod_declarations: match language {
+                Language::Rust => extract_mod_declarations(&tree, &script),
+                Language::TypeScript => Vec::new(),
+            },
+        })
+    }
+
+    /// Map cached edges back to node ids; `None` if any endpoint no longer exists
+    fn restore_edges(graph: &CodeGraph, cached: &[CachedEdge]) -> Option<Vec<DependencyEdge>> {
+        let node_id = |symbol_id: &str| graph.find_by_symbol_id(symbol_id).map(|n| n.id);
+        cached
+            .iter()
+            .map(|edge| {
+                let from = match &edge.from {
+                    Some(symbol_id) => node_id(symbol_id)?,
+                    None => usize::MAX,
+                };
+                Some(DependencyEdge {
+                    from,
+                    to: node_id(&edge.to)?,
+                    edge_type: edge.edge_type,
+                    site: edge.site.clone(),
+                })
+            })
+            .collect()
+    }
+
+    fn add_definitions(
+        &self,
+        root: &Path,
+        extraction: &FileExtraction,
+        module_tree: &RustModuleTree,
+        workspace: &Workspace,
+        graph: &mut CodeGraph,
+    ) -> Vec<NodeId> {
+        let path = &extraction.path;
+        let language = extraction.language;
+        let package = workspace.package_for(path).map(|p| p.name.clone()).unwrap_or_default();
+        let module_path = match language {
+            Language::Rust => Some(module_tree.module_path(path)),
+            Language::TypeScript => None,
+        };
 
-        for func in functions {
+        // Symbol ids use the path relative to the scan root so they survive rescans
+        let root = normalize_path(root);
+        let relative_path = path.strip_prefix(&root).unwrap_or(path).to_string_lossy().to_string();
+        let mut ordinals: HashMap<(Vec<String>, String), usize> = HashMap::new();
+        let mut ids = Vec::with_capacity(extraction.functions.len());
+
+        for func in &extraction.functions {
+            let ordinal = ordinals
+                .entry((func.scope.clone(), func.name.clone()))
+                .and_modify(|n| *n += 1)
+                .or_insert(1);
+            let symbol_id = make_symbol_id(language.tag(), &relative_path, &func.scope, &func.name, *ordinal);
+            let qualified_name = module_path
+                .as_ref()
+                .map(|module| {
+                    std::iter::once(module.as_str())
+                        .chain(func.scope.iter().map(String::as_str))
+                        .chain(std::iter::once(func.name.as_str()))
+                        .collect::<Vec<_>>()
+                        .join("::")
+                })
+                .unwrap_or_default();
             let node = CodeNode {
                 id: 0, // Will be set by add_node
-                name: func.name,
-                node_type: NodeType::Function,
+                name: func.name.clone(),
+                node_type: func.node_type,
                 file_path: path.to_path_buf(),
                 line_range: func.line_range,
                 is_exported: func.is_exported,
                 is_used: false,
-                signature: func.signature,
+                signature: func.signature.clone(),
+                qualified_name,
+                symbol_id,
+                package: package.clone(),
             };
-            graph.add_node(node);
+            ids.push(graph.add_node(node));
         }
-
-        Ok(())
+        ids
     }
 
-    fn build_dependencies(&mut self, path: &Path, language: Language, graph: &mut CodeGraph) -> Result<()> {
-        let source = std::fs::read_to_string(path)
-            .context(format!("Failed to read file: {}", path.display()))?;
-
-        let mut parser = CodeParser::new(language)?;
-        let tree = parser
-            .parse_file(path)
-            .context("Failed to parse file")?;
+    fn add_dependencies(
+        &self,
+        extraction: &FileExtraction,
+        resolver: &mut ModuleResolver,
+        module_tree: &RustModuleTree,
+        graph: &mut CodeGraph,
+    ) {
+        let path = extraction.path.as_path();
+        let language = extraction.language;
+        let site = |(line, column): (usize, usize)| {
+            Some(SourcePosition {
+                file_path: path.to_path_buf(),
+                line,
+                column,
+            })
+        };
 
-        // Extract function calls
-        let calls = parser.extract_calls(&tree, &source);
+        // Resolve imports to files so imported names bind to the right definition
+        let mut imported: HashMap<String, PathBuf> = HashMap::new();
+        for import in &extraction.imports {
+            let Resolution::File(target_file) = resolver.resolve(path, &import.module_name) else {
+                continue;
+            };
+            for (i, name) in import.aliases.iter().enumerate() {
+                if let Some(target_id) = self.find_node_in_file(graph, &target_file, name) {
+                    graph.add_edge(DependencyEdge {
+                        from: usize::MAX,
+                        to: target_id,
+                        edge_type: EdgeType::Imports,
+                        site: import.positions.get(i).copied().and_then(site),
+                    });
+                }
+                imported.insert(name.clone(), target_file.clone());
+            }
+        }
 
         // Find matching nodes and create edges
-        for call in calls {
+        for call in &extraction.calls {
             // Find the caller node (if inside a function)
             let caller_id = self.find_node_at_line(graph, path, call.caller_line);
 
-            // Find the callee node by name
-            if let Some(callee_id) = self.find_node_by_name(graph, &call.callee_name) {
+            // Find the callee node: qualified path, imported definition, then same file, then by name
+            let callee = call
+                .path
+                .as_ref()
+                .filter(|_| language == Language::Rust)
+                .and_then(|call_path| {
+                    let module = module_tree.module_path(path);
+                    self.find_node_by_path(graph, &module_tree.absolute_path(&module, call_path), call_path)
+                })
+                .or_else(|| {
+                    imported
+                        .get(&call.callee_name)
+                        .and_then(|file| self.find_node_in_file(graph, file, &call.callee_name))
+                })
+                .or_else(|| self.find_node_in_file(graph, path, &call.callee_name))
+                .or_else(|| self.find_node_by_name(graph, &call.callee_name));
+            if let Some(callee_id) = callee {
                 if let Some(caller_id) = caller_id {
                     // Call from within a function
                     graph.add_edge(DependencyEdge {
                         from: caller_id,
                         to: callee_id,
                         edge_type: EdgeType::Calls,
+                        site: site(call.position),
                     });
                 } else {
                     // Call from top-level (entry point)
@@ -275,28 +647,230 @@ impl Scanner {
                         from: usize::MAX, // Special marker for top-level
                         to: callee_id,
                         edge_type: EdgeType::Calls,
+                        site: site(call.position),
                     });
                 }
             }
         }
 
-        Ok(())
+        // Identifiers used from a component template count as top-level references.
+        // Prefer definitions in the component itself over same-named ones elsewhere.
+        for template_ref in &extraction.template_refs {
+            let target = self
+                .find_node_in_file(graph, path, &template_ref.name)
+                .or_else(|| self.find_node_by_name(graph, &template_ref.name));
+            if let Some(target_id) = target {
+                graph.add_edge(DependencyEdge {
+                    from: usize::MAX,
+                    to: target_id,
+                    edge_type: EdgeType::References,
+                    site: site((template_ref.line, template_ref.column)),
+                });
+            }
+        }
     }
 
+    // Lookups go through the graph indices; equal candidates resolve to the
+    // lowest node id so edges do not depend on HashMap iteration order.
+
+    /// Innermost definition containing `line`
     fn find_node_at_line(&self, graph: &CodeGraph, path: &Path, line: usize) -> Option<usize> {
-        graph.nodes.iter().find_map(|(id, node)| {
-            if node.file_path == path && line >= node.line_range.0 && line <= node.line_range.1 {
-                Some(*id)
-            } else {
-                None
-            }
-        })
+        graph.node_at_line(path, line).map(|node| node.id)
     }
 
-    fn find_node_by_name(&self, graph: &CodeGraph, name: &str) -> Option<usize> {
+    fn find_node_in_file(&self, graph: &CodeGraph, path: &Path, name: &str) -> Option<usize> {
         graph
-            .nodes
-            .iter()
-            .find_map(|(id, node)| if node.name == name { Some(*id) } else { None })
+            .nodes_in_file(path)
+            .filter(|node| node.name == name)
+            .map(|node| node.id)
+            .min()
+    }
+
+    /// Find a node by absolute qualified path, falling back to a suffix match
+    /// for paths through `use` imports or external re-exports (`Type::method`)
+    fn find_node_by_path(&self, graph: &CodeGraph, absolute: &str, written: &str) -> Option<usize> {
+        if let Some(node) = graph.nodes_by_qualified_name(absolute).next() {
+            return Some(node.id);
+        }
+        if written.starts_with("super::") {
+            return None;
+        }
+        let suffix = format!("::{}", written.trim_start_matches("crate::").trim_start_matches("self::"));
+        let name = written.rsplit("::").next().unwrap_or(written);
+        graph
+            .nodes_by_name(name)
+            .find(|node| node.qualified_name.ends_with(&suffix))
+            .map(|node| node.id)
+    }
+
+    fn find_node_by_name(&self, graph: &CodeGraph, name: &str) -> Option<usize> {
+        graph.nodes_by_name(name).next().map(|node| node.id)
+    }
+}
+
+/// tsconfig/jsconfig files (including `tsconfig.base.json` style ones) and package.json
+fn is_resolver_config(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+        return false;
+    };
+    name == "package.json" || ((name.starts_with("tsconfig") || name.starts_with("jsconfig")) && name.ends_with(".json"))
+}
+
+/// One hash over the paths and contents of the resolver config files
+fn hash_resolver_config(files: &[PathBuf]) -> String {
+    let mut hasher = Sha256::new();
+    for file in files {
+        hasher.update(file.display().to_string().as_bytes());
+        hasher.update(ScanCache::hash_file(file).unwrap_or_default().as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_project, write};
+    use crate::graph::{NodeType, PackageDependency};
+
+    #[test]
+    fn test_parallel_scan_is_deterministic() {
+        let test_dir = Path::new("test_rust_project");
+        if !test_dir.exists() {
+            return;
+        }
+
+        let snapshot = |graph: &CodeGraph| {
+            let mut nodes: Vec<(usize, String)> = graph
+                .nodes
+                .values()
+                .map(|n| (n.id, n.symbol_id.clone()))
+                .collect();
+            nodes.sort();
+            let edges: Vec<(usize, usize)> = graph.edges.iter().map(|e| (e.from, e.to)).collect();
+            (nodes, edges)
+        };
+
+        let first = Scanner::with_incremental(false).unwrap().scan_directory(test_dir).unwrap();
+        for _ in 0..3 {
+            let again = Scanner::with_incremental(false).unwrap().scan_directory(test_dir).unwrap();
+            assert_eq!(snapshot(&first), snapshot(&again));
+        }
+        assert!(!first.edges.is_empty());
+    }
+
+    #[test]
+    fn test_scan_reports_progress_and_stops_when_cancelled() {
+        let test_dir = Path::new("test_rust_project");
+        if !test_dir.exists() {
+            return;
+        }
+
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let sink = std::sync::Arc::clone(&reports);
+        let mut scanner = Scanner::with_incremental(false).unwrap();
+        scanner.set_progress(Progress::new(std::sync::Arc::new(move |done, total, _: &str| {
+            sink.lock().unwrap().push((done, total));
+        })));
+        scanner.scan_directory(test_dir).unwrap();
+        let reports = reports.lock().unwrap();
+        let (done, total) = *reports.last().unwrap();
+        assert_eq!(Some(done), total);
+
+        let progress = Progress::default();
+        progress.cancel();
+        let mut scanner = Scanner::with_incremental(false).unwrap();
+        scanner.set_progress(progress);
+        let error = scanner.scan_directory(test_dir).unwrap_err();
+        assert!(crate::progress::is_cancelled(&error));
+    }
+
+    #[test]
+    fn test_incremental_scan_matches_full_scan() {
+        let temp = temp_project();
+        let root = temp.path();
+        write(root, "app.ts", "import { helper } from './util';\nexport function main() {\n  helper();\n  other();\n}\n");
+        write(root, "util.ts", "export function helper() {}\n");
+        write(root, "shapes.ts", "export class Shape {\n  area() {\n    return helper();\n  }\n}\n");
+
+        let snapshot = |graph: &CodeGraph| {
+            let symbol = |id: NodeId| graph.nodes.get(&id).map(|n| n.symbol_id.clone()).unwrap_or_default();
+            let mut nodes: Vec<(String, String)> = graph
+                .nodes
+                .values()
+                .map(|n| (n.symbol_id.clone(), format!("{:?}", n.node_type)))
+                .collect();
+            nodes.sort();
+            let edges: Vec<(String, String, String)> = graph
+                .edges
+                .iter()
+                .map(|e| (symbol(e.from), symbol(e.to), format!("{:?}", e.edge_type)))
+                .collect();
+            (nodes, edges)
+        };
+
+        // Warm the cache, then an unchanged rescan must give the same graph
+        let cold = Scanner::new().unwrap().scan_directory(root).unwrap();
+        let warm = Scanner::new().unwrap().scan_directory(root).unwrap();
+        assert_eq!(snapshot(&cold), snapshot(&warm));
+        assert!(warm.nodes.values().any(|n| n.name == "area" && n.node_type == NodeType::Method));
+
+        // A new definition of `other` must be picked up by the unchanged app.ts
+        write(root, "util.ts", "export function helper() {}\nexport function other() {}\n");
+        let incremental = Scanner::new().unwrap().scan_directory(root).unwrap();
+        let full = Scanner::with_incremental(false).unwrap().scan_directory(root).unwrap();
+        assert_eq!(snapshot(&incremental), snapshot(&full));
+        let other = incremental.nodes_by_name("other").next().unwrap().id;
+        assert_eq!(incremental.incoming_edges(other).count(), 1);
+    }
+
+    #[test]
+    fn test_incremental_scan_follows_tsconfig_paths_changes() {
+        let temp = temp_project();
+        let root = temp.path();
+        write(root, "tsconfig.json", r#"{ "compilerOptions": { "baseUrl": ".", "paths": { "@lib/*": ["old/*"] } } }"#);
+        write(root, "app.ts", "import { helper } from '@lib/util';\nexport function main() {\n  helper();\n}\n");
+        write(root, "old/util.ts", "export function helper() {}\n");
+        write(root, "new/util.ts", "export function helper() {}\n");
+
+        let helper_file = |graph: &CodeGraph| {
+            let main = graph.nodes_by_name("main").next().unwrap().id;
+            let mut targets: Vec<PathBuf> = graph
+                .outgoing_edges(main)
+                .filter_map(|e| graph.nodes.get(&e.to))
+                .map(|n| n.file_path.strip_prefix(root).unwrap().to_path_buf())
+                .collect();
+            targets.sort();
+            targets
+        };
+        let cold = Scanner::new().unwrap().scan_directory(root).unwrap();
+        assert_eq!(helper_file(&cold), vec![PathBuf::from("old/util.ts")]);
+
+        // Only the config changes: the cached edges of app.ts must not be reused
+        write(root, "tsconfig.json", r#"{ "compilerOptions": { "baseUrl": ".", "paths": { "@lib/*": ["new/*"] } } }"#);
+        let warm = Scanner::new().unwrap().scan_directory(root).unwrap();
+        assert_eq!(helper_file(&warm), vec![PathBuf::from("new/util.ts")]);
+    }
+
+    #[test]
+    fn test_workspace_packages_and_cross_package_edges() {
+        let temp = temp_project();
+        let root = temp.path();
+        write(root, "package.json", r#"{ "name": "monorepo", "workspaces": ["packages/*", "apps/*"] }"#);
+        write(root, "packages/ui/package.json", r#"{ "name": "@acme/ui", "main": "dist/index.js" }"#);
+        write(root, "packages/ui/src/index.ts", "export function renderButton() {}\n");
+        write(root, "apps/web/package.json", r#"{ "name": "web" }"#);
+        write(root, "apps/web/src/main.ts", "import { renderButton } from '@acme/ui';\nfunction main() {\n  renderButton();\n}\n");
+
+        let graph = Scanner::with_incremental(false).unwrap().scan_directory(root).unwrap();
+        let package_of = |name: &str| graph.nodes_by_name(name).next().map(|n| n.package.clone());
+        assert_eq!(package_of("renderButton").as_deref(), Some("@acme/ui"));
+        assert_eq!(package_of("main").as_deref(), Some("web"));
+
+        let render = graph.nodes_by_name("renderButton").next().unwrap().id;
+        assert!(graph.incoming_edges(render).any(|e| e.from != usize::MAX));
+        assert_eq!(
+            graph.package_dependencies(),
+            vec![PackageDependency { from: "web".to_string(), to: "@acme/ui".to_string(), edges: 1 }]
+        );
     }
 }
@@ -4,6 +4,7 @@
 use crate::graph::{CodeGraph, NodeId, TraversalResult};
 use crate::search::index::CodeMetadata;
 use serde::{Deserialize, Serialize};
+use std::sync::Arc;
 
 #[cfg(feature = "semantic-search")]
 use crate::embedding::EmbeddingGenerator;
@@ -79,13 +80,13 @@ pub struct GraphSearchResult {
 
 /// グラフベースのセマンティック検索
 pub struct GraphSearcher {
-    graph: CodeGraph,
+    graph: Arc<CodeGraph>,
 }
 
 impl GraphSearcher {
-    /// 新しい GraphSearcher を作成
-    pub fn new(graph: CodeGraph) -> Self {
-        Self { graph }
+    /// 新しい GraphSearcher を作成（`Arc` を渡せばグラフはコピーされない）
+    pub fn new(graph: impl Into<Arc<CodeGraph>>) -> Self {
+        Self { graph: graph.into() }
     }
 
     pub fn search(&self, query: &str, top_k: usize, depth: usize) -> Vec<GraphSearchResult> {
@@ -223,10 +224,11 @@ impl GraphSearcher {
                 let (edge, direction) = if i > 0 {
                     let prev_id = path[i - 1];
                     // 出力エッジを探す
-                    let edge_info = self.graph.edges.iter().find(|e| {
-                        (e.from == prev_id && e.to == node_id)
-                            || (e.to == prev_id && e.from == node_id)
-                    });
+                    let edge_info = self
+                        .graph
+                        .outgoing_edges(prev_id)
+                        .find(|e| e.to == node_id)
+                        .or_else(|| self.graph.incoming_edges(prev_id).find(|e| e.from == node_id));
 
                     if let Some(e) = edge_info {
                         let dir = if e.from == prev_id {
@@ -359,9 +361,7 @@ impl GraphSearcher {
                             code_snippet: String::new(),
                             dependencies: self
                                 .graph
-                                .edges
-                                .iter()
-                                .filter(|e| e.from == tr.node_id)
+                                .outgoing_edges(tr.node_id)
                                 .filter_map(|e| self.graph.get_node(e.to))
                                 .map(|n| n.name.clone())
                                 .collect(),
@@ -413,6 +413,9 @@ mod tests {
             is_exported: true,
             is_used: true,
             signature: "function auth()".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         });
 
         let user_db_id = graph.add_node(CodeNode {
@@ -424,6 +427,9 @@ mod tests {
             is_exported: true,
             is_used: true,
             signature: "function user_db()".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         });
 
         let config_id = graph.add_node(CodeNode {
@@ -435,6 +441,9 @@ mod tests {
             is_exported: true,
             is_used: true,
             signature: "const config".to_string(),
+            qualified_name: String::new(),
+            symbol_id: String::new(),
+            package: String::new(),
         });
 
         // エッジを追加
@@ -442,12 +451,14 @@ mod tests {
             from: auth_id,
             to: user_db_id,
             edge_type: EdgeType::Calls,
+            site: None,
         });
 
         graph.add_edge(DependencyEdge {
             from: auth_id,
             to: config_id,
             edge_type: EdgeType::References,
+            site: None,
         });
 
         graph
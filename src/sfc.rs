@@ -0,0 +1,324 @@
+// Single-file component support (Vue / Svelte)
+// <script> ブロックを抽出し、テンプレートから参照される識別子を収集する
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+
+/// Single-file component flavour
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SfcKind {
+    Vue,
+    Svelte,
+}
+
+impl SfcKind {
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        match ext {
+            "vue" => Some(SfcKind::Vue),
+            "svelte" => Some(SfcKind::Svelte),
+            _ => None,
+        }
+    }
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(Self::from_extension)
+    }
+}
+
+/// Identifier referenced from component markup
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemplateReference {
+    pub name: String,
+    /// 1-based line in the original component file
+    pub line: usize,
+    /// 1-based column (in characters) of the first reference on that line
+    pub column: usize,
+}
+
+/// Result of splitting a component file
+#[derive(Debug, Clone)]
+pub struct SfcDocument {
+    /// Contents of every `<script>` block, with everything else blanked out.
+    /// Newlines and byte offsets are preserved, so tree-sitter positions
+    /// match the original file.
+    pub script_source: String,
+    /// Identifiers used by the template (interpolations, directives, components)
+    pub template_refs: Vec<TemplateReference>,
+}
+
+/// Words that appear in template expressions but never name a definition
+const TEMPLATE_KEYWORDS: &[&str] = &[
+    "true", "false", "null", "undefined", "this", "typeof", "instanceof",
+    "in", "of", "new", "void", "delete", "return", "let", "const", "var",
+    "function", "class", "extends", "super", "import", "export", "default",
+    "if", "else", "each", "as", "await", "then", "catch", "key", "html",
+    "debug", "render", "snippet", "async", "yield", "NaN", "Infinity",
+    "$event", "$slots", "$props", "$attrs", "$emit", "$refs",
+];
+
+/// Split a Vue or Svelte component into script source and template references
+pub fn parse_sfc(source: &str, kind: SfcKind) -> SfcDocument {
+    let script_re = Regex::new(r"(?is)<script\b[^>]*>(.*?)</script\s*>").unwrap();
+    let style_re = Regex::new(r"(?is)<style\b[^>]*>.*?</style\s*>").unwrap();
+    let comment_re = Regex::new(r"(?s)<!--.*?-->").unwrap();
+
+    let mut script_ranges: Vec<Range<usize>> = Vec::new();
+    let mut non_template: Vec<Range<usize>> = Vec::new();
+
+    for cap in script_re.captures_iter(source) {
+        if let Some(body) = cap.get(1) {
+            script_ranges.push(body.range());
+        }
+        non_template.push(cap.get(0).unwrap().range());
+    }
+    for m in style_re.find_iter(source) {
+        non_template.push(m.range());
+    }
+    for m in comment_re.find_iter(source) {
+        non_template.push(m.range());
+    }
+
+    let script_source = mask_outside(source, &script_ranges);
+    let template = mask_outside_inverse(source, &non_template);
+    let template_refs = extract_template_refs(&template, kind);
+
+    SfcDocument {
+        script_source,
+        template_refs,
+    }
+}
+
+/// Keep only the bytes inside `keep`, replacing others with spaces (newlines kept)
+fn mask_outside(source: &str, keep: &[Range<usize>]) -> String {
+    mask(source, |pos| keep.iter().any(|r| r.contains(&pos)))
+}
+
+/// Blank out the bytes inside `drop`, keeping everything else
+fn mask_outside_inverse(source: &str, drop: &[Range<usize>]) -> String {
+    mask(source, |pos| !drop.iter().any(|r| r.contains(&pos)))
+}
+
+fn mask(source: &str, keep: impl Fn(usize) -> bool) -> String {
+    let mut out = String::with_capacity(source.len());
+    for (pos, ch) in source.char_indices() {
+        if ch == '\n' || keep(pos) {
+            out.push(ch);
+        } else {
+            // 複数バイト文字も同じバイト数の空白に置き換えてオフセットを維持する
+            for _ in 0..ch.len_utf8() {
+                out.push(' ');
+            }
+        }
+    }
+    out
+}
+
+/// Collect identifiers referenced from the (masked) template text
+fn extract_template_refs(template: &str, kind: SfcKind) -> Vec<TemplateReference> {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(template.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset);
+
+    // (expression text, byte offset in template)
+    let mut expressions: Vec<(&str, usize)> = Vec::new();
+
+    match kind {
+        SfcKind::Vue => {
+            let mustache_re = Regex::new(r"(?s)\{\{(.*?)\}\}").unwrap();
+            let directive_re = Regex::new(
+                r#"(?:\bv-[\w-]+(?::[\w.\-\[\]]+)?|[:@#][\w.\-\[\]]*)\s*=\s*(?:"([^"]*)"|'([^']*)')"#,
+            )
+            .unwrap();
+            for cap in mustache_re.captures_iter(template) {
+                let m = cap.get(1).unwrap();
+                expressions.push((m.as_str(), m.start()));
+            }
+            for cap in directive_re.captures_iter(template) {
+                if let Some(m) = cap.get(1).or_else(|| cap.get(2)) {
+                    expressions.push((m.as_str(), m.start()));
+                }
+            }
+        }
+        SfcKind::Svelte => {
+            let brace_re = Regex::new(r"\{([^{}]*)\}").unwrap();
+            for cap in brace_re.captures_iter(template) {
+                let m = cap.get(1).unwrap();
+                // {#if ...} / {:else if ...} / {@html ...} / {/if} のブロック記号を除去
+                let text = m.as_str();
+                let trimmed = text.trim_start_matches(['#', ':', '@', '/']);
+                let skipped = text.len() - trimmed.len();
+                expressions.push((trimmed, m.start() + skipped));
+            }
+        }
+    }
+
+    let ident_re = Regex::new(r"[A-Za-z_$][\w$]*").unwrap();
+    let mut seen: HashSet<(String, usize)> = HashSet::new();
+    let mut refs = Vec::new();
+
+    let mut push_ref = |name: &str, offset: usize, refs: &mut Vec<TemplateReference>| {
+        if TEMPLATE_KEYWORDS.contains(&name) {
+            return;
+        }
+        let line = line_of(offset);
+        if seen.insert((name.to_string(), line)) {
+            let line_start = line_starts[line - 1];
+            let column = template.get(line_start..offset).map_or(0, |prefix| prefix.chars().count()) + 1;
+            refs.push(TemplateReference {
+                name: name.to_string(),
+                line,
+                column,
+            });
+        }
+    };
+
+    for (expr, base) in expressions {
+        let stripped = blank_string_literals(expr);
+        for m in ident_re.find_iter(&stripped) {
+            // Skip member accesses (`user.name` -> only `user`)
+            let before = stripped[..m.start()].trim_end();
+            if before.ends_with('.') && !before.ends_with("...") {
+                continue;
+            }
+            push_ref(m.as_str(), base + m.start(), &mut refs);
+        }
+    }
+
+    // Component tags: <UserCard> or <user-card> (Vue kebab-case)
+    let tag_re = Regex::new(r"<([A-Za-z][\w-]*)").unwrap();
+    for cap in tag_re.captures_iter(template) {
+        let m = cap.get(1).unwrap();
+        let tag = m.as_str();
+        if tag.starts_with(|c: char| c.is_ascii_uppercase()) {
+            push_ref(tag, m.start(), &mut refs);
+        } else if kind == SfcKind::Vue && tag.contains('-') {
+            let pascal: String = tag
+                .split('-')
+                .filter(|part| !part.is_empty())
+                .map(|part| {
+                    let mut chars = part.chars();
+                    match chars.next() {
+                        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                        None => String::new(),
+                    }
+                })
+                .collect();
+            push_ref(&pascal, m.start(), &mut refs);
+        }
+    }
+
+    refs.sort_by(|a, b| a.line.cmp(&b.line).then_with(|| a.name.cmp(&b.name)));
+    refs
+}
+
+/// Replace the contents of string literals with spaces so their words are not treated as identifiers
+fn blank_string_literals(expr: &str) -> String {
+    let mut out = String::with_capacity(expr.len());
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+
+    for ch in expr.chars() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if ch == '\\' {
+                    escaped = true;
+                } else if ch == q {
+                    quote = None;
+                    out.push(ch);
+                    continue;
+                }
+                for _ in 0..ch.len_utf8() {
+                    out.push(' ');
+                }
+            }
+            None => {
+                if matches!(ch, '\'' | '"' | '`') {
+                    quote = Some(ch);
+                }
+                out.push(ch);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{CodeParser, Language};
+
+    #[test]
+    fn test_vue_script_offsets_and_template_refs() {
+        let source = r#"<template>
+  <div @click="handleClick">
+    {{ formatName(user.name) }}
+    <user-card :item="selected" />
+  </div>
+</template>
+
+<script setup lang="ts">
+function handleClick() {}
+
+function formatName(name: string) {
+  return name.trim();
+}
+</script>
+
+<style>
+.name { color: red; }
+</style>
+"#;
+        let doc = parse_sfc(source, SfcKind::Vue);
+
+        assert_eq!(doc.script_source.lines().count(), source.lines().count());
+        assert!(!doc.script_source.contains("<template>"));
+
+        let mut parser = CodeParser::new(Language::TypeScript).unwrap();
+        let tree = parser.parse_source(&doc.script_source).unwrap();
+        let functions = parser.extract_functions(&tree, &doc.script_source);
+        let format_name = functions.iter().find(|f| f.name == "formatName").unwrap();
+        assert_eq!(format_name.line_range, (11, 13));
+
+        let names: Vec<(&str, usize)> = doc
+            .template_refs
+            .iter()
+            .map(|r| (r.name.as_str(), r.line))
+            .collect();
+        assert!(names.contains(&("handleClick", 2)));
+        assert!(names.contains(&("formatName", 3)));
+        assert!(names.contains(&("user", 3)));
+        assert!(!names.iter().any(|(n, _)| *n == "name"));
+        assert!(names.contains(&("UserCard", 4)));
+        assert!(names.contains(&("selected", 4)));
+    }
+
+    #[test]
+    fn test_svelte_template_refs() {
+        let source = r#"<script>
+  export let items = [];
+  function remove(item) {}
+</script>
+
+{#each items as item}
+  <button on:click={() => remove(item)}>{label("x y")}</button>
+{/each}
+"#;
+        let doc = parse_sfc(source, SfcKind::Svelte);
+        let names: Vec<&str> = doc.template_refs.iter().map(|r| r.name.as_str()).collect();
+
+        assert!(names.contains(&"items"));
+        assert!(names.contains(&"remove"));
+        assert!(names.contains(&"label"));
+        assert!(!names.contains(&"each"));
+        assert!(!names.contains(&"y"));
+        assert!(doc.script_source.contains("function remove(item) {}"));
+    }
+}
@@ -0,0 +1,30 @@
+// Fixtures shared by the unit tests
+
+use std::path::Path;
+use tempfile::TempDir;
+
+/// An empty project directory, removed when the guard drops (also when an assertion fails)
+pub fn temp_project() -> TempDir {
+    tempfile::Builder::new().prefix("index-chan-").tempdir().unwrap()
+}
+
+/// A temporary copy of a checked-in fixture project, so scans leave no caches in the repository
+pub fn copy_fixture(name: &str) -> TempDir {
+    let temp = temp_project();
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join(name);
+    for entry in std::fs::read_dir(source).unwrap() {
+        let path = entry.unwrap().path();
+        // Only the sources: a `.index-chan` left behind by an older run is not part of the fixture
+        if path.is_file() {
+            std::fs::copy(&path, temp.path().join(path.file_name().unwrap())).unwrap();
+        }
+    }
+    temp
+}
+
+/// Write `content` to `root/rel`, creating the parent directories
+pub fn write(root: &Path, rel: &str, content: &str) {
+    let path = root.join(rel);
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
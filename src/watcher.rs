@@ -0,0 +1,313 @@
+// Watch mode
+// ファイル変更を監視し、変更されたファイルだけ再抽出してグラフと DB を差分更新する
+#![cfg(feature = "db")]
+
+use anyhow::{Context, Result};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, FileIdMap};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+
+use crate::database::GraphDB;
+use crate::detector::{detect_dead_code, DeadCode};
+use crate::graph::CodeGraph;
+use crate::parser::Language;
+use crate::resolver::normalize_path;
+use crate::scanner::Scanner;
+
+/// Files whose edits change module resolution for the whole project
+const PROJECT_FILES: &[&str] = &["Cargo.toml", "tsconfig.json", "jsconfig.json", "package.json"];
+
+/// What a batch of file events requires
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeSet {
+    /// Source files to re-extract
+    Files(HashSet<PathBuf>),
+    /// A manifest or config changed: rescan everything (unchanged files still hit the cache)
+    Full,
+}
+
+/// Dead-code findings that appeared or went away after an update
+#[derive(Debug, Clone, Default)]
+pub struct DeadCodeDelta {
+    pub added: Vec<DeadCode>,
+    pub resolved: Vec<DeadCode>,
+}
+
+/// In-memory graph of a project kept up to date from file events
+pub struct ProjectWatcher {
+    directory: PathBuf,
+    /// Canonical form of `directory`, to map absolute event paths back
+    canonical_root: PathBuf,
+    scanner: Scanner,
+    /// Replaced, not mutated, on every update so readers can keep a snapshot
+    graph: Arc<CodeGraph>,
+    /// Current findings by symbol id
+    dead_code: HashMap<String, DeadCode>,
+}
+
+impl ProjectWatcher {
+    /// Scan the project once to build the initial graph
+    pub fn new(directory: &Path) -> Result<Self> {
+        let canonical_root = directory
+            .canonicalize()
+            .context(format!("Directory not found: {}", directory.display()))?;
+        let mut scanner = Scanner::new()?;
+        let graph = scanner.scan_directory(directory)?;
+        let dead_code = dead_code_by_symbol(&graph);
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            canonical_root,
+            scanner,
+            graph: Arc::new(graph),
+            dead_code,
+        })
+    }
+
+    pub fn graph(&self) -> &CodeGraph {
+        &self.graph
+    }
+
+    /// The current graph, shared without copying it
+    pub fn shared_graph(&self) -> Arc<CodeGraph> {
+        Arc::clone(&self.graph)
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn dead_code_count(&self) -> usize {
+        self.dead_code.len()
+    }
+
+    /// Turn raw event paths into a change set; `None` if nothing relevant changed
+    pub fn classify(&self, paths: &[PathBuf]) -> Option<ChangeSet> {
+        let mut files = HashSet::new();
+        for path in paths {
+            // Event paths are absolute; the graph uses paths under `directory`
+            let relative = path
+                .strip_prefix(&self.canonical_root)
+                .or_else(|_| path.strip_prefix(&self.directory))
+                .unwrap_or(path);
+            if relative.components().any(|c| {
+                matches!(c.as_os_str().to_str(), Some(".index-chan" | ".git" | "node_modules" | "target"))
+            }) {
+                continue;
+            }
+
+            let file_name = relative.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            if PROJECT_FILES.contains(&file_name) || file_name.starts_with("tsconfig.") {
+                return Some(ChangeSet::Full);
+            }
+            let supported = relative
+                .extension()
+                .and_then(|e| e.to_str())
+                .and_then(Language::from_extension)
+                .is_some();
+            if supported {
+                files.insert(normalize_path(&self.directory.join(relative)));
+            }
+        }
+        (!files.is_empty()).then_some(ChangeSet::Files(files))
+    }
+
+    /// Re-extract the changed files and report how dead-code findings moved
+    pub fn apply(&mut self, changes: &ChangeSet) -> Result<DeadCodeDelta> {
+        self.graph = Arc::new(match changes {
+            ChangeSet::Files(files) => self.scanner.rescan(&self.directory, files)?,
+            ChangeSet::Full => self.scanner.scan_directory(&self.directory)?,
+        });
+
+        let current = dead_code_by_symbol(&self.graph);
+        let mut delta = DeadCodeDelta::default();
+        for (key, dead) in &current {
+            if !self.dead_code.contains_key(key) {
+                delta.added.push(dead.clone());
+            }
+        }
+        for (key, dead) in &self.dead_code {
+            if !current.contains_key(key) {
+                delta.resolved.push(dead.clone());
+            }
+        }
+        let location = |d: &DeadCode| (d.node.file_path.clone(), d.node.line_range.0);
+        delta.added.sort_by_key(location);
+        delta.resolved.sort_by_key(location);
+
+        self.dead_code = current;
+        Ok(delta)
+    }
+
+    /// Re-check every file against its content hash and re-extract the stale ones, catching
+    /// up with edits whose events have not arrived yet. True if the graph changed.
+    pub fn sync(&mut self) -> Result<bool> {
+        self.apply(&ChangeSet::Full)?;
+        Ok(self.scanner.last_scan_changed())
+    }
+}
+
+fn dead_code_by_symbol(graph: &CodeGraph) -> HashMap<String, DeadCode> {
+    detect_dead_code(graph)
+        .into_iter()
+        .map(|dead| {
+            let key = if dead.node.symbol_id.is_empty() {
+                format!("{}#{}", dead.node.file_path.display(), dead.node.name)
+            } else {
+                dead.node.symbol_id.clone()
+            };
+            (key, dead)
+        })
+        .collect()
+}
+
+/// Watch handle returned by [`event_stream`]; watching stops when it is dropped
+pub type EventDebouncer = Debouncer<RecommendedWatcher, FileIdMap>;
+
+/// Start watching `directory` recursively. Events arrive on the receiver until
+/// the returned debouncer is dropped.
+pub fn event_stream(
+    directory: &Path,
+    debounce: Duration,
+) -> Result<(EventDebouncer, mpsc::Receiver<DebounceEventResult>)> {
+    let (tx, rx) = mpsc::channel::<DebounceEventResult>();
+    let mut debouncer = new_debouncer(debounce, None, tx)?;
+    debouncer
+        .watcher()
+        .watch(directory, RecursiveMode::Recursive)
+        .context(format!("Failed to watch {}", directory.display()))?;
+    debouncer.cache().add_root(directory, RecursiveMode::Recursive);
+    Ok((debouncer, rx))
+}
+
+/// Paths touched by a batch of debounced events (errors are logged and skipped)
+pub fn event_paths(result: DebounceEventResult) -> Vec<PathBuf> {
+    match result {
+        Ok(events) => events.iter().flat_map(|e| e.paths.iter().cloned()).collect(),
+        Err(errors) => {
+            for error in errors {
+                eprintln!("⚠️  Watch error: {}", error);
+            }
+            Vec::new()
+        }
+    }
+}
+
+/// Watch `directory` until interrupted, keeping the graph and the database at `db_path` current
+pub fn watch(directory: &Path, db_path: &Path, debounce: Duration) -> Result<()> {
+    let mut project = ProjectWatcher::new(directory)?;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let db = runtime.block_on(async {
+        if let Some(parent) = db_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        GraphDB::new(db_path).await
+    })?;
+    let stats = runtime.block_on(db.sync_graph(project.graph()))?;
+    println!(
+        "💾 データベース同期: {} (+{} ~{} -{} nodes)",
+        db_path.display(),
+        stats.nodes_added,
+        stats.nodes_updated,
+        stats.nodes_removed
+    );
+    println!("🗑️  デッドコード: {} 個", project.dead_code_count());
+
+    let (_debouncer, events) = event_stream(directory, debounce)?;
+
+    println!();
+    println!("👀 監視中: {} (Ctrl+C で終了)", directory.display());
+
+    for result in events {
+        let paths = event_paths(result);
+        let Some(changes) = project.classify(&paths) else {
+            continue;
+        };
+
+        println!();
+        match &changes {
+            ChangeSet::Files(files) => {
+                let mut files: Vec<&PathBuf> = files.iter().collect();
+                files.sort();
+                for file in files {
+                    println!("🔄 {}", file.display());
+                }
+            }
+            ChangeSet::Full => println!("🔄 プロジェクト設定が変更されました（全体を再スキャン）"),
+        }
+
+        let delta = match project.apply(&changes) {
+            Ok(delta) => delta,
+            Err(e) => {
+                eprintln!("⚠️  Update failed: {}", e);
+                continue;
+            }
+        };
+        match runtime.block_on(db.sync_graph(project.graph())) {
+            Ok(stats) => println!(
+                "💾 DB: +{} ~{} -{} nodes, +{} -{} edges",
+                stats.nodes_added, stats.nodes_updated, stats.nodes_removed, stats.edges_added, stats.edges_removed
+            ),
+            Err(e) => eprintln!("⚠️  Database update failed: {}", e),
+        }
+
+        for dead in &delta.added {
+            println!(
+                "  ➕ {} ({}:{})",
+                dead.node.name,
+                dead.node.file_path.display(),
+                dead.node.line_range.0
+            );
+        }
+        for dead in &delta.resolved {
+            println!(
+                "  ➖ {} ({}:{})",
+                dead.node.name,
+                dead.node.file_path.display(),
+                dead.node.line_range.0
+            );
+        }
+        println!(
+            "🗑️  デッドコード: {} 個 (+{} / -{})",
+            project.dead_code_count(),
+            delta.added.len(),
+            delta.resolved.len()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_project;
+
+    #[test]
+    fn test_apply_reports_dead_code_delta() {
+        let temp = temp_project();
+        let root = temp.path();
+        std::fs::write(root.join("app.ts"), "function main() {\n  helper();\n}\nfunction helper() {}\n").unwrap();
+
+        let mut project = ProjectWatcher::new(root).unwrap();
+        assert_eq!(project.dead_code_count(), 0);
+
+        // Dropping the call leaves `helper` unused
+        std::fs::write(root.join("app.ts"), "function main() {\n}\nfunction helper() {}\n").unwrap();
+        let changes = project
+            .classify(&[root.canonicalize().unwrap().join("app.ts"), root.join(".index-chan/graph.db")])
+            .unwrap();
+        assert_eq!(changes, ChangeSet::Files(HashSet::from([normalize_path(&root.join("app.ts"))])));
+
+        let delta = project.apply(&changes).unwrap();
+        assert_eq!(delta.added.len(), 1);
+        assert_eq!(delta.added[0].node.name, "helper");
+        assert!(delta.resolved.is_empty());
+
+        assert_eq!(project.classify(&[root.join("tsconfig.json")]), Some(ChangeSet::Full));
+        assert_eq!(project.classify(&[root.join("README.md")]), None);
+    }
+}
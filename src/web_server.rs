@@ -9,37 +9,72 @@ pub mod server {
     };
     use serde::{Deserialize, Serialize};
     use std::net::SocketAddr;
-    use std::sync::Arc;
+    use std::path::PathBuf;
+    use std::sync::{Arc, Mutex};
     use tower_http::cors::CorsLayer;
     use tower_http::services::ServeDir;
     use anyhow::Result;
 
-    use crate::graph::CodeGraph;
+    use crate::daemon::DaemonClient;
+    use crate::graph::{CodeGraph, CodeNode};
     use crate::filter::GraphFilter;
+    use crate::mcp::changes::{ChangeManager, FileChange, PreviewResult};
+
+    /// Graph last fetched from the daemon, with its generation
+    type LiveGraph = Arc<Mutex<Option<(u64, Arc<CodeGraph>)>>>;
 
     #[derive(Clone)]
     pub struct AppState {
         pub graph: Arc<CodeGraph>,
+        pub project_dir: PathBuf,
+        /// Use the live graph of a daemon serving `project_dir`, while one runs
+        pub follow_daemon: bool,
+        live: LiveGraph,
+    }
+
+    impl AppState {
+        /// Daemon's current graph if one is running, otherwise the graph given at startup
+        async fn current_graph(&self) -> Arc<CodeGraph> {
+            if !self.follow_daemon {
+                return Arc::clone(&self.graph);
+            }
+            let dir = self.project_dir.clone();
+            let live = Arc::clone(&self.live);
+            let fetched = tokio::task::spawn_blocking(move || {
+                let mut client = DaemonClient::connect(&dir)?;
+                let generation = client.status().ok()?.generation;
+                let mut live = live.lock().unwrap();
+                match live.as_ref() {
+                    Some((cached, graph)) if *cached == generation => Some(Arc::clone(graph)),
+                    _ => {
+                        let snapshot = client.graph().ok()?;
+                        let graph = Arc::new(snapshot.graph);
+                        *live = Some((snapshot.generation, Arc::clone(&graph)));
+                        Some(graph)
+                    }
+                }
+            })
+            .await
+            .ok()
+            .flatten();
+            fetched.unwrap_or_else(|| Arc::clone(&self.graph))
+        }
     }
 
     pub async fn start_server(
         graph: CodeGraph,
+        project_dir: PathBuf,
+        follow_daemon: bool,
         port: u16,
     ) -> Result<()> {
         let state = AppState {
             graph: Arc::new(graph),
+            project_dir,
+            follow_daemon,
+            live: Arc::new(Mutex::new(None)),
         };
 
-        let app = Router::new()
-            .route("/", get(index_handler))
-            .route("/api/graph", get(graph_handler))
-            .route("/api/filter", post(filter_handler))
-            .route("/api/filter/keywords", get(filter_keywords_handler))
-            .route("/api/filter/dead-code", get(filter_dead_code_handler))
-            .route("/api/filter/file", get(filter_file_handler))
-            .nest_service("/static", ServeDir::new("static"))
-            .layer(CorsLayer::permissive())
-            .with_state(state);
+        let app = router(state);
 
         let addr = SocketAddr::from(([127, 0, 0, 1], port));
         println!("🌐 Webサーバー起動: http://{}", addr);
@@ -52,12 +87,27 @@ pub mod server {
         Ok(())
     }
 
+    fn router(state: AppState) -> Router {
+        Router::new()
+            .route("/", get(index_handler))
+            .route("/api/graph", get(graph_handler))
+            .route("/api/filter", post(filter_handler))
+            .route("/api/filter/keywords", get(filter_keywords_handler))
+            .route("/api/filter/dead-code", get(filter_dead_code_handler))
+            .route("/api/filter/file", get(filter_file_handler))
+            .route("/api/symbol", get(symbol_handler))
+            .route("/api/preview", post(preview_handler))
+            .nest_service("/static", ServeDir::new("static"))
+            .layer(CorsLayer::permissive())
+            .with_state(state)
+    }
+
     async fn index_handler() -> impl IntoResponse {
         Html(include_str!("../static/index.html"))
     }
 
     async fn graph_handler(State(state): State<AppState>) -> impl IntoResponse {
-        Json((*state.graph).clone())
+        Json((*state.current_graph().await).clone())
     }
 
     // Filter request/response types
@@ -66,7 +116,9 @@ pub mod server {
         query: String,
         #[serde(default)]
         include_dependencies: bool,
+        /// Accepted from the UI; LLM filtering is not wired up yet
         #[serde(default)]
+        #[allow(dead_code)]
         use_llm: bool,
     }
 
@@ -92,6 +144,31 @@ pub mod server {
         include_dependencies: bool,
     }
 
+    #[derive(Deserialize)]
+    struct SymbolQuery {
+        /// Symbol id (`rs:src/scanner.rs#Scanner.scan_file`) or function name
+        id: String,
+    }
+
+    #[derive(Serialize)]
+    struct SymbolResponse {
+        node: CodeNode,
+        callers: Vec<CodeNode>,
+        callees: Vec<CodeNode>,
+    }
+
+    #[derive(Deserialize)]
+    struct PreviewQuery {
+        /// Add word-level changes per line
+        #[serde(default)]
+        inline: bool,
+    }
+
+    #[derive(Deserialize)]
+    struct PreviewRequest {
+        changes: Vec<FileChange>,
+    }
+
     #[derive(Deserialize)]
     struct FileQuery {
         file_pattern: String,
@@ -105,6 +182,7 @@ pub mod server {
         Json(req): Json<FilterRequest>,
     ) -> Result<Json<FilterResponse>, (StatusCode, String)> {
         let filter = GraphFilter::new();
+        let graph = state.current_graph().await;
         
         // Parse keywords from query (simple split for now)
         let keywords: Vec<String> = req.query
@@ -113,13 +191,13 @@ pub mod server {
             .collect();
 
         let filtered_graph = filter
-            .filter_by_keywords(&state.graph, &keywords, req.include_dependencies)
+            .filter_by_keywords(&graph, &keywords, req.include_dependencies)
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
         let stats = FilterStats {
-            original_nodes: state.graph.nodes.len(),
+            original_nodes: graph.nodes.len(),
             filtered_nodes: filtered_graph.nodes.len(),
-            original_edges: state.graph.edges.len(),
+            original_edges: graph.edges.len(),
             filtered_edges: filtered_graph.edges.len(),
             filter_query: req.query.clone(),
         };
@@ -136,6 +214,7 @@ pub mod server {
         Query(params): Query<KeywordsQuery>,
     ) -> Result<Json<FilterResponse>, (StatusCode, String)> {
         let filter = GraphFilter::new();
+        let graph = state.current_graph().await;
         
         let keywords: Vec<String> = params.keywords
             .split(',')
@@ -143,13 +222,13 @@ pub mod server {
             .collect();
 
         let filtered_graph = filter
-            .filter_by_keywords(&state.graph, &keywords, params.include_dependencies)
+            .filter_by_keywords(&graph, &keywords, params.include_dependencies)
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
         let stats = FilterStats {
-            original_nodes: state.graph.nodes.len(),
+            original_nodes: graph.nodes.len(),
             filtered_nodes: filtered_graph.nodes.len(),
-            original_edges: state.graph.edges.len(),
+            original_edges: graph.edges.len(),
             filtered_edges: filtered_graph.edges.len(),
             filter_query: params.keywords.clone(),
         };
@@ -165,15 +244,16 @@ pub mod server {
         State(state): State<AppState>,
     ) -> Result<Json<FilterResponse>, (StatusCode, String)> {
         let filter = GraphFilter::new();
+        let graph = state.current_graph().await;
         
         let filtered_graph = filter
-            .filter_dead_code(&state.graph)
+            .filter_dead_code(&graph)
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
         let stats = FilterStats {
-            original_nodes: state.graph.nodes.len(),
+            original_nodes: graph.nodes.len(),
             filtered_nodes: filtered_graph.nodes.len(),
-            original_edges: state.graph.edges.len(),
+            original_edges: graph.edges.len(),
             filtered_edges: filtered_graph.edges.len(),
             filter_query: "dead_code".to_string(),
         };
@@ -190,15 +270,16 @@ pub mod server {
         Query(params): Query<FileQuery>,
     ) -> Result<Json<FilterResponse>, (StatusCode, String)> {
         let filter = GraphFilter::new();
+        let graph = state.current_graph().await;
         
         let filtered_graph = filter
-            .filter_by_file(&state.graph, &params.file_pattern, params.include_dependencies)
+            .filter_by_file(&graph, &params.file_pattern, params.include_dependencies)
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
         let stats = FilterStats {
-            original_nodes: state.graph.nodes.len(),
+            original_nodes: graph.nodes.len(),
             filtered_nodes: filtered_graph.nodes.len(),
-            original_edges: state.graph.edges.len(),
+            original_edges: graph.edges.len(),
             filtered_edges: filtered_graph.edges.len(),
             filter_query: format!("file:{}", params.file_pattern),
         };
@@ -208,4 +289,116 @@ pub mod server {
             stats,
         }))
     }
+
+    // Look up one symbol with its direct callers / callees (GET /api/symbol?id=ts:src/app.ts#main)
+    async fn symbol_handler(
+        State(state): State<AppState>,
+        Query(params): Query<SymbolQuery>,
+    ) -> Result<Json<SymbolResponse>, (StatusCode, String)> {
+        let graph = state.current_graph().await;
+        let node = match graph.find_by_symbol_id(&params.id) {
+            Some(node) => node,
+            None => {
+                // A plain name must name exactly one symbol
+                let matches: Vec<&CodeNode> = graph.nodes_by_name(&params.id).collect();
+                match matches.as_slice() {
+                    [] => return Err((StatusCode::NOT_FOUND, format!("Symbol not found: {}", params.id))),
+                    [node] => *node,
+                    _ => {
+                        let ids: Vec<&str> = matches.iter().map(|n| n.symbol_id.as_str()).collect();
+                        return Err((
+                            StatusCode::CONFLICT,
+                            format!("Ambiguous symbol name {}; use one of: {}", params.id, ids.join(", ")),
+                        ));
+                    }
+                }
+            }
+        };
+
+        let callers = graph
+            .incoming_edges(node.id)
+            .filter_map(|e| graph.nodes.get(&e.from).cloned())
+            .collect();
+        let callees = graph
+            .outgoing_edges(node.id)
+            .filter_map(|e| graph.nodes.get(&e.to).cloned())
+            .collect();
+
+        Ok(Json(SymbolResponse {
+            node: node.clone(),
+            callers,
+            callees,
+        }))
+    }
+
+    // Diff proposed changes against the project files (POST /api/preview?inline=true)
+    async fn preview_handler(
+        State(state): State<AppState>,
+        Query(params): Query<PreviewQuery>,
+        Json(req): Json<PreviewRequest>,
+    ) -> Result<Json<PreviewResult>, (StatusCode, String)> {
+        let dir = state.project_dir.clone();
+        let preview = tokio::task::spawn_blocking(move || {
+            ChangeManager::from_directory(&dir)?.preview_changes(&req.changes, params.inline)
+        })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        Ok(Json(preview))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::test_support::temp_project;
+        use serde_json::{json, Value};
+
+        async fn start(project_dir: PathBuf) -> String {
+            let state = AppState {
+                graph: Arc::new(CodeGraph::new()),
+                project_dir,
+                follow_daemon: false,
+                live: Arc::new(Mutex::new(None)),
+            };
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, router(state)).await });
+            url
+        }
+
+        #[tokio::test]
+        async fn test_preview_adds_inline_segments_when_asked() {
+            let temp = temp_project();
+            std::fs::write(temp.path().join("app.ts"), "let x = foo(1);\nkeep();\n").unwrap();
+            let url = start(temp.path().to_path_buf()).await;
+            let client = reqwest::Client::new();
+            let body = json!({
+                "changes": [{ "file_path": "app.ts", "modified_content": "let x = bar(1);", "start_line": 1, "end_line": 1 }]
+            });
+
+            let response = client.post(format!("{}/api/preview?inline=true", url)).json(&body).send().await.unwrap();
+            assert_eq!(response.status(), 200);
+            let preview: Value = response.json().await.unwrap();
+            let diff = &preview["diffs"][0];
+            assert_eq!(diff["file_path"], "app.ts");
+            assert_eq!((&preview["total_additions"], &preview["total_deletions"]), (&json!(1), &json!(1)));
+            let inline = diff["inline"].as_array().unwrap();
+            assert_eq!(inline.len(), 1);
+            assert_eq!((&inline[0]["old_line"], &inline[0]["new_line"]), (&json!(1), &json!(1)));
+            assert_eq!(
+                inline[0]["segments"],
+                json!([
+                    { "kind": "equal", "text": "let x = " },
+                    { "kind": "delete", "text": "foo" },
+                    { "kind": "insert", "text": "bar" },
+                    { "kind": "equal", "text": "(1);" }
+                ])
+            );
+
+            // Without the flag only the unified diff is sent
+            let plain: Value = client.post(format!("{}/api/preview", url)).json(&body).send().await.unwrap().json().await.unwrap();
+            assert!(plain["diffs"][0].get("inline").is_none());
+            assert!(plain["diffs"][0]["diff"].as_str().unwrap().contains("+let x = bar(1);"));
+        }
+    }
 }
@@ -0,0 +1,199 @@
+// Workspace packages
+// package.json workspaces / pnpm-workspace.yaml / Cargo workspace members からパッケージ境界を検出する
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::resolver::{normalize_path, read_jsonc};
+use crate::rust_modules::{expand_glob, read_manifest, workspace_manifests};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PackageKind {
+    Npm,
+    Cargo,
+}
+
+/// One package of a workspace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    /// `name` from package.json / Cargo.toml
+    pub name: String,
+    /// Directory holding the manifest
+    pub root: PathBuf,
+    pub kind: PackageKind,
+}
+
+/// Packages found under a scan root
+#[derive(Debug, Clone, Default)]
+pub struct Workspace {
+    /// Deepest roots first, so the first match is the innermost package
+    packages: Vec<Package>,
+}
+
+impl Workspace {
+    /// Detect npm/pnpm/yarn workspace packages and Cargo workspace members for `root`.
+    /// The root manifests count as packages too, so single-package projects get a name.
+    pub fn detect(root: &Path) -> Self {
+        let mut packages = Vec::new();
+        let mut add = |package: Package| {
+            if !packages.iter().any(|p: &Package| p.root == package.root && p.kind == package.kind) {
+                packages.push(package);
+            }
+        };
+
+        // npm / pnpm / yarn
+        let npm_root = root
+            .ancestors()
+            .find(|dir| dir.join("package.json").is_file() || dir.join("pnpm-workspace.yaml").is_file());
+        if let Some(npm_root) = npm_root {
+            let manifest = read_jsonc(&npm_root.join("package.json"));
+            let mut patterns: Vec<String> = manifest
+                .as_ref()
+                .and_then(|m| m.get("workspaces"))
+                .map(|w| w.get("packages").unwrap_or(w))
+                .and_then(|w| w.as_array())
+                .map(|a| a.iter().filter_map(|v| v.as_str().map(String::from)).collect())
+                .unwrap_or_default();
+            patterns.extend(pnpm_workspace_patterns(&npm_root.join("pnpm-workspace.yaml")));
+
+            let (excluded, included): (Vec<String>, Vec<String>) =
+                patterns.into_iter().partition(|p| p.starts_with('!'));
+            let excluded: Vec<PathBuf> = excluded
+                .iter()
+                .flat_map(|p| expand_glob(npm_root, &p[1..]))
+                .map(|d| normalize_path(&d))
+                .collect();
+
+            let mut dirs = vec![npm_root.to_path_buf()];
+            dirs.extend(included.iter().flat_map(|p| expand_glob(npm_root, p)));
+            for dir in dirs {
+                let dir = normalize_path(&dir);
+                if excluded.contains(&dir) {
+                    continue;
+                }
+                let name = read_jsonc(&dir.join("package.json"))
+                    .and_then(|m| m.get("name").and_then(|n| n.as_str()).map(String::from));
+                if let Some(name) = name {
+                    add(Package { name, root: dir, kind: PackageKind::Npm });
+                }
+            }
+        }
+
+        // Cargo
+        let cargo_manifest = root.ancestors().map(|dir| dir.join("Cargo.toml")).find(|p| p.is_file());
+        if let Some(manifest) = cargo_manifest {
+            for member in workspace_manifests(&manifest) {
+                let name = read_manifest(&member).and_then(|value| {
+                    value
+                        .get("package")
+                        .and_then(|p| p.get("name"))
+                        .and_then(|n| n.as_str())
+                        .map(String::from)
+                });
+                if let Some(name) = name {
+                    let dir = normalize_path(member.parent().unwrap_or(Path::new("")));
+                    add(Package { name, root: dir, kind: PackageKind::Cargo });
+                }
+            }
+        }
+
+        packages.sort_by_key(|p| std::cmp::Reverse(p.root.components().count()));
+        Self { packages }
+    }
+
+    pub fn packages(&self) -> &[Package] {
+        &self.packages
+    }
+
+    /// Innermost package containing `file` whose kind matches the file's language
+    /// (a crate for `.rs`, an npm package otherwise); any kind if none matches
+    pub fn package_for(&self, file: &Path) -> Option<&Package> {
+        let file = normalize_path(file);
+        let kind = match file.extension().and_then(|e| e.to_str()) {
+            Some("rs") => PackageKind::Cargo,
+            _ => PackageKind::Npm,
+        };
+        let mut containing = self.packages.iter().filter(|p| file.starts_with(&p.root));
+        containing.clone().find(|p| p.kind == kind).or_else(|| containing.next())
+    }
+
+    /// npm packages by name, for resolving `import ... from "@scope/pkg"`
+    pub fn npm_packages(&self) -> impl Iterator<Item = &Package> {
+        self.packages.iter().filter(|p| p.kind == PackageKind::Npm)
+    }
+}
+
+/// `packages:` entries of pnpm-workspace.yaml (a flat list is all pnpm supports there)
+fn pnpm_workspace_patterns(path: &Path) -> Vec<String> {
+    let Ok(content) = std::fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let mut patterns = Vec::new();
+    let mut in_packages = false;
+    for line in content.lines() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(' ') && !line.starts_with('-') {
+            in_packages = trimmed == "packages:";
+            continue;
+        }
+        if let Some(item) = trimmed.strip_prefix('-').filter(|_| in_packages) {
+            let item = item.split(" #").next().unwrap_or(item).trim();
+            patterns.push(item.trim_matches(|c| c == '"' || c == '\'').to_string());
+        }
+    }
+    patterns
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{temp_project, write};
+
+    #[test]
+    fn test_detect_pnpm_and_cargo_packages() {
+        let temp = temp_project();
+        let root = temp.path();
+        write(root, "package.json", r#"{ "name": "monorepo", "private": true }"#);
+        write(root, "pnpm-workspace.yaml", "packages:\n  - 'packages/*'\n  - \"!packages/legacy\"\n");
+        write(root, "packages/ui/package.json", r#"{ "name": "@acme/ui" }"#);
+        write(root, "packages/utils/package.json", r#"{ "name": "@acme/utils" }"#);
+        write(root, "packages/legacy/package.json", r#"{ "name": "@acme/legacy" }"#);
+        write(root, "Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\n");
+        write(root, "crates/core/Cargo.toml", "[package]\nname = \"acme-core\"\n");
+
+        let workspace = Workspace::detect(root);
+        let mut names: Vec<&str> = workspace.packages().iter().map(|p| p.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["@acme/ui", "@acme/utils", "acme-core", "monorepo"]);
+
+        let owner = |rel: &str| workspace.package_for(&root.join(rel)).map(|p| p.name.clone());
+        assert_eq!(owner("packages/ui/src/button.ts").as_deref(), Some("@acme/ui"));
+        assert_eq!(owner("crates/core/src/lib.rs").as_deref(), Some("acme-core"));
+        assert_eq!(owner("scripts/build.ts").as_deref(), Some("monorepo"));
+        assert_eq!(owner("packages/legacy/index.ts").as_deref(), Some("monorepo"));
+    }
+
+    #[test]
+    fn test_package_kind_follows_the_file_language() {
+        let temp = temp_project();
+        let root = temp.path();
+        // A napi crate: Cargo.toml and package.json in the same directory
+        write(root, "Cargo.toml", "[package]\nname = \"native-core\"\n");
+        write(root, "package.json", r#"{ "name": "@acme/native" }"#);
+
+        let workspace = Workspace::detect(root);
+        let owner = |rel: &str| workspace.package_for(&root.join(rel)).map(|p| p.name.clone());
+        assert_eq!(owner("src/lib.rs").as_deref(), Some("native-core"));
+        assert_eq!(owner("index.ts").as_deref(), Some("@acme/native"));
+
+        // No npm package at all: TypeScript files fall back to the crate
+        let temp = temp_project();
+        write(temp.path(), "Cargo.toml", "[package]\nname = \"only-crate\"\n");
+        let workspace = Workspace::detect(temp.path());
+        let owner = workspace.package_for(&temp.path().join("web/app.ts")).map(|p| p.name.clone());
+        assert_eq!(owner.as_deref(), Some("only-crate"));
+    }
+}